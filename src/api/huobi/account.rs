@@ -1,30 +1,197 @@
 use super::{
     client::Client,
+    error::{BatchItemError, HuobiError, OrderError},
     models::{
-        APIResult, AccountsResult, Asset, BalanceResult, CancelOrderResult,
-        CancelOrderWithIdResult, ExchangeApiResult, OpenOrdersResult, PlaceOrderResult,
-        TradeHistoryResult, Transaction,
+        self, APIResult, AccountHistoryQuery, AccountHistoryResult, AccountState, AccountType,
+        AccountsResult, AlgoOrderResult, Amount, Asset, AutoPlaceOrderResult, BalanceResult,
+        BatchCancelResult, BatchOrderItem, BatchOrdersResult, CancelAllAfter, CancelAllAfterResult,
+        CancelOpenOrdersData, CancelOpenOrdersResult, CancelOrderResult, CancelOrderWithIdResult,
+        Endpoint, ExchangeApiResult, FeeRateResult, LedgerEntry, LinkedOrders, MatchQuery,
+        MatchResult, NewOrder, OpenOrder, OpenOrdersResult, OrderDetailResult, OrderEndpoint,
+        OrderId, OrderState, PlaceOrderResult, StopOrder, StreamedList, Symbol, TradeHistory,
+        TradeHistoryResult, Transaction, TransferRecord, TransferRecordsResult, VipInfo,
+        WithdrawResult,
     },
+    symbol_cache::SymbolCache,
 };
-use serde_json;
-use std::collections::BTreeMap;
+use chrono::{DateTime, Utc};
+use rust_decimal::prelude::{FromPrimitive, ToPrimitive};
+use rust_decimal::{Decimal, RoundingStrategy};
+use serde_json::{self, json};
+use std::collections::{BTreeMap, HashSet};
+use std::error::Error;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
 use tracing::{debug, error};
 
+const CANCEL_CONFIRM_ATTEMPTS: usize = 10;
+const CANCEL_CONFIRM_INTERVAL: Duration = Duration::from_millis(200);
+const PLACE_RETRY_INTERVAL: Duration = Duration::from_millis(500);
+const CANCEL_ALL_MAX_REQUESTS: usize = 100;
+pub(crate) const ORDER_LOOKUP_CONCURRENCY: usize = 4;
+// The history starts at the id but is not guaranteed to be contiguous
+const WITHDRAW_LOOKUP_SIZE: usize = 10;
+// Account types missing from the listed accounts trigger at most one listing per interval
+const ACCOUNTS_REFRESH_INTERVAL: Duration = Duration::from_secs(10);
+
 #[derive(Clone)]
 pub struct Account {
     // api_key: Option<String>,
     // secret_key: Option<String>,
+    // Empty to use the spot account, which is looked up once on first use
     pub account_id: String,
     pub client: Client,
+    pub order_endpoint: OrderEndpoint,
+    // When set, withdrawals to other addresses are rejected before sending
+    withdraw_allowlist: Option<Arc<HashSet<String>>>,
+    // Used to tell the allowed precision in PrecisionExceeded errors
+    symbol_cache: Option<Arc<SymbolCache>>,
+    // Accounts of the key, listed on first use and shared by the clones of the Account
+    accounts: Arc<Mutex<Option<ListedAccounts>>>,
+}
+
+struct ListedAccounts {
+    accounts: Vec<models::Account>,
+    listed_at: Instant,
 }
+
 impl Account {
     pub fn new(account_id: &str, api_key: Option<String>, secret_key: Option<String>) -> Self {
         Account {
             account_id: account_id.to_string(),
             client: Client::new(&api_key.unwrap(), &secret_key.unwrap()),
+            order_endpoint: OrderEndpoint::Place,
+            withdraw_allowlist: None,
+            symbol_cache: None,
+            accounts: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    // Account of the spot account of the key, its id is looked up on first use
+    pub fn discover(api_key: Option<String>, secret_key: Option<String>) -> Self {
+        Account::new("", api_key, secret_key)
+    }
+
+    // The account_id given to new, or else the id of the working spot account
+    pub fn account_id(&self) -> APIResult<String> {
+        if !self.account_id.is_empty() {
+            return Ok(self.account_id.clone());
+        }
+
+        Ok(self.get_account_id(AccountType::Spot)?.to_string())
+    }
+
+    // Id of the working account of that type, from the accounts listed on first use.
+    // They are listed again when the type is missing, e.g. for an isolated margin
+    // account opened since
+    pub fn get_account_id(&self, account_type: AccountType) -> APIResult<u64> {
+        let is_due = match &*self.accounts.lock().unwrap() {
+            Some(listed) => match find_account_id(&listed.accounts, &account_type) {
+                Some(account_id) => return Ok(account_id),
+                None => listed.listed_at.elapsed() >= ACCOUNTS_REFRESH_INTERVAL,
+            },
+            None => true,
+        };
+
+        let mut account_id = None;
+
+        // Not locked meanwhile, two threads may both list the accounts
+        if is_due {
+            let accounts = self.get_accounts()?.data;
+            self.client
+                .log_policy()
+                .event(format_args!("[Huobi] Listed {} accounts", accounts.len()));
+
+            account_id = find_account_id(&accounts, &account_type);
+
+            *self.accounts.lock().unwrap() = Some(ListedAccounts {
+                accounts,
+                listed_at: Instant::now(),
+            });
+        }
+
+        account_id.ok_or_else(|| {
+            let why = match account_type {
+                AccountType::Spot => format!(
+                    "no working {} account, give the account id explicitly",
+                    account_type
+                ),
+                _ => format!(
+                    "no working {} account, it is not opened or it is locked",
+                    account_type
+                ),
+            };
+
+            Box::new(HuobiError::ApiError(why)) as Box<dyn Error>
+        })
+    }
+
+    pub fn with_symbol_cache(mut self, symbol_cache: Arc<SymbolCache>) -> Self {
+        self.symbol_cache = Some(symbol_cache);
+        self
+    }
+
+    // Adds the precision of the cached symbol to a PrecisionExceeded error
+    fn with_allowed_precision(&self, e: Box<dyn Error>, symbol: &str) -> Box<dyn Error> {
+        let cache = match &self.symbol_cache {
+            Some(cache) => cache,
+            None => return e,
+        };
+
+        let field = match e.downcast_ref() {
+            Some(OrderError::PrecisionExceeded {
+                field,
+                allowed: None,
+            }) => field.clone(),
+            _ => return e,
+        };
+
+        let allowed = if field == "price" {
+            cache.price_precision(symbol)
+        } else {
+            cache.amount_precision(symbol)
+        };
+
+        match allowed {
+            Ok(allowed) => Box::new(OrderError::PrecisionExceeded {
+                field,
+                allowed: Some(allowed),
+            }),
+            Err(_) => e,
+        }
+    }
+
+    // Defense in depth against a leaked key: only these addresses can be withdrawn to
+    pub fn with_withdraw_allowlist<I, S>(mut self, addresses: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.withdraw_allowlist = Some(Arc::new(
+            addresses
+                .into_iter()
+                .map(|address| address.into())
+                .collect(),
+        ));
+        self
+    }
+
+    fn check_withdraw_address(&self, address: &str) -> Result<(), HuobiError> {
+        match &self.withdraw_allowlist {
+            Some(allowlist) if !allowlist.contains(address) => Err(HuobiError::InvalidWithdraw(
+                format!("address {} is not in the withdraw allowlist", address),
+            )),
+            _ => Ok(()),
         }
     }
 
+    pub fn with_order_endpoint(mut self, order_endpoint: OrderEndpoint) -> Self {
+        self.order_endpoint = order_endpoint;
+        self
+    }
+
     pub fn get_open_orders(
         &self,
         symbol: &str,
@@ -35,18 +202,29 @@ impl Account {
     ) -> APIResult<OpenOrdersResult> {
         let mut params: BTreeMap<String, String> = BTreeMap::new();
 
-        params.insert("account-id".into(), self.account_id.clone().into());
+        params.insert("account-id".into(), self.account_id()?);
         params.insert("symbol".into(), symbol.to_lowercase().into());
 
-        let data = self.client.get_signed("/v1/order/openOrders", params)?;
-
-        debug!("[Huobi] Get open orders {:?}", data);
+        let data = self.client.get_signed(Endpoint::OpenOrders, params)?;
 
         let open_orders: OpenOrdersResult = serde_json::from_str(data.as_str())?;
 
         Ok(open_orders)
     }
 
+    // Open orders per symbol, to stay under the open order cap of Huobi
+    // (account-orders-limit), which the API does not report. Reads every page
+    pub fn get_open_order_counts(
+        &self,
+        symbol: Option<&str>,
+    ) -> APIResult<BTreeMap<String, usize>> {
+        let orders = self
+            .iter_open_orders(symbol)
+            .collect::<APIResult<Vec<OpenOrder>>>()?;
+
+        Ok(count_by_symbol(&orders))
+    }
+
     pub fn limit_buy(
         &self,
         symbol: &str,
@@ -54,14 +232,7 @@ impl Account {
         price: f64,
         client_order_id: Option<String>,
     ) -> ExchangeApiResult<Transaction> {
-        match self._place_order(
-            &self.account_id,
-            amount,
-            Some(price),
-            symbol,
-            "buy-limit",
-            client_order_id,
-        ) {
+        match self._place_order(amount, Some(price), symbol, "buy-limit", client_order_id) {
             Ok(placed_order) => {
                 debug!(
                     "[Huobi API] Limit buy: symbol {}, amount {}, price {}",
@@ -70,7 +241,7 @@ impl Account {
 
                 Ok(Transaction {
                     symbol: symbol.to_string(),
-                    order_id: placed_order.data.parse().unwrap(),
+                    order_id: placed_order.data,
                 })
             }
             Err(e) => {
@@ -89,14 +260,7 @@ impl Account {
         price: f64,
         client_order_id: Option<String>,
     ) -> ExchangeApiResult<Transaction> {
-        match self._place_order(
-            &self.account_id,
-            amount,
-            Some(price),
-            symbol,
-            "sell-limit",
-            client_order_id,
-        ) {
+        match self._place_order(amount, Some(price), symbol, "sell-limit", client_order_id) {
             Ok(placed_order) => {
                 debug!(
                     "[Huobi API] Limit sell: symbol {}, amount {}, price {}",
@@ -105,15 +269,16 @@ impl Account {
 
                 Ok(Transaction {
                     symbol: symbol.to_string(),
-                    order_id: placed_order.data.parse().unwrap(),
+                    order_id: placed_order.data,
                 })
             }
             Err(_) => Err("Huobi Limit sell Error"),
         }
     }
 
+    // Huobi treats the amount of a buy-market order as the quote currency to spend
     pub fn market_buy(&self, symbol: &str, amount: f64) -> ExchangeApiResult<Transaction> {
-        match self._place_order(&self.account_id, amount, None, symbol, "buy-market", None) {
+        match self._place_order(amount, None, symbol, "buy-market", None) {
             Ok(placed_order) => {
                 debug!(
                     "[Huobi Huobi] Market buy: symbol {}, amount {}",
@@ -122,7 +287,7 @@ impl Account {
 
                 Ok(Transaction {
                     symbol: symbol.to_string(),
-                    order_id: placed_order.data.parse().unwrap(),
+                    order_id: placed_order.data,
                 })
             }
             Err(_) => Err("Huobi Market buy Error"),
@@ -130,7 +295,7 @@ impl Account {
     }
 
     pub fn market_sell(&self, symbol: &str, amount: f64) -> ExchangeApiResult<Transaction> {
-        match self._place_order(&self.account_id, amount, None, symbol, "sell-market", None) {
+        match self._place_order(amount, None, symbol, "sell-market", None) {
             Ok(placed_order) => {
                 debug!(
                     "[Huobi API] Market sell: symbol {}, amount {}",
@@ -139,26 +304,147 @@ impl Account {
 
                 Ok(Transaction {
                     symbol: symbol.to_string(),
-                    order_id: placed_order.data.parse().unwrap(),
+                    order_id: placed_order.data,
                 })
             }
             Err(_) => Err("Huobi Market sell Error"),
         }
     }
 
+    pub fn place_order(&self, order: &NewOrder) -> APIResult<Transaction> {
+        order.validate()?;
+
+        let placed_order = self
+            ._post_order(&order.to_body(&self.account_id()?))
+            .map_err(|e| self.with_allowed_precision(e, &order.symbol))?;
+
+        debug!(
+            "[Huobi API] Place order: symbol {}, type {}, amount {:?}, price {:?}",
+            order.symbol,
+            order.wire_type(),
+            order.amount,
+            order.price
+        );
+
+        Ok(Transaction {
+            symbol: order.symbol.clone(),
+            order_id: placed_order.data,
+        })
+    }
+
+    // Same as place_order, but first checks that the available balance covers the order.
+    // Costs a symbol and a balance request
+    pub fn place_order_checked(&self, order: &NewOrder) -> APIResult<Transaction> {
+        order.validate()?;
+
+        let symbol_info = self.client.get_symbol(&order.symbol)?;
+
+        let needed = order.needed(&symbol_info);
+        let available = self.get_available_balance(&needed.currency)?;
+
+        if needed.amount > available {
+            return Err(Box::new(OrderError::InsufficientBalance {
+                currency: needed.currency,
+                needed: needed.amount,
+                available,
+            }));
+        }
+
+        self.place_order(order)
+    }
+
+    // Huobi has no linked orders on spot, so this is not atomic: the entry is placed first,
+    // then the stop. If the stop is rejected the entry is canceled, but it may already be
+    // (partially) filled by then
+    pub fn place_order_with_stop(
+        &self,
+        entry: &NewOrder,
+        stop: &StopOrder,
+    ) -> APIResult<LinkedOrders> {
+        let transaction = self.place_order(entry)?;
+
+        let side = if entry.order_type.is_buy() {
+            "sell"
+        } else {
+            "buy"
+        };
+
+        match self._place_algo_order(&entry.symbol, side, stop) {
+            Ok(stop_client_order_id) => Ok(LinkedOrders {
+                entry: transaction,
+                stop_client_order_id,
+            }),
+            Err(e) => {
+                error!(
+                    "[Huobi] Stop order was rejected, cancel entry order {}: {}",
+                    transaction.order_id, e
+                );
+
+                match self.cancel_order(&entry.symbol, transaction.order_id) {
+                    Ok(_) => Err(e),
+                    Err(cancel_error) => Err(Box::new(HuobiError::ApiError(format!(
+                        "stop order failed: {}, entry order {} is still open: {}",
+                        e, transaction.order_id, cancel_error
+                    )))),
+                }
+            }
+        }
+    }
+
+    fn _place_algo_order(&self, symbol: &str, side: &str, stop: &StopOrder) -> APIResult<String> {
+        let params: BTreeMap<String, String> = BTreeMap::new();
+        let mut body: BTreeMap<String, String> = BTreeMap::new();
+
+        body.insert("accountId".into(), self.account_id()?);
+        body.insert("symbol".into(), symbol.to_lowercase());
+        body.insert("orderSide".into(), side.into());
+        body.insert("stopPrice".into(), stop.stop_price.to_string());
+        body.insert("clientOrderId".into(), stop.client_order_id.clone());
+
+        match stop.order_price {
+            Some(price) => {
+                body.insert("orderType".into(), "limit".into());
+                body.insert("orderPrice".into(), price.to_string());
+                body.insert("orderSize".into(), stop.amount.to_string());
+            }
+            // Like buy-market, a market buy algo order is sized in quote currency
+            None if side == "buy" => {
+                body.insert("orderType".into(), "market".into());
+                body.insert("orderValue".into(), stop.amount.to_string());
+            }
+            None => {
+                body.insert("orderType".into(), "market".into());
+                body.insert("orderSize".into(), stop.amount.to_string());
+            }
+        }
+
+        let data = self
+            .client
+            .post_signed(Endpoint::AlgoOrders, params, &body)?;
+
+        let algo_order: AlgoOrderResult = serde_json::from_str(data.as_str())?;
+
+        match algo_order.data {
+            Some(algo_order_data) if algo_order.code == 200 => Ok(algo_order_data.client_order_id),
+            _ => Err(Box::new(HuobiError::ApiError(format!(
+                "algo order error {}: {}",
+                algo_order.code,
+                algo_order.message.unwrap_or_default()
+            )))),
+        }
+    }
+
     fn _place_order(
         &self,
-        account_id: &str,
         amount: f64,
         price: Option<f64>,
         symbol: &str,
         type_: &str,
         client_order_id: Option<String>,
     ) -> APIResult<PlaceOrderResult> {
-        let params: BTreeMap<String, String> = BTreeMap::new();
         let mut body: BTreeMap<String, String> = BTreeMap::new();
 
-        body.insert("account-id".into(), account_id.into());
+        body.insert("account-id".into(), self.account_id()?);
         body.insert("amount".into(), amount.to_string());
         body.insert("price".into(), price.unwrap_or(0.).to_string());
         body.insert("source".into(), "api".into());
@@ -169,38 +455,438 @@ impl Account {
             body.insert("client-order-id".into(), id.into());
         }
 
+        self._post_order(&body)
+    }
+
+    fn _post_order(&self, body: &BTreeMap<String, String>) -> APIResult<PlaceOrderResult> {
+        let params: BTreeMap<String, String> = BTreeMap::new();
+
+        match self.order_endpoint {
+            OrderEndpoint::Place => {
+                let data = self
+                    .client
+                    .post_signed(self.order_endpoint.path(), params, body);
+
+                // debug!("[Huobi] Place order result: {:?} ", data?);
+
+                let order: PlaceOrderResult = serde_json::from_str(data?.as_str())?;
+
+                Ok(order)
+            }
+            OrderEndpoint::AutoPlace(trade_purpose) => {
+                let mut body = body.clone();
+                body.insert("trade-purpose".into(), trade_purpose.as_str().into());
+
+                let data = self
+                    .client
+                    .post_signed(self.order_endpoint.path(), params, &body)?;
+
+                let order: AutoPlaceOrderResult = serde_json::from_str(data.as_str())?;
+
+                Ok(PlaceOrderResult {
+                    data: OrderId(order.data.order_id),
+                })
+            }
+        }
+    }
+
+    // One result per order, in the same order: the order id or why it was not placed.
+    // Invalid orders are not sent, batches of more than 10 orders are sent 10 at a time.
+    // When the request of a batch fails its orders get a request-failed error
+    // and the next batches are still sent
+    pub fn place_orders(
+        &self,
+        orders: &[NewOrder],
+    ) -> APIResult<Vec<Result<OrderId, BatchItemError>>> {
+        let account_id = self.account_id()?;
+        let mut results = Vec::with_capacity(orders.len());
+
+        for chunk in orders.chunks(10) {
+            let mut chunk_results: Vec<Option<Result<OrderId, BatchItemError>>> = vec![];
+            let mut bodies = vec![];
+
+            for order in chunk {
+                match order.validate() {
+                    Ok(()) => {
+                        bodies.push(order.to_body(&account_id));
+                        chunk_results.push(None);
+                    }
+                    Err(e) => chunk_results.push(Some(Err(BatchItemError {
+                        order_id: None,
+                        client_order_id: order.client_order_id.clone(),
+                        err_code: "invalid-order".into(),
+                        err_msg: e.to_string(),
+                    }))),
+                }
+            }
+
+            let sent = if bodies.is_empty() {
+                Ok(vec![])
+            } else {
+                self.post_batch_orders(&bodies)
+            };
+
+            let (mut items, failed): (Vec<Option<BatchOrderItem>>, _) = match sent {
+                Ok(items) => (items.into_iter().map(Some).collect(), None),
+                Err(e) => (vec![], Some(e)),
+            };
+
+            for (order, result) in chunk.iter().zip(chunk_results) {
+                results.push(match (result, &failed) {
+                    (Some(result), _) => result,
+                    (None, Some(e)) => Err(request_failed(
+                        None,
+                        order.client_order_id.clone(),
+                        e.as_ref(),
+                    )),
+                    (None, None) => {
+                        match take_batch_item(&mut items, order.client_order_id.as_deref()) {
+                            Some(item) => batch_item_result(item),
+                            None => Err(BatchItemError {
+                                order_id: None,
+                                client_order_id: order.client_order_id.clone(),
+                                err_code: "missing-result".into(),
+                                err_msg: "order is not in the batch response".into(),
+                            }),
+                        }
+                    }
+                });
+            }
+        }
+
+        Ok(results)
+    }
+
+    fn post_batch_orders(
+        &self,
+        bodies: &[BTreeMap<String, String>],
+    ) -> APIResult<Vec<BatchOrderItem>> {
+        let params: BTreeMap<String, String> = BTreeMap::new();
+
         let data = self
             .client
-            .post_signed("/v1/order/orders/place", params, &body);
+            .post_signed(Endpoint::BatchOrders, params, bodies)?;
 
-        // debug!("[Huobi] Place order result: {:?} ", data?);
+        let batch: BatchOrdersResult = serde_json::from_str(data.as_str())?;
 
-        let order: PlaceOrderResult = serde_json::from_str(data?.as_str())?;
+        Ok(batch.data)
+    }
 
-        Ok(order)
+    // One result per order id, in the same order, 50 order ids per request.
+    // When a request fails its order ids get a request-failed error
+    pub fn cancel_orders(
+        &self,
+        order_ids: &[OrderId],
+    ) -> APIResult<Vec<Result<OrderId, BatchItemError>>> {
+        let mut results = Vec::with_capacity(order_ids.len());
+
+        for chunk in order_ids.chunks(50) {
+            let params: BTreeMap<String, String> = BTreeMap::new();
+            let body = json!({
+                "order-ids": chunk.iter().map(|order_id| order_id.to_string()).collect::<Vec<_>>()
+            });
+
+            let batch = match self.post_batch_cancel(params, &body) {
+                Ok(batch) => batch,
+                Err(e) => {
+                    for order_id in chunk {
+                        results.push(Err(request_failed(Some(*order_id), None, e.as_ref())));
+                    }
+                    continue;
+                }
+            };
+
+            for order_id in chunk {
+                let failed = batch
+                    .data
+                    .failed
+                    .iter()
+                    .find(|item| item.order_id == Some(*order_id));
+
+                results.push(match failed {
+                    Some(item) => batch_item_result(item.clone()),
+                    None if batch.data.success.contains(&order_id.to_string()) => Ok(*order_id),
+                    None => Err(BatchItemError {
+                        order_id: Some(*order_id),
+                        client_order_id: None,
+                        err_code: "missing-result".into(),
+                        err_msg: "order is not in the batch response".into(),
+                    }),
+                });
+            }
+        }
+
+        Ok(results)
     }
 
-    pub fn get_accounts(&self) -> APIResult<AccountsResult> {
+    fn post_batch_cancel(
+        &self,
+        params: BTreeMap<String, String>,
+        body: &serde_json::Value,
+    ) -> APIResult<BatchCancelResult> {
+        let data = self
+            .client
+            .post_signed(Endpoint::BatchCancel, params, body)?;
+
+        Ok(serde_json::from_str(data.as_str())?)
+    }
+
+    // Cancels the open orders of the symbol created before the cutoff, e.g. stale quotes.
+    // One result per canceled order id, every page of open orders is read first
+    pub fn cancel_orders_before(
+        &self,
+        symbol: &str,
+        before: DateTime<Utc>,
+    ) -> APIResult<Vec<Result<OrderId, BatchItemError>>> {
+        let open_orders = self
+            .iter_open_orders(Some(symbol))
+            .collect::<APIResult<Vec<OpenOrder>>>()?;
+        let order_ids = created_before(&open_orders, before);
+
+        if order_ids.is_empty() {
+            return Ok(vec![]);
+        }
+
+        self.client.log_policy().event(format_args!(
+            "[Huobi] Cancel {} orders of {} created before {}",
+            order_ids.len(),
+            symbol,
+            before
+        ));
+
+        self.cancel_orders(&order_ids)
+    }
+
+    // Cancels the open orders of the account, of one symbol only when it is given.
+    // Huobi cancels up to 100 orders per request, requests are repeated until none is left
+    pub fn cancel_all_open_orders(&self, symbol: Option<&str>) -> APIResult<CancelOpenOrdersData> {
+        let account_id = self.account_id()?;
+        let mut total = CancelOpenOrdersData::default();
+
+        for _ in 0..CANCEL_ALL_MAX_REQUESTS {
+            let params: BTreeMap<String, String> = BTreeMap::new();
+            let mut body: BTreeMap<String, String> = BTreeMap::new();
+
+            body.insert("account-id".into(), account_id.clone());
+
+            if let Some(symbol) = symbol {
+                body.insert("symbol".into(), symbol.to_lowercase());
+            }
+
+            let data = self
+                .client
+                .post_signed(Endpoint::BatchCancelOpenOrders, params, &body)?;
+
+            let canceled: CancelOpenOrdersResult = serde_json::from_str(data.as_str())?;
+
+            total.success_count += canceled.data.success_count;
+            total.failed_count += canceled.data.failed_count;
+            total.next_id = canceled.data.next_id;
+
+            if canceled.data.next_id == -1 || canceled.data.success_count == 0 {
+                break;
+            }
+        }
+
+        Ok(total)
+    }
+
+    pub fn get_order(&self, order_id: OrderId) -> APIResult<TradeHistory> {
+        let params: BTreeMap<String, String> = BTreeMap::new();
+
+        let data = self
+            .client
+            .get_signed(Endpoint::Order(order_id.get()), params)?;
+
+        let order: OrderDetailResult = serde_json::from_str(data.as_str())?;
+
+        Ok(order.data)
+    }
+
+    // Huobi has no multi-id order lookup, so the orders are fetched one by one on a few
+    // threads paced by the rate limiter. Each id is fetched once, the orders come in the
+    // order their id first appears. Err if any lookup fails
+    pub fn get_orders_by_ids(&self, ids: &[OrderId]) -> APIResult<Vec<TradeHistory>> {
+        let ids = unique_ids(ids);
+
+        self.client
+            .batch_get(&ids, ORDER_LOOKUP_CONCURRENCY, |_, &id| self.get_order(id))
+            .into_iter()
+            .collect()
+    }
+
+    // Polls the order until it is filled or canceled and returns it in its final state,
+    // a canceled order is returned too. Every poll goes through the rate limiter
+    pub fn wait_for_fill(
+        &self,
+        order_id: OrderId,
+        timeout: Duration,
+        poll_interval: Duration,
+    ) -> APIResult<TradeHistory> {
+        let deadline = Instant::now() + timeout;
+
+        loop {
+            let order = self.get_order(order_id)?;
+
+            if order.state.is_final() {
+                return Ok(order);
+            }
+
+            let now = Instant::now();
+            if now >= deadline {
+                return Err(Box::new(OrderError::WaitTimeout {
+                    order_id,
+                    state: order.state,
+                }));
+            }
+
+            thread::sleep(poll_interval.min(deadline - now));
+        }
+    }
+
+    pub fn get_order_by_client_id(&self, client_order_id: &str) -> APIResult<TradeHistory> {
+        let mut params: BTreeMap<String, String> = BTreeMap::new();
+
+        params.insert("clientOrderId".into(), client_order_id.into());
+
+        let data = self.client.get_signed(Endpoint::ClientOrder, params)?;
+
+        let order: OrderDetailResult = serde_json::from_str(data.as_str())?;
+
+        Ok(order.data)
+    }
+
+    // place_order that retries on timeouts and connect errors, after which the order may
+    // or may not have reached Huobi. The client-order-id makes it safe: a duplicate
+    // client-order-id on a retry means an earlier attempt was placed, so that order is
+    // returned. A duplicate on the first attempt is a real collision and is the error
+    pub fn place_order_with_retry(
+        &self,
+        order: &NewOrder,
+        attempts: usize,
+    ) -> APIResult<Transaction> {
+        let client_order_id = match &order.client_order_id {
+            Some(id) => id,
+            None => return Err(Box::new(OrderError::MissingClientOrderId)),
+        };
+
+        let mut attempt = 1;
+
+        loop {
+            let err = match self.place_order(order) {
+                Ok(transaction) => return Ok(transaction),
+                Err(err) => err,
+            };
+
+            if attempt > 1 {
+                if let Some(OrderError::DuplicateClientOrderId(_)) = err.downcast_ref() {
+                    let placed = self.get_order_by_client_id(client_order_id)?;
+
+                    self.client.log_policy().event(format_args!(
+                        "[Huobi] Order {} was placed by an earlier attempt: {}",
+                        client_order_id, placed.id
+                    ));
+
+                    return Ok(Transaction {
+                        symbol: order.symbol.clone(),
+                        order_id: OrderId(placed.id),
+                    });
+                }
+            }
+
+            let retryable = match err.downcast_ref() {
+                Some(HuobiError::Network { kind, .. }) => kind.is_retryable(),
+                _ => false,
+            };
+
+            if attempt >= attempts || !retryable {
+                return Err(err);
+            }
+
+            error!(
+                "[Huobi] Place order {} attempt {} failed: {}",
+                client_order_id, attempt, err
+            );
+
+            attempt += 1;
+            thread::sleep(PLACE_RETRY_INTERVAL);
+        }
+    }
+
+    // Cancel then place, Huobi has no order amend. The new order is placed only once the
+    // old one is confirmed canceled: if the cancel fails, is not confirmed in time or the
+    // old order was filled meanwhile, the old order is left as it is and nothing is placed.
+    // A partially filled old order is replaced by what it did not fill, see remaining_order
+    pub fn replace_order(&self, order_id: OrderId, new: NewOrder) -> APIResult<OrderId> {
+        new.validate()?;
+
         let params: BTreeMap<String, String> = BTreeMap::new();
+        let mut body: BTreeMap<String, String> = BTreeMap::new();
+
+        body.insert("order-id".into(), order_id.to_string());
+
+        self.client
+            .post_signed(Endpoint::SubmitCancel(order_id.get()), params, &body)?;
+
+        let mut state = OrderState::Unknown(String::new());
 
-        let data = self.client.get_signed("/v1/account/accounts", params)?;
+        for _ in 0..CANCEL_CONFIRM_ATTEMPTS {
+            let old = self.get_order(order_id)?;
+            state = old.state.clone();
 
-        debug!("[Huobi] Get accounts result: {:?} ", data);
+            match &state {
+                OrderState::Canceled | OrderState::PartialCanceled => {
+                    let new = match state {
+                        OrderState::PartialCanceled => {
+                            remaining_order(&new, &old, &self.client.get_symbol(&new.symbol)?)?
+                        }
+                        _ => new.clone(),
+                    };
+                    let placed = self.place_order(&new)?;
+
+                    self.client.log_policy().event(format_args!(
+                        "[Huobi] Order {} was replaced by {}",
+                        order_id, placed.order_id
+                    ));
+
+                    return Ok(placed.order_id);
+                }
+                OrderState::Filled => {
+                    return Err(Box::new(HuobiError::ApiError(format!(
+                        "order {} was filled before the cancel, the new order is not placed",
+                        order_id
+                    ))))
+                }
+                _ => thread::sleep(CANCEL_CONFIRM_INTERVAL),
+            }
+        }
+
+        Err(Box::new(HuobiError::ApiError(format!(
+            "cancel of order {} is not confirmed, state {}, the new order is not placed",
+            order_id, state
+        ))))
+    }
+
+    pub fn get_accounts(&self) -> APIResult<AccountsResult> {
+        let params: BTreeMap<String, String> = BTreeMap::new();
+
+        let data = self.client.get_signed(Endpoint::Accounts, params)?;
 
         let accounts: AccountsResult = serde_json::from_str(data.as_str())?;
 
         Ok(accounts)
     }
 
-    pub fn cancel_order(&self, symbol: &str, order_id: u64) -> ExchangeApiResult<Transaction> {
+    pub fn cancel_order(&self, symbol: &str, order_id: OrderId) -> ExchangeApiResult<Transaction> {
         let params: BTreeMap<String, String> = BTreeMap::new();
         let mut body: BTreeMap<String, String> = BTreeMap::new();
 
         body.insert("order-id".into(), order_id.to_string());
 
-        let endpoint = format!("/v1/order/orders/{}/submitcancel", order_id);
-
-        match self.client.post_signed(&endpoint, params, &body) {
+        match self
+            .client
+            .post_signed(Endpoint::SubmitCancel(order_id.get()), params, &body)
+        {
             Ok(data) => {
                 let cancel_order: CancelOrderResult = serde_json::from_str(data.as_str()).unwrap();
 
@@ -211,7 +897,7 @@ impl Account {
 
                 Ok(Transaction {
                     symbol: symbol.to_string(),
-                    order_id: cancel_order.data.parse().unwrap(),
+                    order_id: cancel_order.data,
                 })
             }
             Err(_e) => Err("Huobi Request error"),
@@ -228,9 +914,10 @@ impl Account {
 
         body.insert("client-order-id".into(), custom_order_id.into());
 
-        let endpoint = "/v1/order/orders/submitCancelClientOrder";
-
-        match self.client.post_signed(endpoint, params, &body) {
+        match self
+            .client
+            .post_signed(Endpoint::SubmitCancelClientOrder, params, &body)
+        {
             Ok(data) => {
                 let _cancel_order: CancelOrderWithIdResult =
                     serde_json::from_str(data.as_str()).unwrap();
@@ -244,7 +931,7 @@ impl Account {
                     symbol: symbol.to_string(),
                     // order_id: cancel_order.data.parse().unwrap(),
                     // We don't know order id
-                    order_id: 0,
+                    order_id: OrderId(0),
                 })
             }
             Err(_e) => Err("Huobi Request error"),
@@ -254,17 +941,30 @@ impl Account {
     pub fn get_all_balances(&self) -> APIResult<BalanceResult> {
         let params: BTreeMap<String, String> = BTreeMap::new();
 
-        let endpoint = format!("/v1/account/accounts/{}/balance", self.account_id);
+        let endpoint = Endpoint::AccountBalance(self.account_id()?);
 
-        let data = self.client.get_signed(&endpoint, params)?;
-
-        debug!("[Huobi] Get balance result: {:?} ", data);
+        let data = self.client.get_signed(endpoint, params)?;
 
         let balances: BalanceResult = serde_json::from_str(data.as_str())?;
 
         Ok(balances)
     }
 
+    // Tradable (not frozen) balance, 0 if the currency has never been held
+    pub fn get_available_balance(&self, currency: &str) -> APIResult<Decimal> {
+        let balances = self.get_all_balances()?;
+
+        Ok(balances
+            .data
+            .list
+            .into_iter()
+            .filter(|asset| {
+                asset.trade_type == "trade" && asset.currency == currency.to_lowercase()
+            })
+            .map(|asset| asset.balance)
+            .sum())
+    }
+
     pub fn get_balance(&self, symbol: &str) -> ExchangeApiResult<Asset> {
         let balances = self.get_all_balances().unwrap();
 
@@ -286,9 +986,7 @@ impl Account {
 
         params.insert("symbol".into(), symbol.to_lowercase().into());
 
-        let data = self.client.get_signed("/v1/order/history", params)?;
-
-        debug!("[Huobi] Get trade history {:?}", data);
+        let data = self.client.get_signed(Endpoint::OrderHistory, params)?;
 
         let trade_history: TradeHistoryResult = serde_json::from_str(data.as_str())?;
 
@@ -298,12 +996,796 @@ impl Account {
     pub fn trade_history_all(&self) -> APIResult<TradeHistoryResult> {
         let params: BTreeMap<String, String> = BTreeMap::new();
 
-        let data = self.client.get_signed("/v1/order/history", params)?;
-
-        debug!("[Huobi] Get all trade history {:?}", data);
+        let data = self.client.get_signed(Endpoint::OrderHistory, params)?;
 
         let trade_history: TradeHistoryResult = serde_json::from_str(data.as_str())?;
 
         Ok(trade_history)
     }
+
+    // Fee rates of up to 10 symbols in one request
+    pub fn get_vip_tiers(&self, symbols: &[&str]) -> APIResult<Vec<VipInfo>> {
+        let symbols: Vec<String> = symbols.iter().map(|symbol| symbol.to_lowercase()).collect();
+
+        let data = self
+            .client
+            .get_signed_params(Endpoint::TransactFeeRate, &json!({ "symbols": symbols }))?;
+
+        let fee_rates: FeeRateResult = serde_json::from_str(data.as_str())?;
+
+        Ok(fee_rates.data.into_iter().map(VipInfo::from).collect())
+    }
+
+    // Huobi grants the rates per symbol and reports no VIP level number, so the tier
+    // is the rates of one symbol
+    pub fn get_vip_tier(&self, symbol: &str) -> APIResult<VipInfo> {
+        let mut params: BTreeMap<String, String> = BTreeMap::new();
+
+        params.insert("symbols".into(), symbol.to_lowercase());
+
+        let data = self
+            .client
+            .get_signed_cached(Endpoint::TransactFeeRate, params)?;
+
+        let fee_rates: FeeRateResult = serde_json::from_str(data.as_str())?;
+
+        match fee_rates.data.into_iter().next() {
+            Some(fee_rate) => Ok(VipInfo::from(fee_rate)),
+            None => Err(Box::new(HuobiError::ApiError(format!(
+                "no fee rate for symbol {}",
+                symbol
+            )))),
+        }
+    }
+
+    // The chain and the amount are checked against the currency reference before sending
+    pub fn create_withdraw(
+        &self,
+        currency: &str,
+        address: &str,
+        amount: f64,
+        chain: &str,
+        addr_tag: Option<&str>,
+    ) -> APIResult<u64> {
+        self.check_withdraw_address(address)?;
+
+        let chains = self.client.get_currency_chains(currency)?;
+
+        match chains
+            .iter()
+            .find(|currency_chain| currency_chain.chain == chain)
+        {
+            Some(currency_chain) => currency_chain.check_withdraw(amount)?,
+            None => {
+                return Err(Box::new(HuobiError::InvalidWithdraw(format!(
+                    "chain {} is not available for {}",
+                    chain, currency
+                ))))
+            }
+        }
+
+        let params: BTreeMap<String, String> = BTreeMap::new();
+        let mut body: BTreeMap<String, String> = BTreeMap::new();
+
+        body.insert("address".into(), address.into());
+        body.insert("amount".into(), amount.to_string());
+        body.insert("currency".into(), currency.to_lowercase());
+        body.insert("chain".into(), chain.into());
+
+        if let Some(tag) = addr_tag {
+            body.insert("addr-tag".into(), tag.into());
+        }
+
+        let data = self
+            .client
+            .post_signed(Endpoint::WithdrawCreate, params, &body)?;
+
+        Ok(parse_withdraw(&data)?)
+    }
+
+    // Current state of a withdrawal made by create_withdraw, with its tx hash once it is
+    // broadcast. Poll it until WithdrawState::is_final
+    pub fn get_withdraw_status(&self, withdraw_id: u64) -> APIResult<TransferRecord> {
+        let mut params: BTreeMap<String, String> = BTreeMap::new();
+
+        params.insert("type".into(), "withdraw".into());
+        params.insert("from".into(), withdraw_id.to_string());
+        params.insert("direct".into(), "next".into());
+        params.insert("size".into(), WITHDRAW_LOOKUP_SIZE.to_string());
+
+        let data = self.client.get_signed(Endpoint::DepositWithdraw, params)?;
+
+        let records: TransferRecordsResult = serde_json::from_str(data.as_str())?;
+
+        Ok(find_withdraw(records.data, withdraw_id)?)
+    }
+
+    // Dead man's switch: Huobi cancels all open orders if it is not refreshed within
+    // timeout_secs (5-60). A timeout of 0 turns the switch off
+    pub fn set_dead_mans_switch(&self, timeout_secs: u32) -> APIResult<CancelAllAfter> {
+        if timeout_secs != 0 && !(5..=60).contains(&timeout_secs) {
+            return Err(Box::new(HuobiError::ApiError(format!(
+                "dead man's switch timeout should be 0 or in 5-60 seconds, got {}",
+                timeout_secs
+            ))));
+        }
+
+        let params: BTreeMap<String, String> = BTreeMap::new();
+        let body = json!({ "timeout": timeout_secs });
+
+        let data = self
+            .client
+            .post_signed(Endpoint::CancelAllAfter, params, &body)?;
+
+        let result: CancelAllAfterResult = serde_json::from_str(data.as_str())?;
+
+        match result.data {
+            Some(cancel_all_after) if result.code == 200 => Ok(cancel_all_after),
+            _ => Err(Box::new(HuobiError::ApiError(format!(
+                "cancel all after error {}: {}",
+                result.code,
+                result.message.unwrap_or_default()
+            )))),
+        }
+    }
+
+    // Refreshes the dead man's switch at half of the timeout while running is true,
+    // then turns it off. If the process dies, Huobi cancels the open orders
+    pub fn run_dead_mans_switch(
+        &self,
+        timeout_secs: u32,
+        running: Arc<AtomicBool>,
+    ) -> JoinHandle<()> {
+        let account = self.clone();
+        let interval = Duration::from_millis(u64::from(timeout_secs) * 1000 / 2);
+
+        // Heartbeat errors are logged whatever the LogPolicy, the thread has no caller
+        thread::spawn(move || {
+            while running.load(Ordering::Relaxed) {
+                if let Err(e) = account.set_dead_mans_switch(timeout_secs) {
+                    error!("[Huobi] Dead man's switch heartbeat error: {}", e);
+                }
+
+                thread::sleep(interval);
+            }
+
+            match account.set_dead_mans_switch(0) {
+                Ok(_) => account
+                    .client
+                    .log_policy()
+                    .event(format_args!("[Huobi] Dead man's switch is turned off")),
+                Err(e) => error!("[Huobi] Can't turn off dead man's switch: {}", e),
+            }
+        })
+    }
+
+    pub fn get_account_history(
+        &self,
+        query: &AccountHistoryQuery,
+    ) -> APIResult<AccountHistoryResult> {
+        let params = self.account_history_params(query)?;
+
+        let data = self.client.get_signed(Endpoint::AccountHistory, params)?;
+
+        let history: AccountHistoryResult = serde_json::from_str(data.as_str())?;
+
+        Ok(history)
+    }
+
+    // Same page as get_account_history without holding it in memory,
+    // next_id of the result is the from_id of the next page
+    pub fn for_each_account_history<F>(
+        &self,
+        query: &AccountHistoryQuery,
+        on_entry: F,
+    ) -> APIResult<StreamedList>
+    where
+        F: FnMut(LedgerEntry),
+    {
+        let params = self.account_history_params(query)?;
+
+        self.client
+            .get_signed_each(Endpoint::AccountHistory, params, on_entry)
+    }
+
+    pub fn for_each_trade_history<F>(&self, symbol: &str, on_order: F) -> APIResult<StreamedList>
+    where
+        F: FnMut(TradeHistory),
+    {
+        let mut params: BTreeMap<String, String> = BTreeMap::new();
+
+        params.insert("symbol".into(), symbol.to_lowercase());
+
+        self.client
+            .get_signed_each(Endpoint::OrderHistory, params, on_order)
+    }
+
+    // Every fill matching the query, each symbol is paged through until a short page
+    pub fn get_all_matchresults(&self, query: MatchQuery) -> APIResult<Vec<MatchResult>> {
+        self.iter_matchresults(query).collect()
+    }
+
+    pub(crate) fn account_history_params(
+        &self,
+        query: &AccountHistoryQuery,
+    ) -> APIResult<BTreeMap<String, String>> {
+        let mut params: BTreeMap<String, String> = BTreeMap::new();
+
+        params.insert("account-id".into(), self.account_id()?);
+
+        if let Some(currency) = &query.currency {
+            params.insert("currency".into(), currency.to_lowercase());
+        }
+        if let Some(transact_types) = &query.transact_types {
+            params.insert("transact-types".into(), transact_types.clone());
+        }
+        if let Some(start_time) = query.start_time {
+            params.insert("start-time".into(), start_time.to_string());
+        }
+        if let Some(end_time) = query.end_time {
+            params.insert("end-time".into(), end_time.to_string());
+        }
+        if let Some(size) = query.size {
+            params.insert("size".into(), size.to_string());
+        }
+        if let Some(from_id) = query.from_id {
+            params.insert("from-id".into(), from_id.to_string());
+        }
+
+        Ok(params)
+    }
+}
+
+fn count_by_symbol(orders: &[OpenOrder]) -> BTreeMap<String, usize> {
+    let mut counts = BTreeMap::new();
+
+    for order in orders {
+        *counts.entry(order.symbol.clone()).or_insert(0) += 1;
+    }
+
+    counts
+}
+
+fn unique_ids(ids: &[OrderId]) -> Vec<OrderId> {
+    let mut seen = HashSet::new();
+
+    ids.iter().copied().filter(|id| seen.insert(*id)).collect()
+}
+
+// Accounts are listed in no particular order, a key has one account per type
+// and one isolated margin account per symbol
+fn find_account_id(accounts: &[models::Account], account_type: &AccountType) -> Option<u64> {
+    accounts
+        .iter()
+        .find(|account| account_type.matches(account) && account.state == AccountState::Working)
+        .map(|account| account.id)
+}
+
+// Orders and items are paired by client-order-id, orders without one take the next
+// item without one. Huobi answers in the order of the request, but an item must never
+// be credited to another order if it does not
+fn take_batch_item(
+    items: &mut [Option<BatchOrderItem>],
+    client_order_id: Option<&str>,
+) -> Option<BatchOrderItem> {
+    let index = items.iter().position(|item| match item {
+        Some(item) => {
+            item.client_order_id.as_deref().filter(|id| !id.is_empty()) == client_order_id
+        }
+        None => false,
+    })?;
+
+    items[index].take()
+}
+
+fn batch_item_result(item: BatchOrderItem) -> Result<OrderId, BatchItemError> {
+    match (item.order_id, &item.err_code) {
+        (Some(order_id), None) => Ok(order_id),
+        _ => Err(BatchItemError {
+            order_id: item.order_id,
+            client_order_id: item.client_order_id,
+            err_code: item.err_code.unwrap_or_default(),
+            err_msg: item.err_msg.unwrap_or_default(),
+        }),
+    }
+}
+
+// An item of a batch whose request failed as a whole, e.g. on a network error
+fn request_failed(
+    order_id: Option<OrderId>,
+    client_order_id: Option<String>,
+    e: &dyn Error,
+) -> BatchItemError {
+    BatchItemError {
+        order_id,
+        client_order_id,
+        err_code: "request-failed".into(),
+        err_msg: e.to_string(),
+    }
+}
+
+// Wallet endpoints may answer "ok" with the failure in "data", so a withdrawal
+// is a success only when data is a withdraw id and nothing reports an error
+fn parse_withdraw(body: &str) -> Result<u64, HuobiError> {
+    let rejected = |why: String| HuobiError::InvalidWithdraw(format!("rejected: {}", why));
+
+    let response: serde_json::Value = serde_json::from_str(body)
+        .map_err(|e| rejected(format!("unreadable response {}: {}", body, e)))?;
+
+    let status = response["status"].as_str().unwrap_or_default();
+    let err_code = response["err-code"].as_str().unwrap_or_default();
+    if status != "ok" || !err_code.is_empty() {
+        return Err(rejected(body.to_string()));
+    }
+
+    let withdraw: WithdrawResult =
+        serde_json::from_value(response).map_err(|_| rejected(body.to_string()))?;
+
+    if withdraw.data == 0 {
+        return Err(rejected(body.to_string()));
+    }
+
+    Ok(withdraw.data)
+}
+
+fn find_withdraw(
+    records: Vec<TransferRecord>,
+    withdraw_id: u64,
+) -> Result<TransferRecord, HuobiError> {
+    records
+        .into_iter()
+        .find(|record| record.id == withdraw_id && record.type_ == "withdraw")
+        .ok_or_else(|| {
+            HuobiError::InvalidWithdraw(format!("withdraw {} is not found", withdraw_id))
+        })
+}
+
+// The new order less what the old one filled, in the unit of the new amount: the base
+// filled, or the quote spent for a quote amount. When the rest is under the minimum
+// amount of the symbol, or its minimum value for a quote amount, nothing is left to place
+fn remaining_order(
+    new: &NewOrder,
+    old: &TradeHistory,
+    symbol: &Symbol,
+) -> Result<NewOrder, OrderError> {
+    let (filled, min, precision) = match new.amount {
+        Amount::Base(_) => (old.field_amount, symbol.min_amount, symbol.amount_precision),
+        Amount::Quote(_) => (
+            old.field_cash_amount,
+            symbol.min_value,
+            symbol.value_precision,
+        ),
+    };
+
+    let remaining = (Decimal::from_f64(new.amount.value()).unwrap_or_default() - filled)
+        .round_dp_with_strategy(u32::from(precision), RoundingStrategy::ToZero);
+    let remaining_value = remaining.to_f64().unwrap_or_default();
+
+    if remaining_value <= 0. || remaining_value < min {
+        return Err(OrderError::RemainderBelowMin {
+            order_id: OrderId(old.id),
+            filled,
+            remaining,
+        });
+    }
+
+    let mut order = new.clone();
+    order.amount = match new.amount {
+        Amount::Base(_) => Amount::Base(remaining_value),
+        Amount::Quote(_) => Amount::Quote(remaining_value),
+    };
+
+    Ok(order)
+}
+
+fn created_before(orders: &[OpenOrder], before: DateTime<Utc>) -> Vec<OrderId> {
+    let before = before.timestamp_millis();
+
+    orders
+        .iter()
+        .filter(|order| (order.created_at as i64) < before)
+        .map(|order| OrderId(order.id))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::super::error::BatchError;
+    use super::super::interceptor::{Interceptor, RequestParts};
+    use super::super::models::{Amount, OrderType, Symbol, SymbolState, WithdrawState};
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn withdraw_with_embedded_error() {
+        assert_eq!(
+            parse_withdraw(r#"{"status":"ok","data":101123262}"#).unwrap(),
+            101123262
+        );
+        assert_eq!(
+            parse_withdraw(r#"{"status":"ok","data":"101123262"}"#).unwrap(),
+            101123262
+        );
+
+        for body in [
+            r#"{"status":"ok","data":{"err-code":"dw-insufficient-balance","err-msg":"insufficient balance"}}"#,
+            r#"{"status":"ok","data":null}"#,
+            r#"{"status":"ok","data":0}"#,
+            r#"{"status":"ok","err-code":"dw-withdraw-min-limit","data":101123262}"#,
+            r#"{"status":"error","err-code":"dw-insufficient-balance","err-msg":"insufficient balance"}"#,
+            "<html>502 Bad Gateway</html>",
+        ]
+        .iter()
+        {
+            match parse_withdraw(body) {
+                Err(HuobiError::InvalidWithdraw(_)) => {}
+                result => panic!("{} is not rejected: {:?}", body, result),
+            }
+        }
+    }
+
+    #[test]
+    fn withdraw_status_by_id() {
+        let records: TransferRecordsResult = serde_json::from_str(r#"{"status":"ok","data":[
+            {"id":101123262,"type":"withdraw","sub-type":"NORMAL","currency":"usdt","chain":"trc20usdt","tx-hash":"","amount":100,"from-addr-tag":"","address":"TXtxrFaSqSNdXnbRpb2Qgn2GjjyjFYvm1e","address-tag":"","fee":1,"state":"pass","error-code":"","error-msg":"","created-at":1630000000000,"updated-at":1630000060000},
+            {"id":101123263,"type":"withdraw","sub-type":"NORMAL","currency":"usdt","chain":"trc20usdt","tx-hash":"a8a7b7f8e1c8d3f0e9b5c6d7e8f9a0b1c2d3e4f5a6b7c8d9e0f1a2b3c4d5e6f7","amount":"50","address":"TXtxrFaSqSNdXnbRpb2Qgn2GjjyjFYvm1e","address-tag":"","fee":"1","state":"confirmed","error-msg":"","created-at":1630000100000,"updated-at":1630000400000}
+        ]}"#).unwrap();
+
+        let pending = find_withdraw(records.data.clone(), 101123262).unwrap();
+        assert_eq!(pending.state, WithdrawState::Pass);
+        assert_eq!(pending.tx_hash, None);
+        assert!(!pending.state.is_final());
+
+        let confirmed = find_withdraw(records.data.clone(), 101123263).unwrap();
+        assert!(confirmed.state.is_confirmed());
+        assert_eq!(confirmed.tx_hash.unwrap().len(), 64);
+        assert_eq!(confirmed.amount, 50.);
+
+        assert!(find_withdraw(records.data, 101123264).is_err());
+    }
+
+    #[test]
+    fn open_orders_counted_per_symbol() {
+        let orders: OpenOrdersResult = serde_json::from_str(r#"{"status":"ok","data":[
+            {"id":11,"client-order-id":"","symbol":"btcusdt","price":"45000","amount":"0.01","created-at":1630000000000,"type":"buy-limit","filled-amount":"0","filled-cash-amount":"0","filled-fees":"0","source":"spot-api","state":"submitted"},
+            {"id":12,"client-order-id":"","symbol":"ethusdt","price":"3000","amount":"0.1","created-at":1630000060000,"type":"sell-limit","filled-amount":"0","filled-cash-amount":"0","filled-fees":"0","source":"spot-api","state":"submitted"},
+            {"id":13,"client-order-id":"","symbol":"btcusdt","price":"45100","amount":"0.01","created-at":1630000060000,"type":"buy-limit","filled-amount":"0","filled-cash-amount":"0","filled-fees":"0","source":"spot-api","state":"partial-filled"}
+        ]}"#).unwrap();
+
+        let counts = count_by_symbol(&orders.data);
+
+        assert_eq!(counts.get("btcusdt"), Some(&2));
+        assert_eq!(counts.get("ethusdt"), Some(&1));
+        assert_eq!(counts.len(), 2);
+    }
+
+    #[test]
+    fn order_ids_deduplicated_in_order() {
+        let ids = [OrderId(3), OrderId(1), OrderId(3), OrderId(2), OrderId(1)];
+
+        assert_eq!(unique_ids(&ids), vec![OrderId(3), OrderId(1), OrderId(2)]);
+    }
+
+    #[test]
+    fn spot_account_is_the_working_one() {
+        let accounts: AccountsResult = serde_json::from_str(r#"{"status":"ok","data":[{"id":100010,"type":"margin","subtype":"btcusdt","state":"working"},{"id":100008,"type":"spot","subtype":"","state":"lock"},{"id":100009,"type":"spot","subtype":"","state":"working"}]}"#).unwrap();
+
+        assert_eq!(
+            find_account_id(&accounts.data, &AccountType::Spot),
+            Some(100009)
+        );
+        assert_eq!(
+            find_account_id(&accounts.data[..2], &AccountType::Spot),
+            None
+        );
+    }
+
+    #[test]
+    fn account_id_by_type() {
+        let accounts: AccountsResult = serde_json::from_str(r#"{"status":"ok","data":[{"id":100009,"type":"spot","subtype":"","state":"working"},{"id":100010,"type":"margin","subtype":"btcusdt","state":"working"},{"id":100011,"type":"margin","subtype":"ethusdt","state":"working"},{"id":100012,"type":"super-margin","subtype":"","state":"working"},{"id":100013,"type":"point","subtype":"","state":"working"}]}"#).unwrap();
+
+        let id = |account_type: AccountType| find_account_id(&accounts.data, &account_type);
+
+        assert_eq!(id(AccountType::Margin("ETHUSDT".into())), Some(100011));
+        assert_eq!(id(AccountType::Margin("htusdt".into())), None);
+        assert_eq!(id(AccountType::SuperMargin), Some(100012));
+        assert_eq!(id(AccountType::Point), Some(100013));
+        assert_eq!(id(AccountType::Otc), None);
+    }
+
+    #[test]
+    fn explicit_account_id_is_not_discovered() {
+        let account = Account::new("100009", Some("key".into()), Some("secret".into()));
+
+        assert_eq!(account.account_id().unwrap(), "100009");
+    }
+
+    #[test]
+    fn orders_created_before_cutoff() {
+        let orders: Vec<OpenOrder> = serde_json::from_str(r#"[
+            {"id":11,"client-order-id":"","symbol":"btcusdt","price":"45000","amount":"0.01","created-at":1630000000000,"type":"buy-limit","filled-amount":"0","filled-cash-amount":"0","filled-fees":"0","source":"spot-api","state":"submitted"},
+            {"id":12,"client-order-id":"","symbol":"btcusdt","price":"45100","amount":"0.01","created-at":1630000060000,"type":"buy-limit","filled-amount":"0","filled-cash-amount":"0","filled-fees":"0","source":"spot-api","state":"submitted"}
+        ]"#).unwrap();
+
+        let cutoff = Utc.timestamp_millis_opt(1630000030000).unwrap();
+
+        assert_eq!(created_before(&orders, cutoff), vec![OrderId(11)]);
+    }
+
+    #[test]
+    fn final_order_states() {
+        for state in ["filled", "canceled", "partial-canceled"].iter() {
+            assert!(state.parse::<OrderState>().unwrap().is_final());
+        }
+        for state in ["created", "submitted", "partial-filled", "canceling"].iter() {
+            assert!(!state.parse::<OrderState>().unwrap().is_final());
+        }
+        assert!(!OrderState::Unknown("new-state".into()).is_final());
+    }
+
+    #[test]
+    fn partial_canceled_order_is_final_with_its_fill() {
+        let order: OrderDetailResult = serde_json::from_str(r#"{"status":"ok","data":{"id":59378,"symbol":"btcusdt","account-id":100009,"amount":"10.0","price":"100.1","created-at":1494901162595,"type":"buy-limit","field-amount":"4.25","field-cash-amount":"425.425","field-fees":"0.0085","finished-at":1494901400468,"source":"api","state":"partial-canceled","canceled-at":1494901400468}}"#).unwrap();
+
+        assert_eq!(order.data.state, OrderState::PartialCanceled);
+        assert!(order.data.state.is_final());
+        assert_eq!(order.data.filled_amount(), Decimal::new(425, 2));
+    }
+
+    #[test]
+    fn replacement_of_partial_fill_is_the_remainder() {
+        let old: OrderDetailResult = serde_json::from_str(r#"{"status":"ok","data":{"id":59378,"symbol":"btcusdt","account-id":100009,"amount":"10.0","price":"100.1","created-at":1494901162595,"type":"buy-limit","field-amount":"4.25","field-cash-amount":"425.425","field-fees":"0.0085","finished-at":1494901400468,"source":"api","state":"partial-canceled","canceled-at":1494901400468}}"#).unwrap();
+        let symbol = Symbol {
+            base: "btc".to_string(),
+            quote: "usdt".to_string(),
+            price_precision: 2,
+            amount_precision: 6,
+            partition: "main".to_string(),
+            symbol: "btcusdt".to_string(),
+            state: SymbolState::Online,
+            value_precision: 8,
+            min_amount: 0.0001,
+            max_amount: 1000.,
+            min_value: 5.,
+            max_leverage: 0.,
+        };
+        let new = |order_type, amount| {
+            let builder = NewOrder::builder("btcusdt", order_type, amount);
+            match order_type {
+                OrderType::BuyLimit => builder.price(100.2).build().unwrap(),
+                _ => builder.build().unwrap(),
+            }
+        };
+
+        let order = remaining_order(
+            &new(OrderType::BuyLimit, Amount::Base(10.)),
+            &old.data,
+            &symbol,
+        )
+        .unwrap();
+        assert_eq!(order.amount, Amount::Base(5.75));
+        assert_eq!(order.price, Some(100.2));
+
+        // A quote amount is reduced by the quote spent
+        let order = remaining_order(
+            &new(OrderType::BuyMarket, Amount::Quote(500.)),
+            &old.data,
+            &symbol,
+        )
+        .unwrap();
+        assert_eq!(order.amount, Amount::Quote(74.575));
+
+        match remaining_order(
+            &new(OrderType::BuyLimit, Amount::Base(4.25005)),
+            &old.data,
+            &symbol,
+        ) {
+            Err(OrderError::RemainderBelowMin {
+                order_id,
+                filled,
+                remaining,
+            }) => {
+                assert_eq!(order_id, OrderId(59378));
+                assert_eq!(filled, Decimal::new(425, 2));
+                assert_eq!(remaining, Decimal::new(5, 5));
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+
+        // Filled more than the new amount
+        match remaining_order(
+            &new(OrderType::BuyLimit, Amount::Base(4.)),
+            &old.data,
+            &symbol,
+        ) {
+            Err(OrderError::RemainderBelowMin { .. }) => {}
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    fn item(order_id: Option<u64>, client_order_id: Option<&str>) -> Option<BatchOrderItem> {
+        Some(BatchOrderItem {
+            order_id: order_id.map(OrderId),
+            client_order_id: client_order_id.map(|id| id.to_string()),
+            err_code: None,
+            err_msg: None,
+        })
+    }
+
+    #[test]
+    fn batch_items_paired_by_client_order_id() {
+        let mut items = vec![
+            item(Some(2), Some("c2")),
+            item(Some(3), None),
+            item(Some(1), Some("c1")),
+        ];
+
+        let first = take_batch_item(&mut items, Some("c1")).unwrap();
+        assert_eq!(first.order_id, Some(OrderId(1)));
+
+        let second = take_batch_item(&mut items, None).unwrap();
+        assert_eq!(second.order_id, Some(OrderId(3)));
+
+        // c3 has no item, the one of c2 is not credited to it
+        assert!(take_batch_item(&mut items, Some("c3")).is_none());
+        assert!(take_batch_item(&mut items, None).is_none());
+        assert_eq!(
+            take_batch_item(&mut items, Some("c2")).unwrap().order_id,
+            Some(OrderId(2))
+        );
+    }
+
+    #[test]
+    fn failed_batch_item_is_an_error() {
+        let failed = BatchItemError {
+            order_id: None,
+            client_order_id: Some("c2".into()),
+            err_code: "order-value-min-error".into(),
+            err_msg: "Order total cannot be lower than: 5".into(),
+        };
+
+        assert_eq!(
+            BatchError::check(vec![Ok(OrderId(1)), Ok(OrderId(2))]).unwrap(),
+            vec![OrderId(1), OrderId(2)]
+        );
+
+        let e = BatchError::check(vec![Ok(OrderId(1)), Err(failed)]).unwrap_err();
+        assert_eq!(e.succeeded, vec![OrderId(1)]);
+        assert_eq!(e.failed[0].err_code, "order-value-min-error");
+    }
+
+    #[test]
+    fn precision_error_with_cached_symbol() {
+        let cache = SymbolCache::new();
+        cache.update(vec![Symbol {
+            base: "btc".to_string(),
+            quote: "usdt".to_string(),
+            price_precision: 2,
+            amount_precision: 6,
+            partition: "main".to_string(),
+            symbol: "btcusdt".to_string(),
+            state: SymbolState::Online,
+            value_precision: 8,
+            min_amount: 0.0001,
+            max_amount: 1000.,
+            min_value: 5.,
+            max_leverage: 0.,
+        }]);
+
+        let account = Account::new("1", Some("key".into()), Some("secret".into()))
+            .with_symbol_cache(Arc::new(cache));
+        let precision_error = || -> Box<dyn Error> {
+            Box::new(OrderError::PrecisionExceeded {
+                field: "amount".into(),
+                allowed: None,
+            })
+        };
+
+        let e = account.with_allowed_precision(precision_error(), "btcusdt");
+        assert_eq!(
+            e.to_string(),
+            "PrecisionExceeded: amount has more than the 6 decimals allowed, round it first"
+        );
+
+        // Unknown symbol, the error is left as it is
+        let e = account.with_allowed_precision(precision_error(), "ethusdt");
+        match e.downcast_ref::<OrderError>() {
+            Some(OrderError::PrecisionExceeded { allowed: None, .. }) => {}
+            _ => panic!("unexpected error {}", e),
+        }
+    }
+
+    fn listed(accounts: &str, listed_at: Instant) -> Arc<Mutex<Option<ListedAccounts>>> {
+        let accounts: AccountsResult = serde_json::from_str(accounts).unwrap();
+
+        Arc::new(Mutex::new(Some(ListedAccounts {
+            accounts: accounts.data,
+            listed_at,
+        })))
+    }
+
+    #[test]
+    fn missing_account_type_lists_the_accounts_again() {
+        let accounts = r#"{"status":"ok","data":[{"id":100009,"type":"spot","subtype":"","state":"working"}]}"#;
+        let margin = || AccountType::Margin("btcusdt".into());
+
+        let mut account = Account::discover(Some("key".into()), Some("secret".into()));
+        account.client = account.client.clone().with_interceptor(Unreachable);
+
+        account.accounts = listed(accounts, Instant::now());
+        assert_eq!(account.get_account_id(AccountType::Spot).unwrap(), 100009);
+
+        // Listed just now, the miss is not worth another request
+        let e = account.get_account_id(margin()).unwrap_err();
+        assert_eq!(
+            e.to_string(),
+            "ApiError: no working margin btcusdt account, it is not opened or it is locked"
+        );
+
+        account.accounts = listed(accounts, Instant::now() - ACCOUNTS_REFRESH_INTERVAL);
+        let e = account.get_account_id(margin()).unwrap_err();
+        match e.downcast_ref::<HuobiError>() {
+            Some(HuobiError::Network { .. }) => {}
+            _ => panic!("the accounts are not listed again: {}", e),
+        }
+    }
+
+    struct Unreachable;
+
+    impl Interceptor for Unreachable {
+        fn before_send(&self, req: &mut RequestParts) {
+            // Nothing listens on port 1
+            req.url = "http://127.0.0.1:1/unreachable".into();
+        }
+    }
+
+    #[test]
+    fn failed_batch_requests_keep_per_order_results() {
+        let mut account = Account::new("1", Some("key".into()), Some("secret".into()));
+        account.client = account.client.clone().with_interceptor(Unreachable);
+
+        let order = NewOrder::builder("btcusdt", OrderType::BuyLimit, Amount::Base(0.5))
+            .price(40000.)
+            .build()
+            .unwrap();
+        let mut invalid = order.clone();
+        invalid.price = None;
+
+        let mut orders = vec![order; 11];
+        orders[0] = invalid;
+
+        let results = account.place_orders(&orders).unwrap();
+
+        assert_eq!(results.len(), 11);
+        assert_eq!(results[0].as_ref().unwrap_err().err_code, "invalid-order");
+        assert!(results[1..]
+            .iter()
+            .all(|result| result.as_ref().unwrap_err().err_code == "request-failed"));
+
+        let results = account.cancel_orders(&[OrderId(1), OrderId(2)]).unwrap();
+
+        assert_eq!(results.len(), 2);
+        let failed = results[1].as_ref().unwrap_err();
+        assert_eq!(failed.err_code, "request-failed");
+        assert_eq!(failed.order_id, Some(OrderId(2)));
+    }
+
+    #[test]
+    fn withdraw_allowlist() {
+        let account = Account::new("1", Some("key".into()), Some("secret".into()));
+
+        assert!(account.check_withdraw_address("TXYZ").is_ok());
+
+        let account = account.with_withdraw_allowlist(vec!["TNb3kvGatUohT2sZdz5Y4jJ4L9KaLhrLBv"]);
+
+        assert!(account
+            .check_withdraw_address("TNb3kvGatUohT2sZdz5Y4jJ4L9KaLhrLBv")
+            .is_ok());
+
+        match account.check_withdraw_address("TXYZ") {
+            Err(HuobiError::InvalidWithdraw(_)) => {}
+            result => panic!("address is not rejected: {:?}", result),
+        }
+    }
 }
@@ -0,0 +1,223 @@
+use super::{
+    account::Account,
+    models::{APIResult, AccountUpdate, BalanceData},
+};
+use rust_decimal::Decimal;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct TrackedBalance {
+    balance: Decimal,
+    available: Decimal,
+    // ms, time of the change the values come from
+    change_time: u64,
+}
+
+// Live balances of an account: seeded from REST, then kept up to date by
+// "accounts.update" pushes. Share it with Arc between the WS handler and the readers.
+//
+// Pushes carry absolute values, so applying one twice is harmless and only the order
+// matters: an update or a seed is applied to a currency only if it is newer than the
+// value already there. Seed again after a reconnect, newer pushes are not overwritten
+pub struct BalanceTracker {
+    balances: Mutex<HashMap<String, TrackedBalance>>,
+}
+
+impl Default for BalanceTracker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl BalanceTracker {
+    pub fn new() -> Self {
+        BalanceTracker {
+            balances: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn seed(&self, account: &Account) -> APIResult<()> {
+        // Taken before the request: changes made while it is in flight may be
+        // in the response or not, the pushes after this time fix them up
+        let seed_time = now_ms();
+        let balances = account.get_all_balances()?;
+
+        for currency in self.seed_from(&balances.data, seed_time) {
+            account.client.log_policy().detail(format_args!(
+                "[Huobi] Keep newer balance of {} over the seed",
+                currency
+            ));
+        }
+
+        Ok(())
+    }
+
+    // Returns the currencies whose newer balance was kept over the seed
+    pub fn seed_from(&self, balance_data: &BalanceData, seed_time: u64) -> Vec<String> {
+        let mut seeded: HashMap<String, TrackedBalance> = HashMap::new();
+
+        for asset in &balance_data.list {
+            let tracked = seeded
+                .entry(asset.currency.clone())
+                .or_insert(TrackedBalance {
+                    balance: Decimal::ZERO,
+                    available: Decimal::ZERO,
+                    change_time: seed_time,
+                });
+
+            // "trade" is available, "frozen" is locked by open orders
+            tracked.balance += asset.balance;
+            if asset.trade_type == "trade" {
+                tracked.available += asset.balance;
+            }
+        }
+
+        let mut balances = self.balances.lock().unwrap();
+        let mut kept = vec![];
+
+        for (currency, tracked) in seeded {
+            match balances.get(&currency) {
+                Some(current) if current.change_time > seed_time => kept.push(currency),
+                _ => {
+                    balances.insert(currency, tracked);
+                }
+            }
+        }
+
+        kept
+    }
+
+    // Returns false if the update is older than the known balance and was skipped
+    pub fn apply(&self, update: &AccountUpdate) -> bool {
+        let mut balances = self.balances.lock().unwrap();
+
+        let change_time = match update.change_time {
+            Some(change_time) => change_time,
+            // Initial push is the current state
+            None => now_ms(),
+        };
+
+        let tracked = balances
+            .entry(update.currency.clone())
+            .or_insert(TrackedBalance {
+                balance: Decimal::ZERO,
+                available: Decimal::ZERO,
+                change_time: 0,
+            });
+
+        if change_time < tracked.change_time {
+            return false;
+        }
+
+        if let Some(balance) = update.balance {
+            tracked.balance = balance;
+        }
+        if let Some(available) = update.available {
+            tracked.available = available;
+        }
+        tracked.change_time = change_time;
+
+        true
+    }
+
+    pub fn available(&self, currency: &str) -> Option<Decimal> {
+        self.balances
+            .lock()
+            .unwrap()
+            .get(&currency.to_lowercase())
+            .map(|tracked| tracked.available)
+    }
+
+    pub fn balance(&self, currency: &str) -> Option<Decimal> {
+        self.balances
+            .lock()
+            .unwrap()
+            .get(&currency.to_lowercase())
+            .map(|tracked| tracked.balance)
+    }
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use super::super::models::Asset;
+
+    fn balance_data(available: i64, frozen: i64) -> BalanceData {
+        BalanceData {
+            id: 1,
+            account_type: "spot".to_string(),
+            state: "working".to_string(),
+            list: vec![
+                Asset {
+                    currency: "usdt".to_string(),
+                    trade_type: "trade".to_string(),
+                    balance: Decimal::from(available),
+                },
+                Asset {
+                    currency: "usdt".to_string(),
+                    trade_type: "frozen".to_string(),
+                    balance: Decimal::from(frozen),
+                },
+            ],
+        }
+    }
+
+    fn update(available: i64, change_time: u64) -> AccountUpdate {
+        AccountUpdate {
+            currency: "usdt".to_string(),
+            account_id: 1,
+            balance: Some(Decimal::from(available + 10)),
+            available: Some(Decimal::from(available)),
+            change_type: Some("order.place".to_string()),
+            account_type: Some("trade".to_string()),
+            change_time: Some(change_time),
+            seq_num: None,
+        }
+    }
+
+    #[test]
+    fn seed_then_apply_updates() {
+        let tracker = BalanceTracker::new();
+
+        tracker.seed_from(&balance_data(90, 10), 1000);
+
+        assert_eq!(tracker.available("USDT"), Some(Decimal::from(90)));
+        assert_eq!(tracker.balance("usdt"), Some(Decimal::from(100)));
+
+        assert!(tracker.apply(&update(80, 1001)));
+        assert_eq!(tracker.available("usdt"), Some(Decimal::from(80)));
+
+        // stale push, e.g. redelivered after a reconnect
+        assert!(!tracker.apply(&update(70, 999)));
+        assert_eq!(tracker.available("usdt"), Some(Decimal::from(80)));
+    }
+
+    #[test]
+    fn seed_does_not_overwrite_newer_update() {
+        let tracker = BalanceTracker::new();
+
+        tracker.apply(&update(80, 2000));
+        tracker.seed_from(&balance_data(90, 10), 1000);
+
+        assert_eq!(tracker.available("usdt"), Some(Decimal::from(80)));
+
+        tracker.seed_from(&balance_data(50, 10), 3000);
+
+        assert_eq!(tracker.available("usdt"), Some(Decimal::from(50)));
+    }
+
+    #[test]
+    fn unknown_currency() {
+        assert_eq!(BalanceTracker::new().available("btc"), None);
+    }
+}
@@ -1,16 +1,65 @@
 use super::error::*;
-use super::models::{APIErrorResponse, APIResult};
+use super::interceptor::{Interceptor, RequestParts, ResponseParts};
+use super::log_policy::{redact_url, LogPolicy};
+use super::models::{APIErrorResponse, APIResult, ApiVersion, ResponseMeta, StreamedList};
+use super::rate_limiter::RateLimiter;
+use super::response_cache::ResponseCache;
+use super::stream::for_each_data;
+use super::symbol_cache::REFRESH_INTERVAL;
+use chrono::{DateTime, Utc};
 use reqwest;
 use reqwest::header::{HeaderMap, HeaderValue, ACCEPT, CONTENT_TYPE, USER_AGENT};
+use reqwest::{Method, StatusCode};
 use ring::{digest, hmac};
-use serde::Serialize;
-use std::collections::BTreeMap;
-use tracing::{debug, info};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::error::Error;
+use std::fmt;
+use std::io::{BufReader, Read};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
 
+// Client is meant to be cloned into every thread that talks to Huobi:
+// clones share the connection pool, the rate limiter and the last response meta.
+// Create a new Client to get an independent pool and limits
 #[derive(Clone)]
 pub struct Client {
     api_key: String,
     secret_key: String,
+    // reqwest client is reference counted, its clones use the same connection pool
+    http_client: reqwest::blocking::Client,
+    rate_limiter: Option<Arc<RateLimiter>>,
+    // Limit of get_external, each host gets its own limiter apart from the spot one
+    external_rate_limit: Option<(usize, Duration)>,
+    external_rate_limiters: Arc<Mutex<HashMap<String, Arc<RateLimiter>>>>,
+    // Rate limit reported by the last signed response
+    response_meta: Arc<Mutex<Option<ResponseMeta>>>,
+    clock: Clock,
+    // Signing timestamp of the current second, Huobi signs with second resolution
+    timestamp_cache: Arc<Mutex<(i64, String)>>,
+    response_cache: Option<Arc<ResponseCache>>,
+    interceptors: Vec<Arc<dyn Interceptor>>,
+    max_response_size: Option<usize>,
+    host: String,
+    // Retries of a GET after a timeout or a connection failure
+    retries: u32,
+    log_policy: LogPolicy,
+    skew_alert: Option<SkewAlert>,
+    // Last successful refresh_symbols, a missing symbol triggers at most one per interval
+    symbols_refreshed_at: Arc<Mutex<Option<Instant>>>,
+}
+
+// Source of the signing timestamp, real UTC time unless replaced for tests
+pub type Clock = Arc<dyn Fn() -> DateTime<Utc> + Send + Sync>;
+
+// Called with the skew in ms when it goes past the threshold
+#[derive(Clone)]
+struct SkewAlert {
+    threshold_ms: i64,
+    on_skew: Arc<dyn Fn(i64) + Send + Sync>,
 }
 
 #[derive(Clone)]
@@ -21,14 +70,313 @@ pub struct APIKey {
 
 static HUOBI_API_HOST: &'static str = "api.huobi.pro";
 
+// Settings of a Client without the keys, so they can be kept in a config file
+// and the keys injected at runtime. Missing fields take their default
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ClientConfig {
+    // e.g. "api-aws.huobi.pro" from AWS
+    pub host: String,
+    // Whole request timeout, None waits forever
+    pub timeout_ms: Option<u64>,
+    // Retries of a GET after a timeout or a connection failure
+    pub retries: u32,
+    pub user_agent: String,
+    pub log_policy: LogPolicy,
+}
+
+impl Default for ClientConfig {
+    fn default() -> Self {
+        ClientConfig {
+            host: HUOBI_API_HOST.into(),
+            timeout_ms: Some(30_000),
+            retries: 0,
+            user_agent: "rs".into(),
+            log_policy: LogPolicy::default(),
+        }
+    }
+}
+
 impl Client {
     pub fn new(api_key: &str, secret_key: &str) -> Self {
+        Client::with_http_client(api_key, secret_key, reqwest::blocking::Client::new())
+    }
+
+    // Uses the caller's reqwest client as is, with its proxies, TLS and pool settings.
+    // The default headers of the crate are not added, Huobi accepts requests without them
+    pub fn with_http_client(
+        api_key: &str,
+        secret_key: &str,
+        http_client: reqwest::blocking::Client,
+    ) -> Self {
         Client {
             api_key: api_key.into(),
             secret_key: secret_key.into(),
+            http_client,
+            rate_limiter: None,
+            external_rate_limit: None,
+            external_rate_limiters: Arc::new(Mutex::new(HashMap::new())),
+            response_meta: Arc::new(Mutex::new(None)),
+            clock: Arc::new(Utc::now),
+            timestamp_cache: Arc::new(Mutex::new((i64::MIN, String::new()))),
+            response_cache: None,
+            interceptors: Vec::new(),
+            max_response_size: None,
+            host: HUOBI_API_HOST.into(),
+            retries: 0,
+            log_policy: LogPolicy::default(),
+            skew_alert: None,
+            symbols_refreshed_at: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    pub fn from_config(api_key: &str, secret_key: &str, config: &ClientConfig) -> APIResult<Self> {
+        let mut headers = build_headers(false)?;
+        headers.insert(USER_AGENT, HeaderValue::from_str(&config.user_agent)?);
+
+        let http_client = reqwest::blocking::Client::builder()
+            .timeout(config.timeout_ms.map(Duration::from_millis))
+            .default_headers(headers)
+            .build()?;
+
+        let mut client = Client::with_http_client(api_key, secret_key, http_client);
+        client.host = config.host.clone();
+        client.retries = config.retries;
+        client.log_policy = config.log_policy;
+
+        Ok(client)
+    }
+
+    pub fn with_clock<F>(mut self, clock: F) -> Self
+    where
+        F: Fn() -> DateTime<Utc> + Send + Sync + 'static,
+    {
+        self.clock = Arc::new(clock);
+        self
+    }
+
+    // Takes a builder configured by the caller (TLS backend, root certificates,
+    // proxies, pool limits...) and only adds the default headers of the crate on top
+    pub fn with_client_builder(
+        mut self,
+        builder: reqwest::blocking::ClientBuilder,
+    ) -> APIResult<Self> {
+        self.http_client = builder.default_headers(build_headers(false)?).build()?;
+        Ok(self)
+    }
+
+    // Reference data (symbols, currencies, fee rates) is then served from the cache
+    // until its TTL expires, other endpoints are never cached
+    pub fn with_response_cache(mut self, cache: ResponseCache) -> Self {
+        self.response_cache = Some(Arc::new(cache));
+        self
+    }
+
+    // Huobi allows 100 signed requests per 2 seconds for most endpoints
+    pub fn with_rate_limit(mut self, max_requests: usize, window: Duration) -> Self {
+        self.rate_limiter = Some(Arc::new(RateLimiter::new(max_requests, window)));
+        self
+    }
+
+    // Hosts outside the spot API, e.g. api.hbdm.com for swaps or the status page,
+    // have limits of their own and do not use up the budget of with_rate_limit
+    pub fn with_external_rate_limit(mut self, max_requests: usize, window: Duration) -> Self {
+        self.external_rate_limit = Some((max_requests, window));
+        self
+    }
+
+    // Bodies over max_bytes are not read to the end and fail with ResponseTooLarge.
+    // Streamed list responses are parsed as they come and are not bounded
+    pub fn with_max_response_size(mut self, max_bytes: usize) -> Self {
+        self.max_response_size = Some(max_bytes);
+        self
+    }
+
+    // Huobi rejects signed requests whose Timestamp is too far from its time, the skew is
+    // measured by Client::sync_time and Client::ping and on_skew is told of it in ms,
+    // positive when the local clock is ahead, as soon as it is over the threshold
+    pub fn with_skew_alert<F>(mut self, threshold: Duration, on_skew: F) -> Self
+    where
+        F: Fn(i64) + Send + Sync + 'static,
+    {
+        self.skew_alert = Some(SkewAlert {
+            threshold_ms: threshold.as_millis() as i64,
+            on_skew: Arc::new(on_skew),
+        });
+        self
+    }
+
+    pub(crate) fn log_policy(&self) -> &LogPolicy {
+        &self.log_policy
+    }
+
+    pub(crate) fn is_symbols_refresh_due(&self) -> bool {
+        match *self.symbols_refreshed_at.lock().unwrap() {
+            Some(last) => last.elapsed() >= REFRESH_INTERVAL,
+            None => true,
+        }
+    }
+
+    pub(crate) fn set_symbols_refreshed(&self) {
+        *self.symbols_refreshed_at.lock().unwrap() = Some(Instant::now());
+    }
+
+    pub(crate) fn check_skew(&self, skew_ms: i64) {
+        if let Some(alert) = &self.skew_alert {
+            if skew_ms.abs() > alert.threshold_ms {
+                (alert.on_skew)(skew_ms);
+            }
         }
     }
 
+    pub fn with_log_policy(mut self, log_policy: LogPolicy) -> Self {
+        self.log_policy = log_policy;
+        self
+    }
+
+    // Sees every request and response of this Client and of its clones made afterwards
+    pub fn with_interceptor<I: Interceptor + 'static>(mut self, interceptor: I) -> Self {
+        self.interceptors.push(Arc::new(interceptor));
+        self
+    }
+
+    // Every request goes out here, after the interceptors had their say.
+    // The body of the response is left unread
+    fn dispatch(
+        &self,
+        request: RequestParts,
+    ) -> APIResult<(RequestParts, reqwest::blocking::Response)> {
+        let mut attempt = 0;
+
+        loop {
+            let mut request = request.clone();
+            for interceptor in &self.interceptors {
+                interceptor.before_send(&mut request);
+            }
+
+            self.log_policy
+                .request(&request.method, &request.url, request.body.as_deref());
+
+            let mut builder = self
+                .http_client
+                .request(request.method.clone(), request.url.as_str())
+                .headers(request.headers.clone());
+            if let Some(body) = &request.body {
+                builder = builder.body(body.clone());
+            }
+
+            match builder.send().map_err(HuobiError::from) {
+                Ok(response) => return Ok((request, response)),
+                // Only a GET is safe to send twice
+                Err(HuobiError::Network { kind, why })
+                    if kind.is_retryable()
+                        && request.method == Method::GET
+                        && attempt < self.retries =>
+                {
+                    attempt += 1;
+                    self.log_policy
+                        .retry(attempt, self.retries, &request.url, &why);
+                    self.wait_rate_limit();
+                }
+                Err(e) => return Err(Box::new(e)),
+            }
+        }
+    }
+
+    fn after_recv(
+        &self,
+        request: &RequestParts,
+        status: StatusCode,
+        headers: &HeaderMap,
+        body: Option<&str>,
+    ) {
+        self.log_policy
+            .response(&request.method, &request.url, status, body);
+
+        let response = ResponseParts {
+            method: &request.method,
+            url: &request.url,
+            status,
+            headers,
+            body,
+        };
+
+        for interceptor in &self.interceptors {
+            interceptor.after_recv(&response);
+        }
+    }
+
+    fn send(&self, request: RequestParts) -> APIResult<(StatusCode, HeaderMap, String)> {
+        let (request, response) = self.dispatch(request)?;
+        let status = response.status();
+        let headers = response.headers().clone();
+        let body = read_body(response, self.max_response_size)?;
+
+        self.after_recv(&request, status, &headers, Some(&body));
+
+        Ok((status, headers, body))
+    }
+
+    // Runs request for every input on at most concurrency threads and returns the
+    // results in the input order. The threads share this Client, so its rate limiter
+    // still paces the requests
+    pub fn batch_get<I, T, F>(
+        &self,
+        inputs: &[I],
+        concurrency: usize,
+        request: F,
+    ) -> Vec<APIResult<T>>
+    where
+        I: Sync,
+        T: Send,
+        F: Fn(&Client, &I) -> APIResult<T> + Sync,
+    {
+        fan_out(inputs, concurrency, |input| request(self, input))
+            .into_iter()
+            .map(|result| result.map_err(|e| Box::new(e) as Box<dyn Error>))
+            .collect()
+    }
+
+    fn wait_rate_limit(&self) {
+        if let Some(rate_limiter) = &self.rate_limiter {
+            rate_limiter.acquire();
+        }
+    }
+
+    fn wait_external_rate_limit(&self, url: &str) {
+        let (max_requests, window) = match self.external_rate_limit {
+            Some(limit) => limit,
+            None => return,
+        };
+
+        let host = reqwest::Url::parse(url)
+            .ok()
+            .and_then(|url| url.host_str().map(String::from))
+            .unwrap_or_default();
+
+        let rate_limiter = self
+            .external_rate_limiters
+            .lock()
+            .unwrap()
+            .entry(host)
+            .or_insert_with(|| Arc::new(RateLimiter::new(max_requests, window)))
+            .clone();
+
+        rate_limiter.acquire();
+    }
+
+    pub fn last_response_meta(&self) -> Option<ResponseMeta> {
+        self.response_meta.lock().unwrap().clone()
+    }
+
+    fn update_response_meta(&self, headers: &HeaderMap) -> ResponseMeta {
+        let response_meta = response_meta(headers);
+
+        *self.response_meta.lock().unwrap() = Some(response_meta.clone());
+
+        response_meta
+    }
+
     pub fn build_request(parameters: &BTreeMap<String, String>) -> String {
         let mut request = String::new();
         for (key, value) in parameters {
@@ -40,7 +388,11 @@ impl Client {
         request
     }
 
-    pub fn get(&self, endpoint: &str, parameters: &BTreeMap<String, String>) -> APIResult<String> {
+    pub fn get<E: fmt::Display>(
+        &self,
+        endpoint: E,
+        parameters: &BTreeMap<String, String>,
+    ) -> APIResult<String> {
         let mut request_o = String::new();
         for (key, value) in parameters {
             let param = format!("{}={}&", key, value);
@@ -48,142 +400,543 @@ impl Client {
         }
         request_o.pop(); // remove last &
 
-        let request = format!("https://{}{}?{}", HUOBI_API_HOST, endpoint, request_o,);
+        let request = format!("https://{}{}?{}", self.host, endpoint, request_o,);
+
+        self.wait_rate_limit();
 
-        let body = reqwest::blocking::get(request.as_str())?.text()?;
+        let (status, _headers, body) = self.send(RequestParts::new(Method::GET, request))?;
+
+        check_ip_ban(status, &body)?;
 
         // check for errors
         let err_response: APIErrorResponse<serde_json::Value> =
             serde_json::from_str(body.as_str())?;
 
-        info!("err_response: {:?}", err_response);
+        if err_response.status.as_deref() == Some("error") {
+            return Err(api_error(&body, &err_response));
+        }
 
-        match &err_response.status {
-            Some(status) => {
-                if status == "error" {
-                    return Err(Box::new(HuobiError::ApiError(format!(
-                        "result dump: {:?}",
-                        err_response
-                    ))));
-                }
-            }
-            None => info!("err_response: {:?}", err_response),
+        Ok(body)
+    }
+
+    // GET of a full URL outside the Huobi API, e.g. the status page. The body is
+    // returned as is, it has no Huobi status to check
+    pub fn get_external(&self, url: &str) -> APIResult<String> {
+        self.wait_external_rate_limit(url);
+
+        let (status, _headers, body) = self.send(RequestParts::new(Method::GET, url.into()))?;
+
+        if !status.is_success() {
+            return Err(Box::new(HuobiError::ApiError(format!(
+                "HTTP {} from {}",
+                status, url
+            ))));
         }
 
         Ok(body)
     }
 
-    pub fn get_signed(
+    pub fn get_cached<E: fmt::Display>(
         &self,
-        endpoint: &str,
-        mut params: BTreeMap<String, String>,
+        endpoint: E,
+        parameters: &BTreeMap<String, String>,
     ) -> APIResult<String> {
-        params.insert("AccessKeyId".to_string(), self.api_key.clone());
-        params.insert("SignatureMethod".to_string(), "HmacSHA256".to_string());
-        params.insert("SignatureVersion".to_string(), "2".to_string());
-        params.insert("Timestamp".to_string(), get_timestamp());
+        let endpoint = endpoint.to_string();
+        let cache = match &self.response_cache {
+            Some(cache) => cache,
+            None => return self.get(&endpoint, parameters),
+        };
 
-        debug!("[Huobi] Make GET request params: {:?}", params);
+        let key = ResponseCache::key(&endpoint, parameters);
 
-        let params = build_query_string(params);
-        let signature = sign_hmac_sha256_base64(
-            &self.secret_key,
-            &format!("{}\n{}\n{}\n{}", "GET", HUOBI_API_HOST, endpoint, params,),
-        )
-        .to_string();
+        if let Some(body) = self.read_cache(cache, &key) {
+            return Ok(body);
+        }
 
-        let request = format!(
-            "https://{}{}?{}&Signature={}",
-            HUOBI_API_HOST,
+        let body = self.get(&endpoint, parameters)?;
+        self.write_cache(cache, &key, &body);
+
+        Ok(body)
+    }
+
+    // Same as get_cached but always asks Huobi, the cached body is replaced
+    pub fn get_refreshed<E: fmt::Display>(
+        &self,
+        endpoint: E,
+        parameters: &BTreeMap<String, String>,
+    ) -> APIResult<String> {
+        let endpoint = endpoint.to_string();
+        let body = self.get(&endpoint, parameters)?;
+
+        if let Some(cache) = &self.response_cache {
+            self.write_cache(cache, &ResponseCache::key(&endpoint, parameters), &body);
+        }
+
+        Ok(body)
+    }
+
+    // Signed data is per account, the access key is part of the cache key
+    pub fn get_signed_cached<E: fmt::Display>(
+        &self,
+        endpoint: E,
+        params: BTreeMap<String, String>,
+    ) -> APIResult<String> {
+        let endpoint = endpoint.to_string();
+        let cache = match &self.response_cache {
+            Some(cache) => cache,
+            None => return self.get_signed(&endpoint, params),
+        };
+
+        let mut key_params = params.clone();
+        key_params.insert("AccessKeyId".to_string(), self.api_key.clone());
+        let key = ResponseCache::key(&endpoint, &key_params);
+
+        if let Some(body) = self.read_cache(cache, &key) {
+            return Ok(body);
+        }
+
+        let body = self.get_signed(&endpoint, params)?;
+        self.write_cache(cache, &key, &body);
+
+        Ok(body)
+    }
+
+    // Keys of signed data carry the access key, they are logged redacted
+    fn read_cache(&self, cache: &ResponseCache, key: &str) -> Option<String> {
+        let (body, from_disk) = cache.lookup(key)?;
+
+        if from_disk {
+            self.log_policy.detail(format_args!(
+                "[Huobi] Response of {} is read from the disk cache",
+                redact_url(key)
+            ));
+        }
+
+        Some(body)
+    }
+
+    // The cache is an optimization, a failed write only costs a request later
+    fn write_cache(&self, cache: &ResponseCache, key: &str, body: &str) {
+        if let Err(e) = cache.store(key, body) {
+            self.log_policy.detail(format_args!(
+                "[Huobi] Can't write the cache file of {}: {}",
+                redact_url(key),
+                e
+            ));
+        }
+    }
+
+    // Huobi has two request times that are easy to mix up:
+    // - "Timestamp" is added by signed_query to every signed request, UTC formatted
+    //   to the second (2021-08-24T07:06:03) and covered by the signature
+    // - "ts" is a plain number of milliseconds, in the same unit as the ts of responses
+    //   and WS pings. None of the REST endpoints of this crate take it (every Endpoint,
+    //   the order placement paths, the linear swap market data and the status page
+    //   were checked), only the WS pong echoes it. insert_ts is for an endpoint that
+    //   comes to require it, never put the millis in "Timestamp"
+    pub fn ts_millis(&self) -> u64 {
+        (self.clock)().timestamp_millis() as u64
+    }
+
+    pub fn insert_ts(&self, params: &mut BTreeMap<String, String>) {
+        params.insert("ts".to_string(), self.ts_millis().to_string());
+    }
+
+    // Formatting shows up in profiles of fast order submission,
+    // so the string is formatted once per second and reused within it
+    fn signing_timestamp(&self) -> String {
+        let now = (self.clock)();
+        let second = now.timestamp();
+        let mut cache = self.timestamp_cache.lock().unwrap();
+
+        if cache.0 != second {
+            *cache = (second, format_timestamp(now));
+        }
+
+        cache.1.clone()
+    }
+
+    // Query string with the auth params and the signature appended
+    fn signed_query(
+        &self,
+        method: &str,
+        endpoint: &str,
+        mut params: BTreeMap<String, String>,
+    ) -> String {
+        params.insert("Timestamp".to_string(), self.signing_timestamp());
+
+        let (query, signature) = sign_request(
+            method,
+            &self.host,
             endpoint,
             params,
-            percent_encode(&signature.clone())
+            &self.api_key,
+            &self.secret_key,
         );
 
-        debug!("[Huobi] Make GET signed request: {:?}", request);
+        format!("{}&Signature={}", query, percent_encode(&signature))
+    }
+
+    // Full signed GET URL as get_signed would send it, without sending it. It holds the
+    // access key and a signature valid for a few minutes, but never the secret key
+    pub fn signed_url<E: fmt::Display>(
+        &self,
+        endpoint: E,
+        params: BTreeMap<String, String>,
+    ) -> APIResult<String> {
+        let endpoint = endpoint.to_string();
+
+        Ok(format!(
+            "https://{}{}?{}",
+            self.host,
+            endpoint,
+            self.signed_query("GET", &endpoint, params)
+        ))
+    }
+
+    // Signed after the rate limit wait, right before sending, so the Timestamp
+    // is not already stale when the request leaves
+    fn wait_and_sign(
+        &self,
+        method: Method,
+        endpoint: &str,
+        params: BTreeMap<String, String>,
+    ) -> String {
+        self.wait_rate_limit();
+
+        format!(
+            "https://{}{}?{}",
+            self.host,
+            endpoint,
+            self.signed_query(method.as_str(), endpoint, params)
+        )
+    }
+
+    pub fn get_signed<E: fmt::Display>(
+        &self,
+        endpoint: E,
+        params: BTreeMap<String, String>,
+    ) -> APIResult<String> {
+        let (body, _response_meta) = self.get_signed_with_meta(endpoint, params)?;
+
+        Ok(body)
+    }
+
+    pub fn get_signed_params<E: fmt::Display, P: Serialize + ?Sized>(
+        &self,
+        endpoint: E,
+        params: &P,
+    ) -> APIResult<String> {
+        self.get_signed(endpoint, to_params(params)?)
+    }
+
+    pub fn get_signed_version(
+        &self,
+        version: ApiVersion,
+        path: &str,
+        params: BTreeMap<String, String>,
+    ) -> APIResult<String> {
+        self.get_signed(version.path(path), params)
+    }
+
+    pub fn get_signed_with_meta<E: fmt::Display>(
+        &self,
+        endpoint: E,
+        params: BTreeMap<String, String>,
+    ) -> APIResult<(String, ResponseMeta)> {
+        let endpoint = endpoint.to_string();
 
-        let response = reqwest::blocking::get(request.as_str())?;
-        let body = response.text()?;
+        let request = self.wait_and_sign(Method::GET, &endpoint, params);
 
-        debug!("[Huobi] GET responce body: {:?}", body);
+        let (status, headers, body) = self.send(RequestParts::new(Method::GET, request))?;
+        let response_meta = self.update_response_meta(&headers);
+
+        check_ip_ban(status, &body)?;
 
         // check for errors
         let err_response: APIErrorResponse<serde_json::Value> =
             serde_json::from_str(body.as_str())?;
 
-        match &err_response.status {
-            Some(status) => {
-                if status == "error" {
-                    return Err(Box::new(HuobiError::ApiError(format!(
-                        "result dump: {:?}",
-                        err_response
-                    ))));
-                }
+        if err_response.status.as_deref() == Some("error") {
+            return Err(api_error(&body, &err_response));
+        }
+
+        Ok((body, response_meta))
+    }
+
+    // Signed GET for large list responses: the body is parsed while it is downloaded
+    // and every item of "data" goes to on_item instead of being collected
+    pub fn get_signed_each<E, T, F>(
+        &self,
+        endpoint: E,
+        params: BTreeMap<String, String>,
+        on_item: F,
+    ) -> APIResult<StreamedList>
+    where
+        E: fmt::Display,
+        T: DeserializeOwned,
+        F: FnMut(T),
+    {
+        let endpoint = endpoint.to_string();
+
+        let request = self.wait_and_sign(Method::GET, &endpoint, params);
+
+        let (request, response) = self.dispatch(RequestParts::new(Method::GET, request))?;
+        self.update_response_meta(response.headers());
+        let status = response.status();
+        self.after_recv(&request, status, response.headers(), None);
+
+        // Error bodies are small
+        if !status.is_success() {
+            let body = read_body(response, self.max_response_size)?;
+
+            check_ip_ban(status, &body)?;
+
+            return Err(Box::new(HuobiError::ApiError(format!(
+                "HTTP {}: {}",
+                status, body
+            ))));
+        }
+
+        for_each_data(BufReader::new(response), on_item)
+    }
+
+    // Signed request to any endpoint, with the same error handling as the helpers.
+    // Huobi only has GET and POST endpoints, a POST without body sends {}
+    pub fn send_signed<E: fmt::Display>(
+        &self,
+        method: Method,
+        endpoint: E,
+        params: BTreeMap<String, String>,
+        body: Option<&serde_json::Value>,
+    ) -> APIResult<String> {
+        match (method, body) {
+            (Method::GET, None) => self.get_signed(endpoint, params),
+            (Method::GET, Some(_)) => Err(Box::new(HuobiError::ApiError(format!(
+                "GET {} can't have a body",
+                endpoint
+            )))),
+            (Method::POST, body) => {
+                let empty = serde_json::json!({});
+                self.post_signed(endpoint, params, body.unwrap_or(&empty))
             }
-            None => info!("err_response: {:?}", err_response),
+            (method, _) => Err(Box::new(HuobiError::ApiError(format!(
+                "{} {} is not supported, Huobi only has GET and POST endpoints",
+                method, endpoint
+            )))),
         }
+    }
+
+    pub fn post_signed<E: fmt::Display, T: Serialize + ?Sized>(
+        &self,
+        endpoint: E,
+        params: BTreeMap<String, String>,
+        payload: &T,
+    ) -> APIResult<String> {
+        let (body, _response_meta) = self.post_signed_with_meta(endpoint, params, payload)?;
 
         Ok(body)
     }
 
-    pub fn post_signed<T: Serialize + ?Sized>(
+    pub fn post_signed_version<T: Serialize + ?Sized>(
         &self,
-        endpoint: &str,
-        mut params: BTreeMap<String, String>,
+        version: ApiVersion,
+        path: &str,
+        params: BTreeMap<String, String>,
         payload: &T,
     ) -> APIResult<String> {
-        params.insert("AccessKeyId".to_string(), self.api_key.clone());
-        params.insert("SignatureMethod".to_string(), "HmacSHA256".to_string());
-        params.insert("SignatureVersion".to_string(), "2".to_string());
-        params.insert("Timestamp".to_string(), get_timestamp());
-
-        let params = build_query_string(params);
-        let signature = sign_hmac_sha256_base64(
-            &self.secret_key,
-            &format!("{}\n{}\n{}\n{}", "POST", HUOBI_API_HOST, endpoint, params,),
-        )
-        .to_string();
+        self.post_signed(version.path(path), params, payload)
+    }
 
-        let request = format!(
-            "https://{}{}?{}&Signature={}",
-            HUOBI_API_HOST,
-            endpoint,
-            params,
-            percent_encode(&signature.clone())
-        );
+    pub fn post_signed_with_meta<E: fmt::Display, T: Serialize + ?Sized>(
+        &self,
+        endpoint: E,
+        params: BTreeMap<String, String>,
+        payload: &T,
+    ) -> APIResult<(String, ResponseMeta)> {
+        let endpoint = endpoint.to_string();
 
-        debug!("[Huobi] Make POST signed request: {:?}", request);
+        let request = self.wait_and_sign(Method::POST, &endpoint, params);
 
-        let client = reqwest::blocking::Client::new();
-        let response = client
-            .post(request.as_str())
-            .headers(build_headers(true)?)
-            .json(&payload)
-            .send();
+        let mut request = RequestParts::new(Method::POST, request);
+        request.headers = build_headers(true)?;
+        request.body = Some(serde_json::to_string(payload)?);
 
-        let body = response?.text()?;
+        let (status, headers, body) = self.send(request)?;
+        let response_meta = self.update_response_meta(&headers);
 
-        debug!("[Huobi] POST responce body: {:?}", body.clone());
+        check_ip_ban(status, &body)?;
 
         // check for errors
         let err_response: APIErrorResponse<serde_json::Value> =
             serde_json::from_str(body.as_str())?;
 
-        debug!("err_response: {:?}", err_response);
+        if err_response.status.as_deref() == Some("error") {
+            return Err(api_error(&body, &err_response));
+        }
 
-        match &err_response.status {
-            Some(status) => {
-                if status == "error" {
-                    return Err(Box::new(HuobiError::ApiError(format!(
-                        "result dump: {:?}",
-                        err_response
-                    ))));
-                }
-            }
-            None => info!("err_response: {:?}", err_response),
+        Ok((body, response_meta))
+    }
+}
+
+// Box<dyn Error> is not Send, so an error crosses back from a worker thread as a
+// HuobiError: kept as is when it is one, as its text otherwise
+fn fan_out<I, T, F>(inputs: &[I], concurrency: usize, f: F) -> Vec<Result<T, HuobiError>>
+where
+    I: Sync,
+    T: Send,
+    F: Fn(&I) -> APIResult<T> + Sync,
+{
+    let next = AtomicUsize::new(0);
+    let results: Vec<Mutex<Option<Result<T, HuobiError>>>> =
+        inputs.iter().map(|_| Mutex::new(None)).collect();
+
+    thread::scope(|scope| {
+        for _ in 0..concurrency.max(1).min(inputs.len()) {
+            scope.spawn(|| loop {
+                let index = next.fetch_add(1, Ordering::Relaxed);
+                let input = match inputs.get(index) {
+                    Some(input) => input,
+                    None => break,
+                };
+
+                let result = f(input).map_err(|e| match e.downcast::<HuobiError>() {
+                    Ok(e) => *e,
+                    Err(e) => HuobiError::ApiError(e.to_string()),
+                });
+
+                *results[index].lock().unwrap() = Some(result);
+            });
         }
+    });
 
-        Ok(body)
+    results
+        .into_iter()
+        .map(|result| result.into_inner().unwrap().unwrap())
+        .collect()
+}
+
+fn read_body(response: reqwest::blocking::Response, limit: Option<usize>) -> APIResult<String> {
+    let limit = match limit {
+        Some(limit) => limit,
+        None => return Ok(response.text().map_err(HuobiError::from)?),
+    };
+
+    if response.content_length().unwrap_or_default() > limit as u64 {
+        return Err(Box::new(HuobiError::ResponseTooLarge { limit }));
+    }
+
+    // One byte past the limit tells a body of exactly the limit from a larger one
+    let mut body = Vec::new();
+    response
+        .take(limit as u64 + 1)
+        .read_to_end(&mut body)
+        .map_err(|e| HuobiError::Network {
+            kind: NetworkErrorKind::Decode,
+            why: e.to_string(),
+        })?;
+
+    if body.len() > limit {
+        return Err(Box::new(HuobiError::ResponseTooLarge { limit }));
+    }
+
+    Ok(String::from_utf8(body)?)
+}
+
+// Huobi answers an order with an already used client-order-id with this code
+const DUPLICATE_CLIENT_ORDER_ID: &str = "invalid-client-order-id";
+
+// Orders on a symbol that is pre-open, halted or suspended for maintenance
+const MARKET_NOT_OPEN: [&str; 2] = ["order-disabled", "base-symbol-trade-disabled"];
+
+// Lookup of an order id Huobi does not have
+const ORDER_NOT_FOUND: &str = "base-record-invalid";
+
+// Error of a response with status "error". The v1 "err-code" is a string
+// that APIErrorResponse does not parse, so it is read from the body
+fn api_error(body: &str, err_response: &APIErrorResponse<serde_json::Value>) -> Box<dyn Error> {
+    let error: serde_json::Value = serde_json::from_str(body).unwrap_or_default();
+    let err_code = error["err-code"].as_str().unwrap_or_default();
+    let err_msg = error["err-msg"].as_str().unwrap_or_default();
+
+    if err_code == DUPLICATE_CLIENT_ORDER_ID {
+        return Box::new(OrderError::DuplicateClientOrderId(err_msg.to_string()));
+    }
+
+    if MARKET_NOT_OPEN.contains(&err_code) {
+        return Box::new(OrderError::MarketNotOpen(err_msg.to_string()));
+    }
+
+    if err_code == ORDER_NOT_FOUND {
+        return Box::new(OrderError::NotFound(err_msg.to_string()));
+    }
+
+    // order-limitorder-price-precision-error, order-limitorder-amount-precision-error...
+    if err_code.starts_with("order-") && err_code.ends_with("-precision-error") {
+        let field = if err_code.contains("price") {
+            "price"
+        } else {
+            "amount"
+        };
+
+        return Box::new(OrderError::PrecisionExceeded {
+            field: field.to_string(),
+            allowed: precision_scale(err_msg),
+        });
+    }
+
+    Box::new(HuobiError::ApiError(format!(
+        "result dump: {:?}",
+        err_response
+    )))
+}
+
+// Decimals allowed from an err-msg like "limit order price precision error, scale: `2`"
+fn precision_scale(err_msg: &str) -> Option<u8> {
+    let (_, scale) = err_msg.split_once("scale:")?;
+
+    scale.trim().trim_matches('`').parse().ok()
+}
+
+// Checks of get_signed for a body received by another client, e.g. the async one
+pub(crate) fn check_response(status: StatusCode, body: &str) -> APIResult<()> {
+    check_ip_ban(status, body)?;
+
+    let err_response: APIErrorResponse<serde_json::Value> = serde_json::from_str(body)?;
+
+    if err_response.status.as_deref() == Some("error") {
+        return Err(api_error(body, &err_response));
+    }
+
+    Ok(())
+}
+
+// A banned IP gets CloudFront's 403 HTML page instead of a JSON error
+fn check_ip_ban(status: StatusCode, body: &str) -> Result<(), HuobiError> {
+    let is_html = body.trim_start().starts_with('<');
+    let is_cloudfront = body.to_lowercase().contains("cloudfront");
+
+    if is_html && (status == StatusCode::FORBIDDEN || is_cloudfront) {
+        return Err(HuobiError::IpBanned(format!(
+            "HTTP {}, stop sending requests for a while",
+            status
+        )));
+    }
+
+    Ok(())
+}
+
+fn response_meta(headers: &HeaderMap) -> ResponseMeta {
+    let header_number = |name: &str| {
+        headers
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.trim().parse::<u64>().ok())
+    };
+
+    ResponseMeta {
+        requests_remain: header_number("x-hb-ratelimit-requests-remain"),
+        requests_expire: header_number("x-hb-ratelimit-requests-expire"),
     }
 }
 
@@ -195,6 +948,82 @@ pub fn build_query_string(parameters: BTreeMap<String, String>) -> String {
         .join("&")
 }
 
+// Signature of a request without a Client, e.g. for a signing service: returns the
+// query string with the auth params and the base64 signature. The request to send is
+// "https://{host}{endpoint}?{query}&Signature={percent_encode(signature)}", the same as
+// the Client sends. Timestamp is the current UTC time unless it is already in params
+pub fn sign_request(
+    method: &str,
+    host: &str,
+    endpoint: &str,
+    mut params: BTreeMap<String, String>,
+    api_key: &str,
+    secret_key: &str,
+) -> (String, String) {
+    params.insert("AccessKeyId".to_string(), api_key.to_string());
+    params.insert("SignatureMethod".to_string(), "HmacSHA256".to_string());
+    params.insert("SignatureVersion".to_string(), "2".to_string());
+    params
+        .entry("Timestamp".to_string())
+        .or_insert_with(get_timestamp);
+
+    let query = build_query_string(params);
+    let signature = sign_hmac_sha256_base64(
+        secret_key,
+        &format!("{}\n{}\n{}\n{}", method, host, endpoint, query),
+    );
+
+    (query, signature)
+}
+
+// Typed params to the signing map. The map is sorted by key, so the signed query
+// does not depend on the field order of the struct. None fields are skipped.
+// Huobi takes lists as comma separated values (symbols=btcusdt,ethusdt), never as
+// repeated keys, so a list field is joined with commas
+pub fn to_params<T: Serialize + ?Sized>(params: &T) -> APIResult<BTreeMap<String, String>> {
+    let fields = match serde_json::to_value(params)? {
+        serde_json::Value::Object(fields) => fields,
+        value => {
+            return Err(Box::new(HuobiError::ApiError(format!(
+                "params should be a struct or a map, got {}",
+                value
+            ))))
+        }
+    };
+
+    let mut parameters: BTreeMap<String, String> = BTreeMap::new();
+
+    for (key, value) in fields {
+        let value = match value {
+            serde_json::Value::Null => continue,
+            serde_json::Value::Array(values) => {
+                let values = values
+                    .into_iter()
+                    .map(|value| param_value(&key, value))
+                    .collect::<APIResult<Vec<String>>>()?;
+                values.join(",")
+            }
+            value => param_value(&key, value)?,
+        };
+
+        parameters.insert(key, value);
+    }
+
+    Ok(parameters)
+}
+
+fn param_value(key: &str, value: serde_json::Value) -> APIResult<String> {
+    match value {
+        serde_json::Value::String(value) => Ok(value),
+        serde_json::Value::Number(value) => Ok(value.to_string()),
+        serde_json::Value::Bool(value) => Ok(value.to_string()),
+        value => Err(Box::new(HuobiError::ApiError(format!(
+            "param {} should be a string, a number, a bool or a list of them, got {}",
+            key, value
+        )))),
+    }
+}
+
 pub fn sign_hmac_sha256_base64(secret: &str, digest: &str) -> String {
     use data_encoding::BASE64;
 
@@ -215,10 +1044,11 @@ pub fn percent_encode(source: &str) -> String {
 }
 
 pub fn get_timestamp() -> String {
-    let utc_time = chrono::Utc::now();
-    let formatted_time = utc_time.format("%Y-%m-%dT%H:%M:%S").to_string();
+    format_timestamp(Utc::now())
+}
 
-    formatted_time
+pub fn format_timestamp(utc_time: DateTime<Utc>) -> String {
+    utc_time.format("%Y-%m-%dT%H:%M:%S").to_string()
 }
 
 pub fn build_headers(post_method: bool) -> APIResult<HeaderMap> {
@@ -237,3 +1067,572 @@ pub fn build_headers(post_method: bool) -> APIResult<HeaderMap> {
 
     Ok(custom_headers)
 }
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use chrono::TimeZone;
+
+    fn pinned_client() -> Client {
+        Client::new("test-access-key", "test-secret-key").with_clock(|| {
+            DateTime::parse_from_rfc3339("2021-08-24T07:06:03Z")
+                .unwrap()
+                .with_timezone(&Utc)
+        })
+    }
+
+    #[test]
+    fn skew_alert_over_threshold() {
+        let alerts = Arc::new(Mutex::new(Vec::new()));
+        let alerted = alerts.clone();
+        let client = Client::new("test-access-key", "test-secret-key")
+            .with_skew_alert(Duration::from_secs(1), move |skew| {
+                alerted.lock().unwrap().push(skew)
+            });
+
+        client.check_skew(400);
+        client.check_skew(-1000);
+        client.check_skew(-1500);
+        client.check_skew(2000);
+
+        assert_eq!(*alerts.lock().unwrap(), vec![-1500, 2000]);
+    }
+
+    #[test]
+    fn signed_query_with_pinned_clock() {
+        let query = pinned_client().signed_query("GET", "/v1/account/accounts", BTreeMap::new());
+
+        assert_eq!(
+            query,
+            "AccessKeyId=test-access-key&SignatureMethod=HmacSHA256&SignatureVersion=2\
+             &Timestamp=2021-08-24T07%3A06%3A03\
+             &Signature=15pyU7XBq%2BVXf2iHQ4qmOES%2F8U3xNzoVG1olc7PDl1o%3D"
+        );
+    }
+
+    #[test]
+    fn signed_url_with_pinned_clock() {
+        let url = pinned_client()
+            .signed_url("/v1/account/accounts", BTreeMap::new())
+            .unwrap();
+
+        assert_eq!(
+            url,
+            "https://api.huobi.pro/v1/account/accounts?AccessKeyId=test-access-key\
+             &SignatureMethod=HmacSHA256&SignatureVersion=2&Timestamp=2021-08-24T07%3A06%3A03\
+             &Signature=15pyU7XBq%2BVXf2iHQ4qmOES%2F8U3xNzoVG1olc7PDl1o%3D"
+        );
+        assert!(!url.contains("test-secret-key"));
+    }
+
+    #[test]
+    fn signing_timestamp_follows_clock() {
+        let seconds = Arc::new(Mutex::new(1629788763));
+        let clock_seconds = seconds.clone();
+        let client = Client::new("test-access-key", "test-secret-key").with_clock(move || {
+            Utc.timestamp_opt(*clock_seconds.lock().unwrap(), 0)
+                .unwrap()
+        });
+
+        assert_eq!(client.signing_timestamp(), "2021-08-24T07:06:03");
+        assert_eq!(client.signing_timestamp(), "2021-08-24T07:06:03");
+
+        *seconds.lock().unwrap() += 1;
+
+        assert_eq!(client.signing_timestamp(), "2021-08-24T07:06:04");
+    }
+
+    #[test]
+    #[ignore]
+    // Bench: cargo test --release signing_timestamp_bench -- --ignored --nocapture
+    fn signing_timestamp_bench() {
+        use std::time::Instant;
+
+        const CALLS: u32 = 100_000;
+        let client = Client::new("test-access-key", "test-secret-key");
+
+        let start = Instant::now();
+        for _ in 0..CALLS {
+            std::hint::black_box(format_timestamp(Utc::now()));
+        }
+        let formatted = start.elapsed();
+
+        let start = Instant::now();
+        for _ in 0..CALLS {
+            std::hint::black_box(client.signing_timestamp());
+        }
+        let cached = start.elapsed();
+
+        println!(
+            "format every call: {:?}/call, cached: {:?}/call",
+            formatted / CALLS,
+            cached / CALLS
+        );
+
+        assert!(cached < formatted);
+    }
+
+    #[test]
+    fn sign_request_matches_client() {
+        let mut params: BTreeMap<String, String> = BTreeMap::new();
+        params.insert("Timestamp".to_string(), "2021-08-24T07:06:03".to_string());
+
+        let (query, signature) = sign_request(
+            "GET",
+            "api.huobi.pro",
+            "/v1/account/accounts",
+            params,
+            "test-access-key",
+            "test-secret-key",
+        );
+
+        assert_eq!(signature, "15pyU7XBq+VXf2iHQ4qmOES/8U3xNzoVG1olc7PDl1o=");
+        assert_eq!(
+            format!("{}&Signature={}", query, percent_encode(&signature)),
+            pinned_client().signed_query("GET", "/v1/account/accounts", BTreeMap::new())
+        );
+    }
+
+    #[test]
+    fn sent_timestamp_is_the_signed_one() {
+        // Every reading of the clock is one minute later
+        let minutes = Arc::new(Mutex::new(0));
+        let clock_minutes = minutes.clone();
+        let client = Client::new("test-access-key", "test-secret-key").with_clock(move || {
+            let mut minutes = clock_minutes.lock().unwrap();
+            *minutes += 1;
+            Utc.timestamp_opt(1629788763 + *minutes * 60, 0).unwrap()
+        });
+
+        let query = client.signed_query("GET", "/v1/account/accounts", BTreeMap::new());
+
+        let param = |name: &str| {
+            query
+                .split('&')
+                .find_map(|pair| pair.strip_prefix(&format!("{}=", name)))
+                .unwrap()
+                .replace("%3A", ":")
+                .replace("%2B", "+")
+                .replace("%2F", "/")
+                .replace("%3D", "=")
+        };
+
+        let mut params: BTreeMap<String, String> = BTreeMap::new();
+        params.insert("Timestamp".to_string(), param("Timestamp"));
+
+        let (_query, signature) = sign_request(
+            "GET",
+            HUOBI_API_HOST,
+            "/v1/account/accounts",
+            params,
+            "test-access-key",
+            "test-secret-key",
+        );
+
+        assert_eq!(param("Signature"), signature);
+        assert_eq!(*minutes.lock().unwrap(), 1);
+    }
+
+    // Serves one response to the first connection, the body is not announced
+    // with a content-length so it is read until the connection is closed
+    fn serve_once(body: &'static str) -> String {
+        use std::io::Write;
+        use std::net::TcpListener;
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+
+        std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = [0; 4096];
+            let _ = stream.read(&mut request);
+            let _ = write!(
+                stream,
+                "HTTP/1.1 200 OK\r\nconnection: close\r\n\r\n{}",
+                body
+            );
+        });
+
+        format!("http://{}/", address)
+    }
+
+    #[test]
+    fn response_over_the_limit() {
+        let client = Client::new("test-access-key", "test-secret-key").with_max_response_size(16);
+
+        let err = client
+            .get_external(&serve_once(r#"{"status":"ok","data":[1,2,3,4,5,6,7,8]}"#))
+            .unwrap_err();
+        match err.downcast_ref::<HuobiError>() {
+            Some(HuobiError::ResponseTooLarge { limit }) => assert_eq!(*limit, 16),
+            other => panic!("unexpected error {:?}", other),
+        }
+
+        let body = client
+            .get_external(&serve_once(r#"{"status":"ok"}"#))
+            .unwrap();
+        assert_eq!(body, r#"{"status":"ok"}"#);
+    }
+
+    #[test]
+    fn external_hosts_have_their_own_rate_limit() {
+        let client = Client::new("test-access-key", "test-secret-key")
+            .with_rate_limit(1, Duration::from_secs(60))
+            .with_external_rate_limit(1, Duration::from_millis(200));
+        let started_at = Instant::now();
+
+        for _ in 0..2 {
+            client
+                .get_external(&serve_once(r#"{"status":"ok"}"#))
+                .unwrap();
+        }
+
+        // The second request waits for the external window, not for the spot one
+        let elapsed = started_at.elapsed();
+        assert!(elapsed >= Duration::from_millis(200));
+        assert!(elapsed < Duration::from_secs(30));
+    }
+
+    #[test]
+    fn batch_get_is_bounded_and_ordered() {
+        let client = Client::new("test-access-key", "test-secret-key");
+        let running = AtomicUsize::new(0);
+        let max_running = AtomicUsize::new(0);
+        let inputs: Vec<u64> = (0..20).collect();
+
+        let results = client.batch_get(&inputs, 4, |_client, input| {
+            let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+            max_running.fetch_max(now, Ordering::SeqCst);
+            thread::sleep(Duration::from_millis(5));
+            running.fetch_sub(1, Ordering::SeqCst);
+
+            if *input == 7 {
+                return Err(Box::new(HuobiError::ApiError("no such symbol".into())));
+            }
+            Ok(input * 10)
+        });
+
+        assert!(max_running.load(Ordering::SeqCst) <= 4);
+        assert_eq!(results.len(), 20);
+        for (input, result) in inputs.iter().zip(results) {
+            match result {
+                Ok(value) => assert_eq!(value, input * 10),
+                Err(e) => {
+                    assert_eq!(*input, 7);
+                    assert!(matches!(
+                        e.downcast_ref::<HuobiError>(),
+                        Some(HuobiError::ApiError(_))
+                    ));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn send_signed_rejects_unsupported_requests() {
+        let client = Client::new("test-access-key", "test-secret-key");
+        let body = serde_json::json!({ "currency": "usdt" });
+
+        assert!(client
+            .send_signed(
+                Method::GET,
+                "/v1/account/accounts",
+                BTreeMap::new(),
+                Some(&body)
+            )
+            .is_err());
+        assert!(client
+            .send_signed(Method::DELETE, "/v1/order/orders/1", BTreeMap::new(), None)
+            .is_err());
+    }
+
+    #[test]
+    fn client_config_from_file() {
+        let config: ClientConfig =
+            serde_json::from_str(r#"{"host":"api-aws.huobi.pro","retries":2}"#).unwrap();
+
+        assert_eq!(config.host, "api-aws.huobi.pro");
+        assert_eq!(config.retries, 2);
+        assert_eq!(config.timeout_ms, ClientConfig::default().timeout_ms);
+        assert_eq!(config.user_agent, "rs");
+
+        let saved = serde_json::to_string(&config).unwrap();
+        assert_eq!(
+            serde_json::from_str::<ClientConfig>(&saved).unwrap(),
+            config
+        );
+    }
+
+    #[test]
+    fn get_is_retried_on_connect_error() {
+        let urls = Arc::new(Mutex::new(Vec::new()));
+        let config = ClientConfig {
+            retries: 2,
+            ..ClientConfig::default()
+        };
+        let client = Client::from_config("test-access-key", "test-secret-key", &config)
+            .unwrap()
+            .with_interceptor(RecordUrls(urls.clone()));
+
+        assert!(client.get_external("http://127.0.0.1:1/status").is_err());
+        assert_eq!(urls.lock().unwrap().len(), 3);
+    }
+
+    struct RecordUrls(Arc<Mutex<Vec<String>>>);
+
+    impl Interceptor for RecordUrls {
+        fn before_send(&self, req: &mut RequestParts) {
+            self.0.lock().unwrap().push(req.url.clone());
+            req.url = "http://127.0.0.1:1/rewritten".into();
+        }
+    }
+
+    #[test]
+    fn interceptor_sees_the_request() {
+        let urls = Arc::new(Mutex::new(Vec::new()));
+        let client = Client::new("test-access-key", "test-secret-key")
+            .with_interceptor(RecordUrls(urls.clone()));
+
+        // Nothing listens on port 1, only the request side runs
+        let err = client
+            .get_external("http://127.0.0.1:1/status")
+            .unwrap_err();
+
+        assert_eq!(*urls.lock().unwrap(), vec!["http://127.0.0.1:1/status"]);
+        match err.downcast_ref::<HuobiError>() {
+            Some(HuobiError::Network { kind, .. }) => assert_eq!(*kind, NetworkErrorKind::Connect),
+            other => panic!("unexpected error {:?}", other),
+        }
+    }
+
+    #[test]
+    fn ts_param_is_distinct_from_signing_timestamp() {
+        let client = pinned_client();
+        let mut params: BTreeMap<String, String> = BTreeMap::new();
+
+        client.insert_ts(&mut params);
+
+        assert_eq!(params.get("ts").unwrap(), "1629788763000");
+
+        let query = client.signed_query("GET", "/v1/account/accounts", params);
+
+        assert!(query.contains("&Timestamp=2021-08-24T07%3A06%3A03&ts=1629788763000&"));
+    }
+
+    #[derive(Serialize)]
+    struct HistoryParams {
+        symbol: String,
+        #[serde(rename = "start-time")]
+        start_time: u64,
+        size: Option<u32>,
+        direct: Option<String>,
+    }
+
+    // Same fields declared in another order
+    #[derive(Serialize)]
+    struct ReorderedHistoryParams {
+        direct: Option<String>,
+        size: Option<u32>,
+        #[serde(rename = "start-time")]
+        start_time: u64,
+        symbol: String,
+    }
+
+    #[test]
+    fn typed_params_signature_ignores_field_order() {
+        let params = to_params(&HistoryParams {
+            symbol: "btcusdt".to_string(),
+            start_time: 1629788763750,
+            size: Some(100),
+            direct: None,
+        })
+        .unwrap();
+        let reordered = to_params(&ReorderedHistoryParams {
+            direct: None,
+            size: Some(100),
+            start_time: 1629788763750,
+            symbol: "btcusdt".to_string(),
+        })
+        .unwrap();
+
+        assert_eq!(
+            params.keys().collect::<Vec<_>>(),
+            vec!["size", "start-time", "symbol"]
+        );
+
+        let client = pinned_client();
+
+        assert_eq!(
+            client.signed_query("GET", "/v1/order/history", params),
+            client.signed_query("GET", "/v1/order/history", reordered)
+        );
+    }
+
+    #[test]
+    fn typed_params_reject_nested_values() {
+        let params = serde_json::json!({ "filter": { "symbol": "btcusdt" } });
+        assert!(to_params(&params).is_err());
+
+        let params = serde_json::json!({ "symbols": [["btcusdt"], ["ethusdt"]] });
+        assert!(to_params(&params).is_err());
+    }
+
+    #[derive(Serialize)]
+    struct FeeRateParams {
+        symbols: Vec<&'static str>,
+    }
+
+    #[test]
+    fn list_param_signs_what_is_sent() {
+        let params = to_params(&FeeRateParams {
+            symbols: vec!["btcusdt", "ethusdt"],
+        })
+        .unwrap();
+        assert_eq!(params.get("symbols").unwrap(), "btcusdt,ethusdt");
+
+        let client = pinned_client();
+        let query = client.signed_query("GET", "/v2/reference/transact-fee-rate", params);
+
+        // The comma is encoded the same way in the signed payload and in the URL
+        let (sent, signature) = query.split_at(query.find("&Signature=").unwrap());
+        assert!(sent.contains("symbols=btcusdt%2Cethusdt"));
+        assert_eq!(
+            signature.trim_start_matches("&Signature="),
+            percent_encode(&sign_hmac_sha256_base64(
+                "test-secret-key",
+                &format!(
+                    "GET\n{}\n/v2/reference/transact-fee-rate\n{}",
+                    HUOBI_API_HOST, sent
+                )
+            ))
+        );
+    }
+
+    #[test]
+    fn client_from_custom_builder() {
+        let builder = reqwest::blocking::ClientBuilder::new()
+            .timeout(Duration::from_secs(5))
+            .pool_max_idle_per_host(2);
+
+        assert!(Client::new("test-access-key", "test-secret-key")
+            .with_client_builder(builder)
+            .is_ok());
+    }
+
+    const CLOUDFRONT_403: &str = r#"<!DOCTYPE HTML PUBLIC "-//W3C//DTD HTML 4.01 Transitional//EN" "http://www.w3.org/TR/html4/loose.dtd">
+<HTML><HEAD><META HTTP-EQUIV="Content-Type" CONTENT="text/html; charset=iso-8859-1">
+<TITLE>ERROR: The request could not be satisfied</TITLE>
+</HEAD><BODY>
+<H1>403 ERROR</H1>
+<H2>The request could not be satisfied.</H2>
+Generated by cloudfront (CloudFront)
+</BODY></HTML>"#;
+
+    #[test]
+    fn ip_ban_detected() {
+        match check_ip_ban(StatusCode::FORBIDDEN, CLOUDFRONT_403) {
+            Err(HuobiError::IpBanned(_)) => {}
+            _ => panic!("IP ban is not detected"),
+        }
+    }
+
+    #[test]
+    fn ip_ban_not_detected_for_json() {
+        let body = r#"{"status":"error","err-code":"api-signature-not-valid","err-msg":"Signature not valid","data":null}"#;
+
+        assert!(check_ip_ban(StatusCode::OK, body).is_ok());
+        assert!(check_ip_ban(StatusCode::FORBIDDEN, body).is_ok());
+    }
+
+    #[test]
+    fn duplicate_client_order_id_error() {
+        let body = r#"{"status":"error","err-code":"invalid-client-order-id","err-msg":"Invalid client order id","data":null}"#;
+        let err_response = serde_json::from_str(body).unwrap();
+
+        match api_error(body, &err_response).downcast_ref::<OrderError>() {
+            Some(OrderError::DuplicateClientOrderId(_)) => {}
+            _ => panic!("duplicate client-order-id is not detected"),
+        }
+
+        let body = r#"{"status":"error","err-code":"api-signature-not-valid","err-msg":"Signature not valid","data":null}"#;
+        let err_response = serde_json::from_str(body).unwrap();
+
+        assert!(api_error(body, &err_response)
+            .downcast_ref::<HuobiError>()
+            .is_some());
+    }
+
+    #[test]
+    fn market_not_open_error() {
+        let body = r#"{"status":"error","err-code":"order-disabled","err-msg":"The trading pair is pending and it is not allowed to place an order","data":null}"#;
+        let err_response = serde_json::from_str(body).unwrap();
+
+        match api_error(body, &err_response).downcast_ref::<OrderError>() {
+            Some(OrderError::MarketNotOpen(why)) => assert!(why.contains("pending")),
+            _ => panic!("market not open is not detected"),
+        }
+    }
+
+    #[test]
+    fn order_not_found_error() {
+        let body = r#"{"status":"error","err-code":"base-record-invalid","err-msg":"record invalid","data":null}"#;
+        let err_response = serde_json::from_str(body).unwrap();
+
+        match api_error(body, &err_response).downcast_ref::<OrderError>() {
+            Some(OrderError::NotFound(why)) => assert_eq!(why, "record invalid"),
+            _ => panic!("unknown order is not detected"),
+        }
+    }
+
+    #[test]
+    fn precision_error() {
+        let body = r#"{"status":"error","err-code":"order-limitorder-price-precision-error","err-msg":"limit order price precision error, scale: `2`","data":null}"#;
+        let err_response = serde_json::from_str(body).unwrap();
+
+        match api_error(body, &err_response).downcast_ref::<OrderError>() {
+            Some(OrderError::PrecisionExceeded { field, allowed }) => {
+                assert_eq!(field, "price");
+                assert_eq!(*allowed, Some(2));
+            }
+            _ => panic!("precision error is not detected"),
+        }
+
+        let body = r#"{"status":"error","err-code":"order-limitorder-amount-precision-error","err-msg":"limit order amount precision error","data":null}"#;
+        let err_response = serde_json::from_str(body).unwrap();
+
+        match api_error(body, &err_response).downcast_ref::<OrderError>() {
+            Some(OrderError::PrecisionExceeded { field, allowed }) => {
+                assert_eq!(field, "amount");
+                assert_eq!(*allowed, None);
+            }
+            _ => panic!("precision error is not detected"),
+        }
+    }
+
+    #[test]
+    fn response_meta_from_headers() {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            "X-HB-RateLimit-Requests-Remain",
+            HeaderValue::from_static("99"),
+        );
+        headers.insert(
+            "X-HB-RateLimit-Requests-Expire",
+            HeaderValue::from_static("1629788763750"),
+        );
+
+        let meta = response_meta(&headers);
+
+        assert_eq!(meta.requests_remain, Some(99));
+        assert_eq!(meta.requests_expire, Some(1629788763750));
+    }
+
+    #[test]
+    fn response_meta_without_headers() {
+        let meta = response_meta(&HeaderMap::new());
+
+        assert_eq!(meta.requests_remain, None);
+        assert_eq!(meta.requests_expire, None);
+    }
+}
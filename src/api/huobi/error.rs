@@ -1,15 +1,53 @@
+use super::models::{OrderId, OrderState};
 use core::fmt;
+use rust_decimal::Decimal;
 use std::error::Error;
 
 #[derive(Debug, Clone)]
 pub enum HuobiError {
     ApiError(String),
+    ChannelMismatch { expected: String, received: String },
+    // Sum of Money in two currencies
+    CurrencyMismatch { expected: String, received: String },
+    InvalidWithdraw(String),
+    // Huobi (CloudFront) bans the IP for a while when the rate limit is abused
+    IpBanned(String),
+    // No metadata of the symbol to round its price or amount
+    PrecisionUnavailable(String),
+    // Not in the symbols list, even refreshed. A new listing may take a while to show up
+    SymbolNotFound(String),
+    // The request or the response did not get through
+    Network { kind: NetworkErrorKind, why: String },
+    // The body went past the limit set with Client::with_max_response_size
+    ResponseTooLarge { limit: usize },
 }
 
 impl fmt::Display for HuobiError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self.clone() {
             HuobiError::ApiError(why) => write!(f, "ApiError: {}", why),
+            HuobiError::ChannelMismatch { expected, received } => write!(
+                f,
+                "ChannelMismatch: expected {}, received {}",
+                expected, received
+            ),
+            HuobiError::CurrencyMismatch { expected, received } => write!(
+                f,
+                "CurrencyMismatch: expected {}, received {}",
+                expected, received
+            ),
+            HuobiError::InvalidWithdraw(why) => write!(f, "InvalidWithdraw: {}", why),
+            HuobiError::IpBanned(why) => write!(f, "IpBanned: {}", why),
+            HuobiError::PrecisionUnavailable(symbol) => {
+                write!(f, "PrecisionUnavailable: no metadata for symbol {}", symbol)
+            }
+            HuobiError::SymbolNotFound(symbol) => {
+                write!(f, "SymbolNotFound: {} is not listed, or not yet", symbol)
+            }
+            HuobiError::Network { kind, why } => write!(f, "Network({:?}): {}", kind, why),
+            HuobiError::ResponseTooLarge { limit } => {
+                write!(f, "ResponseTooLarge: body over {} bytes", limit)
+            }
         }
     }
 }
@@ -20,5 +58,245 @@ impl Error for HuobiError {
     }
 }
 
+impl From<reqwest::Error> for HuobiError {
+    fn from(e: reqwest::Error) -> Self {
+        let kind = if e.is_timeout() {
+            NetworkErrorKind::Timeout
+        } else if e.is_connect() {
+            NetworkErrorKind::Connect
+        } else if e.is_decode() || e.is_body() {
+            NetworkErrorKind::Decode
+        } else {
+            NetworkErrorKind::Other
+        };
+
+        HuobiError::Network {
+            kind,
+            why: e.to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum NetworkErrorKind {
+    Timeout,
+    // Connection refused, DNS or TLS failure: the request was not sent
+    Connect,
+    // The response body could not be read
+    Decode,
+    Other,
+}
+
+impl NetworkErrorKind {
+    // A timeout may come after the request was processed: retry only what is idempotent
+    pub fn is_retryable(&self) -> bool {
+        matches!(self, NetworkErrorKind::Timeout | NetworkErrorKind::Connect)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum OrderError {
+    InvalidAmount(String),
+    // Missing on a limit order, given on a market order, or not a positive number
+    InvalidPrice(String),
+    InsufficientBalance {
+        currency: String,
+        needed: Decimal,
+        available: Decimal,
+    },
+    // The client-order-id was already used by another order
+    DuplicateClientOrderId(String),
+    // Retrying is only safe for orders with a client-order-id
+    MissingClientOrderId,
+    // The price or the amount has more decimals than the symbol allows. allowed is
+    // known when Huobi tells the scale or the Account has a SymbolCache with the symbol
+    PrecisionExceeded {
+        field: String,
+        allowed: Option<u8>,
+    },
+    // The symbol is pre-open, halted or under maintenance, retrying right away fails too
+    MarketNotOpen(String),
+    // The source does not take this order type
+    InvalidSource(String),
+    // The time in force does not go with the order type
+    InvalidTimeInForce(String),
+    // Huobi does not know the order, or does not anymore
+    NotFound(String),
+    // The order was still open when Account::wait_for_fill gave up
+    WaitTimeout {
+        order_id: OrderId,
+        state: OrderState,
+    },
+    // Account::replace_order canceled a partly filled order and what it did not fill is
+    // under the minimum order of the symbol, so no new order was placed
+    RemainderBelowMin {
+        order_id: OrderId,
+        filled: Decimal,
+        remaining: Decimal,
+    },
+}
+
+impl fmt::Display for OrderError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.clone() {
+            OrderError::InvalidAmount(why) => write!(f, "InvalidAmount: {}", why),
+            OrderError::InvalidPrice(why) => write!(f, "InvalidPrice: {}", why),
+            OrderError::InsufficientBalance {
+                currency,
+                needed,
+                available,
+            } => write!(
+                f,
+                "InsufficientBalance: needed {} {}, available {}",
+                needed, currency, available
+            ),
+            OrderError::DuplicateClientOrderId(why) => write!(f, "DuplicateClientOrderId: {}", why),
+            OrderError::PrecisionExceeded {
+                field,
+                allowed: Some(allowed),
+            } => write!(
+                f,
+                "PrecisionExceeded: {} has more than the {} decimals allowed, round it first",
+                field, allowed
+            ),
+            OrderError::PrecisionExceeded {
+                field,
+                allowed: None,
+            } => write!(
+                f,
+                "PrecisionExceeded: {} has more decimals than the symbol allows, see Symbol precision",
+                field
+            ),
+            OrderError::MissingClientOrderId => {
+                write!(f, "MissingClientOrderId: the order has no client-order-id")
+            }
+            OrderError::MarketNotOpen(why) => write!(
+                f,
+                "MarketNotOpen: {}, check Client::get_system_status and the symbol state before retrying",
+                why
+            ),
+            OrderError::InvalidSource(why) => write!(f, "InvalidSource: {}", why),
+            OrderError::InvalidTimeInForce(why) => write!(f, "InvalidTimeInForce: {}", why),
+            OrderError::NotFound(why) => write!(f, "NotFound: {}", why),
+            OrderError::WaitTimeout { order_id, state } => write!(
+                f,
+                "WaitTimeout: order {} is still {} after the timeout",
+                order_id, state
+            ),
+            OrderError::RemainderBelowMin {
+                order_id,
+                filled,
+                remaining,
+            } => write!(
+                f,
+                "RemainderBelowMin: order {} filled {} before the cancel, the remaining {} is under the minimum order",
+                order_id, filled, remaining
+            ),
+        }
+    }
+}
+
+impl Error for OrderError {
+    fn description(&self) -> &str {
+        "Huobi Order Error"
+    }
+}
+
+// Why one item of a batch request failed, the other items may have succeeded
+#[derive(Debug, Clone)]
+pub struct BatchItemError {
+    pub order_id: Option<OrderId>,
+    pub client_order_id: Option<String>,
+    pub err_code: String,
+    pub err_msg: String,
+}
+
+impl fmt::Display for BatchItemError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "BatchItemError: {} {}", self.err_code, self.err_msg)
+    }
+}
+
+impl Error for BatchItemError {
+    fn description(&self) -> &str {
+        "Huobi Batch Item Error"
+    }
+}
+
+// A batch request whose items did not all succeed. place_orders and cancel_orders
+// return Ok with per-item results, check turns any failed item into an error
+#[derive(Debug, Clone)]
+pub struct BatchError {
+    pub succeeded: Vec<OrderId>,
+    pub failed: Vec<BatchItemError>,
+}
+
+impl BatchError {
+    pub fn check(
+        results: Vec<Result<OrderId, BatchItemError>>,
+    ) -> Result<Vec<OrderId>, BatchError> {
+        let mut succeeded = vec![];
+        let mut failed = vec![];
+
+        for result in results {
+            match result {
+                Ok(order_id) => succeeded.push(order_id),
+                Err(e) => failed.push(e),
+            }
+        }
+
+        if failed.is_empty() {
+            Ok(succeeded)
+        } else {
+            Err(BatchError { succeeded, failed })
+        }
+    }
+}
+
+impl fmt::Display for BatchError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "BatchError: {} items failed, {} succeeded",
+            self.failed.len(),
+            self.succeeded.len()
+        )?;
+
+        if let Some(first) = self.failed.first() {
+            write!(f, ", first: {} {}", first.err_code, first.err_msg)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Error for BatchError {
+    fn description(&self) -> &str {
+        "Huobi Batch Error"
+    }
+}
+
 #[derive(Debug)]
 enum ApiError {}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn network_error_kind_from_reqwest() {
+        // Nothing listens on port 1, the connection is refused right away
+        let e = reqwest::blocking::get("http://127.0.0.1:1").unwrap_err();
+
+        match HuobiError::from(e) {
+            HuobiError::Network { kind, .. } => {
+                assert_eq!(kind, NetworkErrorKind::Connect);
+                assert!(kind.is_retryable());
+            }
+            e => panic!("unexpected error {}", e),
+        }
+
+        assert!(!NetworkErrorKind::Decode.is_retryable());
+    }
+}
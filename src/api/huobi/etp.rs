@@ -0,0 +1,219 @@
+// Leveraged tokens (ETP), e.g. btc3lusdt. They trade as spot symbols, but their
+// NAV and rebalances have endpoints of their own
+use super::{
+    account::Account,
+    client::Client,
+    error::HuobiError,
+    models::{
+        deserialize_number_from_string_or_number, deserialize_option_number_from_string_or_number,
+        strict_enums, wire_serde, wire_str, APIResult, Endpoint, TickResult,
+    },
+};
+use serde::{de, Deserialize, Deserializer, Serialize};
+use serde_json;
+use std::collections::BTreeMap;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct EtpNav {
+    pub symbol: String,
+    #[serde(deserialize_with = "deserialize_number_from_string_or_number")]
+    pub nav: f64,
+    // Time of the NAV in ms
+    #[serde(deserialize_with = "deserialize_number_from_string_or_number")]
+    pub nav_time: u64,
+    #[serde(deserialize_with = "deserialize_number_from_string_or_number")]
+    pub outstanding: f64,
+    #[serde(deserialize_with = "deserialize_number_from_string_or_number")]
+    pub actual_leverage: f64,
+    #[serde(default)]
+    pub basket: Vec<EtpBasketItem>,
+}
+
+// Underlying holding per token
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct EtpBasketItem {
+    pub currency: String,
+    #[serde(deserialize_with = "deserialize_number_from_string_or_number")]
+    pub amount: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RebalanceType {
+    // Daily rebalance at the fixed time
+    Daily,
+    // Extra rebalance on a large move of the underlying
+    Adhoc,
+    Unknown(String),
+}
+
+wire_str!(RebalanceType);
+wire_serde!(RebalanceType);
+
+impl RebalanceType {
+    pub const ALL: [RebalanceType; 2] = [RebalanceType::Daily, RebalanceType::Adhoc];
+
+    pub fn as_str(&self) -> &str {
+        match self {
+            RebalanceType::Daily => "daily",
+            RebalanceType::Adhoc => "adhoc",
+            RebalanceType::Unknown(rebalance_type) => rebalance_type,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct RebalanceQuery {
+    pub symbol: String,
+    pub rebalance_type: Option<RebalanceType>,
+    // ms
+    pub start_time: Option<u64>,
+    pub end_time: Option<u64>,
+    pub limit: Option<u32>,
+    pub from_id: Option<u64>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct RebalanceResult {
+    #[serde(deserialize_with = "deserialize_number_from_string_or_number")]
+    code: u32,
+    message: Option<String>,
+    #[serde(default)]
+    data: Vec<Rebalance>,
+    #[serde(default)]
+    #[serde(deserialize_with = "deserialize_option_number_from_string_or_number")]
+    next_id: Option<u64>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Rebalance {
+    #[serde(deserialize_with = "deserialize_number_from_string_or_number")]
+    pub id: u64,
+    pub symbol: String,
+    #[serde(rename = "rebalType")]
+    pub rebalance_type: RebalanceType,
+    #[serde(rename = "rebalTime")]
+    #[serde(deserialize_with = "deserialize_number_from_string_or_number")]
+    pub rebalance_time: u64,
+    #[serde(rename = "navBeforeRebal")]
+    #[serde(deserialize_with = "deserialize_number_from_string_or_number")]
+    pub nav_before: f64,
+    #[serde(rename = "navAfterRebal")]
+    #[serde(deserialize_with = "deserialize_number_from_string_or_number")]
+    pub nav_after: f64,
+    #[serde(rename = "leverageBeforeRebal")]
+    #[serde(deserialize_with = "deserialize_number_from_string_or_number")]
+    pub leverage_before: f64,
+    #[serde(rename = "leverageAfterRebal")]
+    #[serde(deserialize_with = "deserialize_number_from_string_or_number")]
+    pub leverage_after: f64,
+}
+
+// Rebalances of one page, the next page starts at next_id
+#[derive(Debug, Clone)]
+pub struct RebalancePage {
+    pub rebalances: Vec<Rebalance>,
+    pub next_id: Option<u64>,
+}
+
+impl Client {
+    pub fn get_etp_nav(&self, symbol: &str) -> APIResult<EtpNav> {
+        let mut params: BTreeMap<String, String> = BTreeMap::new();
+
+        params.insert("symbol".into(), symbol.to_lowercase());
+
+        let data = self.get(Endpoint::EtpNav, &params)?;
+
+        let nav: TickResult<EtpNav> = serde_json::from_str(data.as_str())?;
+
+        nav.check_channel(&format!("market.{}.etp", symbol.to_lowercase()))?;
+
+        Ok(nav.tick)
+    }
+}
+
+impl Account {
+    pub fn get_etp_rebalances(&self, query: &RebalanceQuery) -> APIResult<RebalancePage> {
+        let mut params: BTreeMap<String, String> = BTreeMap::new();
+
+        params.insert("symbol".into(), query.symbol.to_lowercase());
+
+        if let Some(rebalance_type) = &query.rebalance_type {
+            params.insert("rebalType".into(), rebalance_type.as_str().into());
+        }
+        if let Some(start_time) = query.start_time {
+            params.insert("startTime".into(), start_time.to_string());
+        }
+        if let Some(end_time) = query.end_time {
+            params.insert("endTime".into(), end_time.to_string());
+        }
+        if let Some(limit) = query.limit {
+            params.insert("limit".into(), limit.to_string());
+        }
+        if let Some(from_id) = query.from_id {
+            params.insert("fromId".into(), from_id.to_string());
+        }
+
+        let data = self.client.get_signed(Endpoint::EtpRebalance, params)?;
+
+        parse_rebalances(&data)
+    }
+}
+
+fn parse_rebalances(data: &str) -> APIResult<RebalancePage> {
+    let result: RebalanceResult = serde_json::from_str(data)?;
+
+    if result.code != 200 {
+        return Err(Box::new(HuobiError::ApiError(format!(
+            "ETP rebalances: {} {}",
+            result.code,
+            result.message.unwrap_or_default()
+        ))));
+    }
+
+    Ok(RebalancePage {
+        rebalances: result.data,
+        next_id: result.next_id,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn rebalance_type_round_trip() {
+        for rebalance_type in RebalanceType::ALL.iter() {
+            assert_eq!(
+                rebalance_type.to_string().parse::<RebalanceType>().unwrap(),
+                *rebalance_type
+            );
+        }
+    }
+
+    #[test]
+    fn parse_nav() {
+        let data = r#"{"ch":"market.btc3lusdt.etp","status":"ok","ts":1630000000500,"tick":{"actualLeverage":2.98,"nav":12.14,"outstanding":155000.1,"symbol":"btc3lusdt","navTime":1630000000000,"basket":[{"currency":"btc","amount":0.00078}]}}"#;
+
+        let nav: TickResult<EtpNav> = serde_json::from_str(data).unwrap();
+
+        assert!(nav.check_channel("market.btc3lusdt.etp").is_ok());
+        assert_eq!(nav.tick.basket[0].currency, "btc");
+        assert!((nav.tick.nav - 12.14).abs() < 1e-9);
+    }
+
+    #[test]
+    fn parse_rebalance_page() {
+        let data = r#"{"code":200,"data":[{"id":301,"symbol":"btc3lusdt","rebalType":"daily","rebalTime":1630022400000,"navBeforeRebal":"12.31","navAfterRebal":"12.31","leverageBeforeRebal":"3.41","leverageAfterRebal":"3.0"}],"nextId":300,"success":true}"#;
+
+        let page = parse_rebalances(data).unwrap();
+
+        assert_eq!(page.rebalances[0].rebalance_type, RebalanceType::Daily);
+        assert_eq!(page.next_id, Some(300));
+
+        assert!(parse_rebalances(r#"{"code":1002,"message":"unauthorized"}"#).is_err());
+    }
+}
@@ -1,64 +1,181 @@
+use super::client::check_response;
+use super::error::HuobiError;
 use super::models::*;
-use hyper::{body::Buf, Body, Client, Method, Request, Response, Uri};
-use hyper_tls::HttpsConnector;
+use super::pagination::page_stream;
+use futures_util::stream::Stream;
+use lazy_static::lazy_static;
 use serde_json;
 use std::collections::HashMap;
+use std::time::Duration;
 use tracing::info;
 
 pub use super::account::Account;
 
 const HUOBI_BASE_URL: &str = "https://api.huobi.pro";
-pub struct HuobiApi;
 
-impl HuobiApi {
-    fn get_uri(endpoint: &str) -> Uri {
-        let url = format!("{}{}", HUOBI_BASE_URL, endpoint);
-        let uri = url.parse::<hyper::Uri>().unwrap();
-        uri
+lazy_static! {
+    // Shared by the default HuobiApi so bursts reuse the pooled (HTTP/2 multiplexed)
+    // connection. It is never replaced, other settings take a HuobiApi of their own
+    static ref HTTP_CLIENT: reqwest::Client = AsyncClientConfig::default()
+        .build()
+        .unwrap_or_else(|_| reqwest::Client::new());
+}
+
+// Connection settings of the async client. HTTP/2 is negotiated with ALPN and used when
+// the server supports it, otherwise requests go over pooled HTTP/1.1 connections
+#[derive(Debug, Clone)]
+pub struct AsyncClientConfig {
+    // TCP keep-alive probes of idle connections
+    pub tcp_keep_alive: Option<Duration>,
+    // HTTP/2 PING interval, keeps an idle multiplexed connection open
+    pub http2_keep_alive_interval: Option<Duration>,
+    pub pool_idle_timeout: Option<Duration>,
+}
+
+impl Default for AsyncClientConfig {
+    fn default() -> Self {
+        AsyncClientConfig {
+            tcp_keep_alive: Some(Duration::from_secs(30)),
+            http2_keep_alive_interval: Some(Duration::from_secs(30)),
+            pool_idle_timeout: Some(Duration::from_secs(90)),
+        }
     }
+}
 
-    async fn get_request(endpoint: &str) -> hyper::Result<impl Buf> {
-        let https = HttpsConnector::new();
-        let client = Client::builder().build::<_, hyper::Body>(https);
+impl AsyncClientConfig {
+    pub fn with_keep_alive(mut self, interval: Duration) -> Self {
+        self.tcp_keep_alive = Some(interval);
+        self.http2_keep_alive_interval = Some(interval);
+        self
+    }
 
-        let uri = HuobiApi::get_uri(endpoint);
-        let resp = client.get(uri).await?;
+    pub fn build(&self) -> reqwest::Result<reqwest::Client> {
+        reqwest::Client::builder()
+            .tcp_keepalive(self.tcp_keep_alive)
+            .http2_keep_alive_interval(self.http2_keep_alive_interval)
+            .http2_keep_alive_while_idle(true)
+            .pool_idle_timeout(self.pool_idle_timeout)
+            .build()
+    }
+}
 
-        // while let Some(chunk) = resp.body_mut().data().await {
-        //     stdout().write_all(&chunk?).await?;
-        // }
+// Async API on an injected reqwest client, the default one shares the pooled client
+#[derive(Clone)]
+pub struct HuobiApi {
+    http_client: reqwest::Client,
+}
 
-        let body = hyper::body::aggregate(resp).await;
-        body
+impl Default for HuobiApi {
+    fn default() -> Self {
+        // reqwest client is reference counted, the clone shares the pool
+        HuobiApi::with_http_client(HTTP_CLIENT.clone())
     }
+}
 
-    async fn post_request(
-        endpoint: &str,
-        _header: HashMap<String, String>,
-        _body: HashMap<String, String>,
-    ) -> hyper::Result<Response<Body>> {
-        let https = HttpsConnector::new();
-        let client = Client::builder().build::<_, hyper::Body>(https);
+impl HuobiApi {
+    pub fn with_http_client(http_client: reqwest::Client) -> Self {
+        HuobiApi { http_client }
+    }
 
-        let uri = HuobiApi::get_uri(endpoint);
+    // Own client with its own pool, the shared one is left as it is
+    pub fn with_config(config: &AsyncClientConfig) -> APIResult<Self> {
+        Ok(HuobiApi::with_http_client(config.build()?))
+    }
 
-        let body = Body::from("");
+    fn get_url(endpoint: &str) -> String {
+        format!("{}{}", HUOBI_BASE_URL, endpoint)
+    }
 
-        let req = Request::builder().method(Method::POST).uri(uri).body(body); //.(key, value);
-        let responce = client.request(req.unwrap()).await;
+    async fn get_request(&self, endpoint: &str) -> reqwest::Result<bytes::Bytes> {
+        let response = self
+            .http_client
+            .get(HuobiApi::get_url(endpoint))
+            .send()
+            .await?;
 
-        responce
+        response.bytes().await
+    }
+
+    async fn post_request(
+        &self,
+        endpoint: &str,
+        _header: HashMap<String, String>,
+        body: HashMap<String, String>,
+    ) -> reqwest::Result<reqwest::Response> {
+        self.http_client
+            .post(HuobiApi::get_url(endpoint))
+            .json(&body)
+            .send()
+            .await
     }
 
     /// Huobi API "/v1/common/symbols"
-    pub async fn symbols() -> hyper::Result<ResultSymbol> {
+    pub async fn get_symbols(&self) -> APIResult<ResultSymbol> {
         info!("[Huobi] get symbols");
         let endpoint = "/v1/common/symbols";
-        let body = HuobiApi::get_request(endpoint).await?;
-        let symbols = serde_json::from_reader(body.reader());
-        Ok(symbols.unwrap())
+        let body = self.get_request(endpoint).await?;
+        let symbols = serde_json::from_slice(&body)?;
+        Ok(symbols)
+    }
+
+    // get_symbols on the shared client
+    pub async fn symbols() -> APIResult<ResultSymbol> {
+        HuobiApi::default().get_symbols().await
+    }
+
+    // Ledger of the account as a Stream, the async counterpart of iter_account_history.
+    // Each page is signed by the Client of the account and sent on this client, without
+    // the rate limiter of the Client. Set the account id of the Account, the lookup of
+    // the spot account id is a blocking request
+    pub fn stream_account_history<'a>(
+        &'a self,
+        account: &'a Account,
+        query: AccountHistoryQuery,
+    ) -> impl Stream<Item = APIResult<LedgerEntry>> + 'a {
+        page_stream(move |from_id: Option<u64>| {
+            let page_query = AccountHistoryQuery {
+                from_id: from_id.or(query.from_id),
+                ..query.clone()
+            };
+
+            async move {
+                let params = account.account_history_params(&page_query)?;
+                let url = account
+                    .client
+                    .signed_url(Endpoint::AccountHistory, params)?;
+
+                let response = self
+                    .http_client
+                    .get(url)
+                    .send()
+                    .await
+                    .map_err(HuobiError::from)?;
+                let status = response.status();
+                let body = response.text().await.map_err(HuobiError::from)?;
+
+                check_response(status, &body)?;
+
+                let history: AccountHistoryResult = serde_json::from_str(&body)?;
+
+                Ok((history.data, history.next_id))
+            }
+        })
     }
 }
 
 #[cfg(test)]
-mod tests {}
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn build_async_client_with_keep_alive() {
+        let config = AsyncClientConfig::default().with_keep_alive(Duration::from_secs(10));
+
+        assert_eq!(
+            config.http2_keep_alive_interval,
+            Some(Duration::from_secs(10))
+        );
+        assert!(HuobiApi::with_config(&config).is_ok());
+    }
+}
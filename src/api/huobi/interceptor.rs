@@ -0,0 +1,42 @@
+use reqwest::header::HeaderMap;
+use reqwest::{Method, StatusCode};
+
+// Request as it is about to be sent. The URL already carries the signature,
+// so changing the query of a signed request invalidates it
+#[derive(Debug, Clone)]
+pub struct RequestParts {
+    pub method: Method,
+    pub url: String,
+    pub headers: HeaderMap,
+    pub body: Option<String>,
+}
+
+impl RequestParts {
+    pub fn new(method: Method, url: String) -> Self {
+        RequestParts {
+            method,
+            url,
+            headers: HeaderMap::new(),
+            body: None,
+        }
+    }
+}
+
+// Response as it was received. The body is None for streamed responses,
+// which are parsed while they are downloaded
+#[derive(Debug)]
+pub struct ResponseParts<'a> {
+    pub method: &'a Method,
+    pub url: &'a str,
+    pub status: StatusCode,
+    pub headers: &'a HeaderMap,
+    pub body: Option<&'a str>,
+}
+
+// Hook into every request of a Client: extra headers, raw body capture,
+// custom logging... Interceptors run in the order they were added
+pub trait Interceptor: Send + Sync {
+    fn before_send(&self, _req: &mut RequestParts) {}
+
+    fn after_recv(&self, _resp: &ResponseParts) {}
+}
@@ -0,0 +1,260 @@
+// What the Client logs of each request and at which level, so the volume can be
+// tuned from the config. Access keys and signatures never reach the logs.
+// Failures of work the library does on its own, off any call (sink writes, heartbeats,
+// shutdown steps), are logged at error whatever the policy: no caller sees them otherwise
+use reqwest::{Method, StatusCode};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use tracing::{debug, error, info, trace, warn};
+
+// Query params masked in logged URLs
+const REDACTED_PARAMS: [&str; 2] = ["AccessKeyId", "Signature"];
+
+// Fields masked in logged WebSocket messages, the auth request carries them
+const REDACTED_FIELDS: [&str; 2] = ["accessKey", "signature"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogLevel {
+    Error,
+    Warn,
+    Info,
+    Debug,
+    Trace,
+}
+
+// None leaves that part out of the logs
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LogPolicy {
+    // Method, path and status of every response
+    pub endpoint: Option<LogLevel>,
+    // Full URL with its query, redacted, before every attempt
+    pub url: Option<LogLevel>,
+    // Request and response bodies
+    pub body: Option<LogLevel>,
+}
+
+impl Default for LogPolicy {
+    fn default() -> Self {
+        LogPolicy {
+            endpoint: Some(LogLevel::Info),
+            url: Some(LogLevel::Debug),
+            body: Some(LogLevel::Trace),
+        }
+    }
+}
+
+impl LogPolicy {
+    // Nothing is logged
+    pub fn off() -> Self {
+        LogPolicy {
+            endpoint: None,
+            url: None,
+            body: None,
+        }
+    }
+
+    pub(crate) fn request(&self, method: &Method, url: &str, body: Option<&str>) {
+        log(
+            self.url,
+            format_args!("[Huobi] {} {}", method, redact_url(url)),
+        );
+
+        if let Some(body) = body {
+            log(
+                self.body,
+                format_args!("[Huobi] {} {} body: {}", method, path(url), body),
+            );
+        }
+    }
+
+    // A GET sent again after a network error, at the url level as it names the URL
+    pub(crate) fn retry(&self, attempt: u32, retries: u32, url: &str, why: &str) {
+        log(
+            self.url,
+            format_args!(
+                "[Huobi] Retry {} of {} {}: {}",
+                attempt,
+                retries,
+                redact_url(url),
+                why
+            ),
+        );
+    }
+
+    pub(crate) fn response(
+        &self,
+        method: &Method,
+        url: &str,
+        status: StatusCode,
+        body: Option<&str>,
+    ) {
+        log(
+            self.endpoint,
+            format_args!("[Huobi] {} {} -> {}", method, path(url), status.as_u16()),
+        );
+
+        if let Some(body) = body {
+            log(
+                self.body,
+                format_args!("[Huobi] {} {} response body: {}", method, path(url), body),
+            );
+        }
+    }
+}
+
+impl LogPolicy {
+    // What a call did besides its request, like listing the accounts or canceling
+    // orders, at the endpoint level
+    pub(crate) fn event(&self, args: fmt::Arguments) {
+        log(self.endpoint, args);
+    }
+
+    // Cache and tracker bookkeeping, at the url level
+    pub(crate) fn detail(&self, args: fmt::Arguments) {
+        log(self.url, args);
+    }
+
+    // Connection events of a WebSocket, at the endpoint level
+    pub(crate) fn ws_event(&self, args: fmt::Arguments) {
+        log(self.endpoint, args);
+    }
+
+    // Text messages of a WebSocket, at the body level. Sent messages are redacted
+    pub(crate) fn ws_sent(&self, message: &serde_json::Value) {
+        log(
+            self.body,
+            format_args!("[Huobi] WS sent: {}", redact_json(message)),
+        );
+    }
+
+    pub(crate) fn ws_received(&self, text: &str) {
+        log(self.body, format_args!("[Huobi] WS received: {}", text));
+    }
+}
+
+fn log(level: Option<LogLevel>, args: fmt::Arguments) {
+    match level {
+        Some(LogLevel::Error) => error!("{}", args),
+        Some(LogLevel::Warn) => warn!("{}", args),
+        Some(LogLevel::Info) => info!("{}", args),
+        Some(LogLevel::Debug) => debug!("{}", args),
+        Some(LogLevel::Trace) => trace!("{}", args),
+        None => {}
+    }
+}
+
+// Path of the URL without host nor query
+fn path(url: &str) -> &str {
+    let path = url.split('?').next().unwrap_or_default();
+
+    match path.find("://") {
+        Some(scheme_end) => {
+            let host_start = scheme_end + 3;
+            path[host_start..]
+                .find('/')
+                .map(|path_start| &path[host_start + path_start..])
+                .unwrap_or("/")
+        }
+        None => path,
+    }
+}
+
+pub(crate) fn redact_url(url: &str) -> String {
+    let (base, query) = match url.find('?') {
+        Some(query_start) => (&url[..query_start], &url[query_start + 1..]),
+        None => return url.to_string(),
+    };
+
+    let query: Vec<String> = query
+        .split('&')
+        .map(|param| match param.split_once('=') {
+            Some((key, _)) if REDACTED_PARAMS.contains(&key) => format!("{}=***", key),
+            _ => param.to_string(),
+        })
+        .collect();
+
+    format!("{}?{}", base, query.join("&"))
+}
+
+pub(crate) fn redact_json(value: &serde_json::Value) -> String {
+    fn redact(value: &mut serde_json::Value) {
+        match value {
+            serde_json::Value::Object(fields) => {
+                for (key, field) in fields.iter_mut() {
+                    if REDACTED_FIELDS.contains(&key.as_str()) {
+                        *field = serde_json::Value::String("***".into());
+                    } else {
+                        redact(field);
+                    }
+                }
+            }
+            serde_json::Value::Array(items) => items.iter_mut().for_each(redact),
+            _ => {}
+        }
+    }
+
+    let mut value = value.clone();
+    redact(&mut value);
+
+    value.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn signed_url_is_redacted() {
+        let url = "https://api.huobi.pro/v1/order/orders?AccessKeyId=e2xxxxxx-99xxxxxx&SignatureMethod=HmacSHA256&SignatureVersion=2&Timestamp=2021-08-24T07%3A06%3A03&symbol=btcusdt&Signature=4F65x5A2bLyMWVQj3Aqp%2BB4w%2BivaA7n5Oi2SuYtCJ9o%3D";
+
+        assert_eq!(
+            redact_url(url),
+            "https://api.huobi.pro/v1/order/orders?AccessKeyId=***&SignatureMethod=HmacSHA256&SignatureVersion=2&Timestamp=2021-08-24T07%3A06%3A03&symbol=btcusdt&Signature=***"
+        );
+        assert_eq!(
+            redact_url("https://api.huobi.pro/v1/common/symbols"),
+            "https://api.huobi.pro/v1/common/symbols"
+        );
+    }
+
+    #[test]
+    fn ws_auth_message_is_redacted() {
+        let auth = serde_json::json!({
+            "action": "req",
+            "ch": "auth",
+            "params": {
+                "authType": "api",
+                "accessKey": "e2xxxxxx-99xxxxxx",
+                "signature": "4F65x5A2bLyMWVQj3Aqp+B4w+ivaA7n5Oi2SuYtCJ9o="
+            }
+        });
+
+        let redacted = redact_json(&auth);
+
+        assert!(!redacted.contains("e2xxxxxx"));
+        assert!(!redacted.contains("4F65x5A2"));
+        assert!(redacted.contains(r#""accessKey":"***""#));
+        assert!(redacted.contains(r#""ch":"auth""#));
+    }
+
+    #[test]
+    fn path_of_url() {
+        assert_eq!(
+            path("https://api.huobi.pro/market/tickers?symbol=btcusdt"),
+            "/market/tickers"
+        );
+        assert_eq!(path("https://status.huobigroup.com"), "/");
+    }
+
+    #[test]
+    fn policy_from_config() {
+        let policy: LogPolicy = serde_json::from_str(r#"{"url":null,"body":"debug"}"#).unwrap();
+
+        assert_eq!(policy.endpoint, Some(LogLevel::Info));
+        assert_eq!(policy.url, None);
+        assert_eq!(policy.body, Some(LogLevel::Debug));
+    }
+}
@@ -0,0 +1,184 @@
+use super::{
+    account::Account,
+    error::HuobiError,
+    models::{
+        APIResult, AccountTransferResult, CrossMarginAccount, CrossMarginBalanceResult,
+        CrossMarginLoanInfo, CrossMarginLoanInfoResult, Endpoint, LoanInfo, MarginLoanInfoResult,
+        MarginTransferResult, RepayRequest, RepaymentResult, SymbolLoanInfo, TransferResult,
+    },
+};
+use serde_json;
+use std::collections::BTreeMap;
+
+impl Account {
+    // Transfers from the spot account into the cross-margin account. Huobi answers once
+    // the funds are moved, so an Ok transfer is complete
+    pub fn cross_margin_transfer_in(
+        &self,
+        currency: &str,
+        amount: f64,
+    ) -> APIResult<TransferResult> {
+        self._cross_margin_transfer(Endpoint::CrossMarginTransferIn, currency, amount)
+    }
+
+    // Transfers from the cross-margin account back to the spot account, complete when Ok
+    pub fn cross_margin_transfer_out(
+        &self,
+        currency: &str,
+        amount: f64,
+    ) -> APIResult<TransferResult> {
+        self._cross_margin_transfer(Endpoint::CrossMarginTransferOut, currency, amount)
+    }
+
+    fn _cross_margin_transfer(
+        &self,
+        endpoint: Endpoint,
+        currency: &str,
+        amount: f64,
+    ) -> APIResult<TransferResult> {
+        let params: BTreeMap<String, String> = BTreeMap::new();
+        let mut body: BTreeMap<String, String> = BTreeMap::new();
+
+        body.insert("currency".into(), currency.to_lowercase());
+        body.insert("amount".into(), amount.to_string());
+
+        let data = self.client.post_signed(endpoint, params, &body)?;
+
+        let transfer: MarginTransferResult = serde_json::from_str(data.as_str())?;
+
+        Ok(TransferResult {
+            transfer_id: transfer.data,
+            success: true,
+        })
+    }
+
+    // Transfers from the spot account to the USDT-margined swap account. margin_account is
+    // "USDT" for cross margin or the contract, e.g. "BTC-USDT", for isolated margin.
+    // Check TransferResult::success before relying on the swap balance
+    pub fn transfer_to_swap(
+        &self,
+        currency: &str,
+        amount: f64,
+        margin_account: &str,
+    ) -> APIResult<TransferResult> {
+        self._account_transfer("spot", "linear-swap", currency, amount, margin_account)
+    }
+
+    // Transfers from the USDT-margined swap account back to the spot account
+    pub fn transfer_from_swap(
+        &self,
+        currency: &str,
+        amount: f64,
+        margin_account: &str,
+    ) -> APIResult<TransferResult> {
+        self._account_transfer("linear-swap", "spot", currency, amount, margin_account)
+    }
+
+    fn _account_transfer(
+        &self,
+        from: &str,
+        to: &str,
+        currency: &str,
+        amount: f64,
+        margin_account: &str,
+    ) -> APIResult<TransferResult> {
+        let params: BTreeMap<String, String> = BTreeMap::new();
+        let mut body: BTreeMap<String, String> = BTreeMap::new();
+
+        body.insert("from".into(), from.into());
+        body.insert("to".into(), to.into());
+        body.insert("currency".into(), currency.to_lowercase());
+        body.insert("amount".into(), amount.to_string());
+        body.insert("margin-account".into(), margin_account.to_uppercase());
+
+        let data = self
+            .client
+            .post_signed(Endpoint::AccountTransfer, params, &body)?;
+
+        let transfer: AccountTransferResult = serde_json::from_str(data.as_str())?;
+
+        Ok(transfer.into_transfer()?)
+    }
+
+    pub fn get_cross_margin_loan_info(&self) -> APIResult<Vec<CrossMarginLoanInfo>> {
+        let params: BTreeMap<String, String> = BTreeMap::new();
+
+        let data = self
+            .client
+            .get_signed(Endpoint::CrossMarginLoanInfo, params)?;
+
+        let loan_info: CrossMarginLoanInfoResult = serde_json::from_str(data.as_str())?;
+
+        Ok(loan_info.data)
+    }
+
+    // Isolated margin loan terms per currency, of all the margin symbols when symbols is None
+    pub fn get_margin_loan_info(&self, symbols: Option<&[&str]>) -> APIResult<Vec<LoanInfo>> {
+        let mut params: BTreeMap<String, String> = BTreeMap::new();
+
+        if let Some(symbols) = symbols {
+            params.insert(
+                "symbols".into(),
+                symbols
+                    .iter()
+                    .map(|symbol| symbol.to_lowercase())
+                    .collect::<Vec<_>>()
+                    .join(","),
+            );
+        }
+
+        let data = self.client.get_signed(Endpoint::MarginLoanInfo, params)?;
+
+        let loan_info: MarginLoanInfoResult = serde_json::from_str(data.as_str())?;
+
+        Ok(loan_info
+            .data
+            .into_iter()
+            .flat_map(SymbolLoanInfo::into_loan_info)
+            .collect())
+    }
+
+    pub fn get_cross_margin_balance(&self) -> APIResult<CrossMarginAccount> {
+        let params: BTreeMap<String, String> = BTreeMap::new();
+
+        let data = self
+            .client
+            .get_signed(Endpoint::CrossMarginBalance, params)?;
+
+        let balance: CrossMarginBalanceResult = serde_json::from_str(data.as_str())?;
+
+        Ok(balance.data)
+    }
+
+    // Returns the repayment ids
+    pub fn repay_margin(&self, repay: RepayRequest) -> APIResult<Vec<u64>> {
+        let params: BTreeMap<String, String> = BTreeMap::new();
+        let mut body: BTreeMap<String, String> = BTreeMap::new();
+
+        body.insert("accountId".into(), repay.account_id);
+        body.insert("currency".into(), repay.currency.to_lowercase());
+        body.insert("amount".into(), repay.amount.to_string());
+
+        if let Some(transact_id) = repay.transact_id {
+            body.insert("transactId".into(), transact_id);
+        }
+
+        let data = self
+            .client
+            .post_signed(Endpoint::Repayment, params, &body)?;
+
+        let repayment: RepaymentResult = serde_json::from_str(data.as_str())?;
+
+        match repayment.data {
+            Some(repayments) if repayment.code == 200 => Ok(repayments
+                .into_iter()
+                .map(|repayment| repayment.repay_id)
+                .collect()),
+            _ => Err(Box::new(HuobiError::ApiError(format!(
+                "repayment error {}: {}",
+                repayment.code,
+                repayment.message.unwrap_or_default()
+            )))),
+        }
+    }
+}
@@ -0,0 +1,281 @@
+use super::{
+    client::Client,
+    error::HuobiError,
+    models::{
+        APIResult, CurrenciesResult, CurrencyChain, CurrencyReferenceResult, DepthStep, Endpoint,
+        MergedTicker, OrderBook, ResultSymbol, Symbol, SymbolTicker, SystemStatus, TickResult,
+        TickersResult, TimestampResult,
+    },
+};
+use serde_json;
+use std::collections::{BTreeMap, HashMap};
+
+const HUOBI_STATUS_URL: &str = "https://status.huobigroup.com/api/v2/summary.json";
+
+impl Client {
+    // Cheap probe that the API is reachable and answers with a valid response,
+    // the clock skew is checked on the way
+    pub fn ping(&self) -> APIResult<()> {
+        self.sync_time()?;
+
+        Ok(())
+    }
+
+    // Skew of the local clock to the server time in ms, positive when the local clock is
+    // ahead. The server time is taken as the local time halfway through the request
+    pub fn sync_time(&self) -> APIResult<i64> {
+        let params: BTreeMap<String, String> = BTreeMap::new();
+
+        let sent = self.ts_millis();
+        let data = self.get(Endpoint::Timestamp, &params)?;
+        let received = self.ts_millis();
+
+        let timestamp: TimestampResult = serde_json::from_str(data.as_str())?;
+
+        let skew = clock_skew(sent, received, timestamp.data);
+        self.check_skew(skew);
+
+        Ok(skew)
+    }
+
+    pub fn get_ticker(&self, symbol: &str) -> APIResult<MergedTicker> {
+        let mut params: BTreeMap<String, String> = BTreeMap::new();
+
+        params.insert("symbol".into(), symbol.to_lowercase());
+
+        let data = self.get(Endpoint::MarketDetailMerged, &params)?;
+
+        let ticker: TickResult<MergedTicker> = serde_json::from_str(data.as_str())?;
+
+        ticker.check_channel(&format!("market.{}.detail.merged", symbol.to_lowercase()))?;
+
+        Ok(ticker.tick)
+    }
+
+    // Tickers of the given symbols in the same order. Huobi has no filter on the
+    // tickers endpoint, so this is one request for the whole snapshot, which is cheaper
+    // than one request per symbol as soon as there are a few of them
+    pub fn get_tickers(&self, symbols: &[&str]) -> APIResult<Vec<SymbolTicker>> {
+        let params: BTreeMap<String, String> = BTreeMap::new();
+
+        let data = self.get(Endpoint::MarketTickers, &params)?;
+
+        let tickers: TickersResult = serde_json::from_str(data.as_str())?;
+
+        select_tickers(tickers.data, symbols)
+    }
+
+    // Close of the merged ticker, the price of the last trade
+    pub fn get_last_price(&self, symbol: &str) -> APIResult<f64> {
+        Ok(self.get_ticker(symbol)?.close)
+    }
+
+    pub fn get_depth(&self, symbol: &str, step: DepthStep) -> APIResult<OrderBook> {
+        let mut params: BTreeMap<String, String> = BTreeMap::new();
+
+        params.insert("symbol".into(), symbol.to_lowercase());
+        params.insert("type".into(), step.as_str().into());
+
+        let data = self.get(Endpoint::MarketDepth, &params)?;
+
+        let depth: TickResult<OrderBook> = serde_json::from_str(data.as_str())?;
+
+        depth.check_channel(&format!(
+            "market.{}.depth.{}",
+            symbol.to_lowercase(),
+            step.as_str()
+        ))?;
+
+        Ok(depth.tick)
+    }
+
+    // Book of every aggregation step keyed by the step name ("step0".."step5"). The steps
+    // are fetched in parallel, sharing the rate limiter of the Client.
+    // The books are taken at nearly the same time but they are not one snapshot
+    pub fn get_depth_all_steps(&self, symbol: &str) -> APIResult<HashMap<String, OrderBook>> {
+        let results = self.batch_get(&DepthStep::ALL, DepthStep::ALL.len(), |client, &step| {
+            client.get_depth(symbol, step)
+        });
+
+        let mut books = HashMap::new();
+
+        for (step, book) in DepthStep::ALL.iter().zip(results) {
+            books.insert(step.as_str().to_string(), book?);
+        }
+
+        Ok(books)
+    }
+
+    // Incidents and maintenances from the status page, which is not part of the API
+    pub fn get_system_status(&self) -> APIResult<SystemStatus> {
+        let data = self.get_external(HUOBI_STATUS_URL)?;
+
+        let status: SystemStatus = serde_json::from_str(data.as_str())?;
+
+        Ok(status)
+    }
+
+    pub fn get_trade_currencies(&self) -> APIResult<Vec<String>> {
+        let params: BTreeMap<String, String> = BTreeMap::new();
+
+        let data = self.get_cached(Endpoint::Currencys, &params)?;
+
+        let currencies: CurrenciesResult = serde_json::from_str(data.as_str())?;
+
+        let mut currencies: Vec<String> = currencies
+            .data
+            .into_iter()
+            .map(|currency| currency.to_lowercase())
+            .collect();
+        currencies.sort();
+        currencies.dedup();
+
+        Ok(currencies)
+    }
+
+    pub fn get_currency_chains(&self, currency: &str) -> APIResult<Vec<CurrencyChain>> {
+        let mut params: BTreeMap<String, String> = BTreeMap::new();
+
+        params.insert("currency".into(), currency.to_lowercase());
+
+        let data = self.get_cached(Endpoint::ReferenceCurrencies, &params)?;
+
+        let reference: CurrencyReferenceResult = serde_json::from_str(data.as_str())?;
+
+        Ok(reference
+            .data
+            .into_iter()
+            .filter(|reference| reference.currency == currency.to_lowercase())
+            .flat_map(|reference| reference.chains)
+            .collect())
+    }
+
+    pub fn get_symbols(&self) -> APIResult<Vec<Symbol>> {
+        let params: BTreeMap<String, String> = BTreeMap::new();
+
+        let data = self.get_cached(Endpoint::Symbols, &params)?;
+
+        let symbols: ResultSymbol = serde_json::from_str(data.as_str())?;
+
+        Ok(symbols.data)
+    }
+
+    // Past the response cache, for a new listing missing from the cached list
+    pub fn refresh_symbols(&self) -> APIResult<Vec<Symbol>> {
+        let params: BTreeMap<String, String> = BTreeMap::new();
+
+        let data = self.get_refreshed(Endpoint::Symbols, &params)?;
+
+        let symbols: ResultSymbol = serde_json::from_str(data.as_str())?;
+
+        self.set_symbols_refreshed();
+
+        Ok(symbols.data)
+    }
+
+    // A symbol missing from the cached list is looked for once more in a refreshed list,
+    // unless the list was refreshed within the last 10 s
+    pub fn get_symbol(&self, symbol: &str) -> APIResult<Symbol> {
+        let find = |symbols: Vec<Symbol>| {
+            symbols
+                .into_iter()
+                .find(|symbol_info| symbol_info.symbol == symbol.to_lowercase())
+        };
+
+        if let Some(symbol_info) = find(self.get_symbols()?) {
+            return Ok(symbol_info);
+        }
+
+        if !self.is_symbols_refresh_due() {
+            return Err(Box::new(HuobiError::SymbolNotFound(symbol.to_lowercase())));
+        }
+
+        match find(self.refresh_symbols()?) {
+            Some(symbol_info) => Ok(symbol_info),
+            None => Err(Box::new(HuobiError::SymbolNotFound(symbol.to_lowercase()))),
+        }
+    }
+}
+
+fn clock_skew(sent: u64, received: u64, server_time: u64) -> i64 {
+    let local_time = sent + received.saturating_sub(sent) / 2;
+
+    local_time as i64 - server_time as i64
+}
+
+fn select_tickers(tickers: Vec<SymbolTicker>, symbols: &[&str]) -> APIResult<Vec<SymbolTicker>> {
+    let by_symbol: HashMap<String, SymbolTicker> = tickers
+        .into_iter()
+        .map(|ticker| (ticker.symbol.clone(), ticker))
+        .collect();
+
+    let mut missing = Vec::new();
+    let mut selected = Vec::with_capacity(symbols.len());
+
+    for symbol in symbols {
+        // A symbol asked twice gets its ticker twice
+        match by_symbol.get(&symbol.to_lowercase()) {
+            Some(ticker) => selected.push(ticker.clone()),
+            None => missing.push(symbol.to_string()),
+        }
+    }
+
+    if !missing.is_empty() {
+        return Err(Box::new(HuobiError::ApiError(format!(
+            "no ticker for {}",
+            missing.join(", ")
+        ))));
+    }
+
+    Ok(selected)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TICKERS: &str = r#"{"status":"ok","ts":1629788763000,"data":[
+        {"symbol":"btcusdt","open":48000.0,"high":49500.5,"low":47800.0,"close":49100.0,
+         "amount":1520.3,"vol":73000000.0,"count":90210,"bid":49099.9,"bidSize":0.5,
+         "ask":49100.1,"askSize":1.2},
+        {"symbol":"ethusdt","open":3200.0,"high":3350.0,"low":3150.0,"close":3300.0,
+         "amount":21000.0,"vol":68000000.0,"count":70111,"bid":3299.9,"bidSize":3.0,
+         "ask":3300.1,"askSize":2.5},
+        {"symbol":"htusdt","open":9.1,"high":9.4,"low":8.9,"close":9.2,
+         "amount":500000.0,"vol":4600000.0,"count":12000,"bid":9.19,"bidSize":100.0,
+         "ask":9.21,"askSize":80.0}
+    ]}"#;
+
+    #[test]
+    fn tickers_of_requested_symbols() {
+        let tickers: TickersResult = serde_json::from_str(TICKERS).unwrap();
+
+        let selected = select_tickers(tickers.data, &["HTUSDT", "btcusdt"]).unwrap();
+
+        let symbols: Vec<&str> = selected.iter().map(|t| t.symbol.as_str()).collect();
+        assert_eq!(symbols, vec!["htusdt", "btcusdt"]);
+        assert_eq!(selected[1].bid_size, Some(0.5));
+    }
+
+    #[test]
+    fn skew_from_the_middle_of_the_request() {
+        assert_eq!(clock_skew(1630000000000, 1630000000200, 1630000000100), 0);
+        assert_eq!(
+            clock_skew(1630000003000, 1630000003200, 1630000000100),
+            3000
+        );
+        assert_eq!(
+            clock_skew(1630000000000, 1630000000200, 1630000002100),
+            -2000
+        );
+    }
+
+    #[test]
+    fn missing_ticker_is_an_error() {
+        let tickers: TickersResult = serde_json::from_str(TICKERS).unwrap();
+
+        let err = select_tickers(tickers.data, &["btcusdt", "dogeusdt"]).unwrap_err();
+
+        assert!(err.to_string().contains("dogeusdt"));
+    }
+}
@@ -0,0 +1,257 @@
+// Persistence of the market stream: every push goes to a user store (file, DB...)
+// through a bounded queue, written on a thread of its own so a slow store does not
+// stall the reading of the socket more than the policy allows
+use super::models::APIResult;
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread::{self, JoinHandle};
+use tracing::error;
+
+// Push as it arrived: the channel, its ts in ms and the decoded JSON text
+#[derive(Debug, Clone, PartialEq)]
+pub struct MarketRecord {
+    pub channel: String,
+    pub ts: Option<u64>,
+    pub payload: String,
+}
+
+pub trait MarketSink: Send {
+    fn write(&mut self, record: &MarketRecord) -> APIResult<()>;
+
+    // Called once the queue is drained, when the writer is closed
+    fn flush(&mut self) -> APIResult<()> {
+        Ok(())
+    }
+}
+
+// What happens to a push when the queue is full
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Backpressure {
+    // The stream waits for the sink: nothing is lost, but the socket is not read
+    // meanwhile and Huobi closes a connection that does not answer its pings
+    Block,
+    // The oldest queued push is dropped, the stream is never slowed down
+    DropOldest,
+}
+
+#[derive(Debug, Default)]
+struct Queue {
+    records: VecDeque<MarketRecord>,
+    closed: bool,
+}
+
+#[derive(Debug, Default)]
+struct Shared {
+    queue: Mutex<Queue>,
+    // Signaled when a record is queued or taken, and on close
+    changed: Condvar,
+    dropped: AtomicU64,
+    failed: AtomicU64,
+}
+
+#[derive(Debug)]
+pub struct SinkWriter {
+    shared: Arc<Shared>,
+    capacity: usize,
+    policy: Backpressure,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl SinkWriter {
+    pub fn new<S: MarketSink + 'static>(sink: S, capacity: usize, policy: Backpressure) -> Self {
+        let shared = Arc::new(Shared::default());
+        let writer_shared = shared.clone();
+
+        let thread = thread::spawn(move || write_records(sink, &writer_shared));
+
+        SinkWriter {
+            shared,
+            capacity: capacity.max(1),
+            policy,
+            thread: Some(thread),
+        }
+    }
+
+    pub fn push(&self, record: MarketRecord) {
+        let mut queue = self.shared.queue.lock().unwrap();
+
+        while queue.records.len() >= self.capacity {
+            match self.policy {
+                Backpressure::Block => queue = self.shared.changed.wait(queue).unwrap(),
+                Backpressure::DropOldest => {
+                    queue.records.pop_front();
+                    self.shared.dropped.fetch_add(1, Ordering::Relaxed);
+                }
+            }
+        }
+
+        queue.records.push_back(record);
+        self.shared.changed.notify_all();
+    }
+
+    // Pushes lost to DropOldest
+    pub fn dropped(&self) -> u64 {
+        self.shared.dropped.load(Ordering::Relaxed)
+    }
+
+    // Records the sink failed to write, they are logged and skipped
+    pub fn failed(&self) -> u64 {
+        self.shared.failed.load(Ordering::Relaxed)
+    }
+
+    // Writes what is queued, flushes the sink and stops the writer thread
+    pub fn close(mut self) {
+        self.stop();
+    }
+
+    fn stop(&mut self) {
+        self.shared.queue.lock().unwrap().closed = true;
+        self.shared.changed.notify_all();
+
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Drop for SinkWriter {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+fn write_records<S: MarketSink>(mut sink: S, shared: &Shared) {
+    loop {
+        let record = {
+            let mut queue = shared.queue.lock().unwrap();
+
+            loop {
+                if let Some(record) = queue.records.pop_front() {
+                    break Some(record);
+                }
+                if queue.closed {
+                    break None;
+                }
+                queue = shared.changed.wait(queue).unwrap();
+            }
+        };
+        // Room for a blocked push
+        shared.changed.notify_all();
+
+        // The writer thread has no Client nor caller to report to, so sink errors are
+        // logged whatever the LogPolicy
+        match record {
+            Some(record) => {
+                if let Err(e) = sink.write(&record) {
+                    shared.failed.fetch_add(1, Ordering::Relaxed);
+                    error!("[Huobi WS] Sink can't write {}: {}", record.channel, e);
+                }
+            }
+            None => {
+                if let Err(e) = sink.flush() {
+                    error!("[Huobi WS] Sink can't flush: {}", e);
+                }
+                return;
+            }
+        }
+    }
+}
+
+// Record of a decoded message when it carries market data: a push ("ch")
+// or the answer to a req ("rep")
+pub(crate) fn market_record(text: &str) -> Option<MarketRecord> {
+    let msg: serde_json::Value = serde_json::from_str(text).ok()?;
+
+    let channel = msg
+        .get("ch")
+        .or_else(|| msg.get("rep"))
+        .and_then(|channel| channel.as_str())?;
+
+    Some(MarketRecord {
+        channel: channel.to_string(),
+        ts: msg.get("ts").and_then(|ts| ts.as_u64()),
+        payload: text.to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use std::time::Duration;
+
+    // Holds every write until the gate is opened
+    struct SlowSink {
+        gate: Arc<(Mutex<bool>, Condvar)>,
+        written: Arc<Mutex<Vec<String>>>,
+    }
+
+    impl MarketSink for SlowSink {
+        fn write(&mut self, record: &MarketRecord) -> APIResult<()> {
+            let (open, opened) = &*self.gate;
+            let mut open = open.lock().unwrap();
+            while !*open {
+                open = opened.wait(open).unwrap();
+            }
+
+            self.written.lock().unwrap().push(record.channel.clone());
+            Ok(())
+        }
+    }
+
+    fn record(channel: &str) -> MarketRecord {
+        MarketRecord {
+            channel: channel.to_string(),
+            ts: None,
+            payload: String::new(),
+        }
+    }
+
+    #[test]
+    fn drop_oldest_when_the_sink_is_slow() {
+        let gate = Arc::new((Mutex::new(false), Condvar::new()));
+        let written = Arc::new(Mutex::new(Vec::new()));
+        let writer = SinkWriter::new(
+            SlowSink {
+                gate: gate.clone(),
+                written: written.clone(),
+            },
+            2,
+            Backpressure::DropOldest,
+        );
+
+        // The writer thread takes the first record and waits on the gate with it
+        writer.push(record("first"));
+        while !writer.shared.queue.lock().unwrap().records.is_empty() {
+            thread::sleep(Duration::from_millis(1));
+        }
+
+        for channel in ["a", "b", "c", "d"].iter() {
+            writer.push(record(channel));
+        }
+        assert_eq!(writer.dropped(), 2);
+
+        *gate.0.lock().unwrap() = true;
+        gate.1.notify_all();
+        writer.close();
+
+        assert_eq!(*written.lock().unwrap(), vec!["first", "c", "d"]);
+    }
+
+    #[test]
+    fn record_of_market_data_only() {
+        let push = r#"{"ch":"market.btcusdt.trade.detail","ts":1630000000123,"tick":{}}"#;
+
+        let record = market_record(push).unwrap();
+        assert_eq!(record.channel, "market.btcusdt.trade.detail");
+        assert_eq!(record.ts, Some(1630000000123));
+        assert_eq!(record.payload, push);
+
+        assert_eq!(market_record(r#"{"ping":1630000000000}"#), None);
+        assert_eq!(
+            market_record(r#"{"id":"1","status":"ok","subbed":"market.btcusdt.trade.detail"}"#),
+            None
+        );
+    }
+}
@@ -1,10 +1,39 @@
+pub mod etp;
 pub mod huobi;
 pub mod models;
+pub mod swap;
 pub mod websocket_account;
 pub mod websocket_data;
 
 mod account;
+mod balance_tracker;
 mod client;
 mod error;
+mod interceptor;
+mod log_policy;
+mod margin;
+mod market;
+mod market_sink;
+mod pagination;
+mod rate_limiter;
+mod reconcile;
+mod report;
+mod response_cache;
+mod shutdown;
+mod stream;
+mod symbol_cache;
 
-pub use huobi::{Account, HuobiApi};
+pub use balance_tracker::BalanceTracker;
+pub use client::{sign_request, Client, ClientConfig};
+pub use error::{BatchError, BatchItemError, HuobiError, NetworkErrorKind, OrderError};
+pub use huobi::{Account, AsyncClientConfig, HuobiApi};
+pub use interceptor::{Interceptor, RequestParts, ResponseParts};
+pub use log_policy::{LogLevel, LogPolicy};
+pub use market_sink::{Backpressure, MarketRecord, MarketSink, SinkWriter};
+pub use pagination::{page_stream, MatchCursor, Paginator};
+pub use reqwest::Method;
+pub use reconcile::{LocalOrder, OrderChange, ReconcileReport};
+pub use report::{AssetValuation, PnlEntry, PortfolioSnapshot};
+pub use response_cache::ResponseCache;
+pub use shutdown::{Disconnect, ShutdownOptions, ShutdownSummary};
+pub use symbol_cache::{RoundingMode, SymbolCache};
@@ -1,26 +1,330 @@
-use serde::de::{self, Unexpected, Visitor};
+use super::error::{HuobiError, OrderError};
+use super::symbol_cache::{ceil_to, floor_to};
+use chrono::{DateTime, TimeZone, Utc};
+use rust_decimal::prelude::FromPrimitive;
+use rust_decimal::Decimal;
+use serde::de;
 use serde::Deserializer;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::fmt;
+use std::ops::{Add, Sub};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
 
 pub type APIResult<T> = Result<T, Box<dyn std::error::Error>>;
 
 pub type ExchangeApiResult<T> = Result<T, &'static str>;
 
+// Display and FromStr of an enum through its as_str and ALL, so the Huobi string
+// parses back to the same variant. Parsing ignores the case of the string.
+// ALL holds the known variants only: an Unknown variant is never parsed from a
+// string, it stands for any value missing from ALL
+macro_rules! wire_str {
+    ($($name:ident),*) => {$(
+        impl std::fmt::Display for $name {
+            fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                f.write_str(self.as_str())
+            }
+        }
+
+        impl std::str::FromStr for $name {
+            type Err = HuobiError;
+
+            fn from_str(s: &str) -> Result<Self, Self::Err> {
+                $name::ALL
+                    .iter()
+                    .find(|value| value.as_str().eq_ignore_ascii_case(s))
+                    .cloned()
+                    .ok_or_else(|| {
+                        HuobiError::ApiError(format!("unknown {} {}", stringify!($name), s))
+                    })
+            }
+        }
+    )*};
+}
+pub(crate) use wire_str;
+
+// Serialize and Deserialize of an enum through its Huobi string, for enums with an
+// Unknown(String) variant that takes the values missing from ALL, see wire_str
+macro_rules! wire_serde {
+    ($($name:ident),*) => {$(
+        impl Serialize for $name {
+            fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                serializer.serialize_str(self.as_str())
+            }
+        }
+
+        impl<'de> Deserialize<'de> for $name {
+            fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                let value = String::deserialize(deserializer)?;
+
+                $name::from_wire(&value, strict_enums()).map_err(de::Error::custom)
+            }
+        }
+
+        impl $name {
+            fn from_wire(value: &str, strict: bool) -> Result<Self, HuobiError> {
+                match value.parse() {
+                    Ok(known) => Ok(known),
+                    Err(e) if strict => Err(e),
+                    Err(_) => Ok($name::Unknown(value.to_string())),
+                }
+            }
+        }
+    )*};
+}
+
+pub(crate) use wire_serde;
+
+wire_serde!(AccountState, SymbolState, OrderState, WithdrawState);
+
+// Values Huobi adds to a wire enum (a new order state, account state...) deserialize to
+// its Unknown variant, so that a running bot keeps going. Strict mode fails them instead,
+// to catch API changes in tests or staging. Process wide, off by default
+static STRICT_ENUMS: AtomicBool = AtomicBool::new(false);
+
+pub fn set_strict_enums(strict: bool) {
+    STRICT_ENUMS.store(strict, Ordering::Relaxed);
+}
+
+pub fn strict_enums() -> bool {
+    STRICT_ENUMS.load(Ordering::Relaxed)
+}
+
+wire_str!(
+    ApiVersion,
+    AccountState,
+    SymbolState,
+    OrderState,
+    WithdrawState,
+    OrderType,
+    StpMode,
+    TimeInForce,
+    OrderSource,
+    MarginTradePurpose,
+    DepthStep,
+    KlinePeriod,
+    Side
+);
+
 #[derive(Debug, Clone)]
 pub struct Transaction {
     pub symbol: String,
-    pub order_id: u64,
+    pub order_id: OrderId,
+}
+
+// Id of an order, kept apart from account ids, trade ids and the like so they can't
+// be passed for each other. Order endpoints answer with it as a number, as a string,
+// or nested under "order-id" / "orderId", all of which deserialize to the same id
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize)]
+#[serde(transparent)]
+pub struct OrderId(pub u64);
+
+impl OrderId {
+    pub fn get(self) -> u64 {
+        self.0
+    }
+}
+
+impl From<u64> for OrderId {
+    fn from(id: u64) -> Self {
+        OrderId(id)
+    }
+}
+
+impl From<OrderId> for u64 {
+    fn from(id: OrderId) -> Self {
+        id.0
+    }
+}
+
+impl fmt::Display for OrderId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl FromStr for OrderId {
+    type Err = HuobiError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.trim()
+            .parse()
+            .map(OrderId)
+            .map_err(|_| HuobiError::ApiError(format!("invalid order id {}", s)))
+    }
+}
+
+impl<'de> Deserialize<'de> for OrderId {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        order_id_of(&serde_json::Value::deserialize(deserializer)?).map_err(de::Error::custom)
+    }
+}
+
+fn order_id_of(value: &serde_json::Value) -> Result<OrderId, HuobiError> {
+    match value {
+        serde_json::Value::Number(id) => id
+            .as_u64()
+            .map(OrderId)
+            .ok_or_else(|| HuobiError::ApiError(format!("invalid order id {}", id))),
+        serde_json::Value::String(id) => id.parse(),
+        serde_json::Value::Object(fields) => ["order-id", "orderId", "order_id", "data"]
+            .iter()
+            .find_map(|key| fields.get(*key))
+            .ok_or_else(|| HuobiError::ApiError(format!("no order id in {}", value)))
+            .and_then(order_id_of),
+        _ => Err(HuobiError::ApiError(format!("invalid order id {}", value))),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ApiVersion {
+    V1,
+    V2,
+}
+
+impl ApiVersion {
+    pub const ALL: [ApiVersion; 2] = [ApiVersion::V1, ApiVersion::V2];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ApiVersion::V1 => "v1",
+            ApiVersion::V2 => "v2",
+        }
+    }
+
+    // Full endpoint path for a path without version, e.g. "/account/accounts"
+    pub fn path(&self, path: &str) -> String {
+        format!("/{}/{}", self.as_str(), path.trim_start_matches('/'))
+    }
+}
+
+// REST endpoints used by the helpers. The client takes anything Display as
+// an endpoint, so plain paths still work for endpoints missing here
+#[derive(Debug, Clone, PartialEq)]
+pub enum Endpoint {
+    Timestamp,
+    MarketDetailMerged,
+    MarketTickers,
+    MarketDepth,
+    Currencys,
+    ReferenceCurrencies,
+    Symbols,
+    Accounts,
+    AccountBalance(String),
+    AccountHistory,
+    TransactFeeRate,
+    OpenOrders,
+    Orders,
+    Order(u64),
+    SubmitCancel(u64),
+    SubmitCancelClientOrder,
+    ClientOrder,
+    BatchOrders,
+    BatchCancel,
+    BatchCancelOpenOrders,
+    OrderHistory,
+    MatchResults,
+    AlgoOrders,
+    CancelAllAfter,
+    WithdrawCreate,
+    DepositWithdraw,
+    CrossMarginTransferIn,
+    CrossMarginTransferOut,
+    AccountTransfer,
+    CrossMarginLoanInfo,
+    CrossMarginBalance,
+    MarginLoanInfo,
+    Repayment,
+    EtpNav,
+    EtpRebalance,
+}
+
+impl Endpoint {
+    // Path of endpoints without an id in it
+    pub fn as_str(&self) -> Option<&'static str> {
+        let path = match self {
+            Endpoint::Timestamp => "/v1/common/timestamp",
+            Endpoint::MarketDetailMerged => "/market/detail/merged",
+            Endpoint::MarketTickers => "/market/tickers",
+            Endpoint::MarketDepth => "/market/depth",
+            Endpoint::Currencys => "/v1/common/currencys",
+            Endpoint::ReferenceCurrencies => "/v2/reference/currencies",
+            Endpoint::Symbols => "/v1/common/symbols",
+            Endpoint::Accounts => "/v1/account/accounts",
+            Endpoint::AccountHistory => "/v1/account/history",
+            Endpoint::TransactFeeRate => "/v2/reference/transact-fee-rate",
+            Endpoint::OpenOrders => "/v1/order/openOrders",
+            Endpoint::Orders => "/v1/order/orders",
+            Endpoint::SubmitCancelClientOrder => "/v1/order/orders/submitCancelClientOrder",
+            Endpoint::ClientOrder => "/v1/order/orders/getClientOrder",
+            Endpoint::BatchOrders => "/v1/order/batch-orders",
+            Endpoint::BatchCancel => "/v1/order/orders/batchcancel",
+            Endpoint::BatchCancelOpenOrders => "/v1/order/orders/batchCancelOpenOrders",
+            Endpoint::OrderHistory => "/v1/order/history",
+            Endpoint::MatchResults => "/v1/order/matchresults",
+            Endpoint::AlgoOrders => "/v2/algo-orders",
+            Endpoint::CancelAllAfter => "/v2/algo-orders/cancel-all-after",
+            Endpoint::WithdrawCreate => "/v1/dw/withdraw/api/create",
+            Endpoint::DepositWithdraw => "/v1/query/deposit-withdraw",
+            Endpoint::CrossMarginTransferIn => "/v1/cross-margin/transfer-in",
+            Endpoint::CrossMarginTransferOut => "/v1/cross-margin/transfer-out",
+            Endpoint::AccountTransfer => "/v2/account/transfer",
+            Endpoint::CrossMarginLoanInfo => "/v1/cross-margin/loan-info",
+            Endpoint::CrossMarginBalance => "/v1/cross-margin/accounts/balance",
+            Endpoint::MarginLoanInfo => "/v1/margin/loan-info",
+            Endpoint::Repayment => "/v2/account/repayment",
+            Endpoint::EtpNav => "/market/etp",
+            Endpoint::EtpRebalance => "/v2/etp/rebalance",
+            Endpoint::AccountBalance(_) | Endpoint::Order(_) | Endpoint::SubmitCancel(_) => {
+                return None
+            }
+        };
+
+        Some(path)
+    }
+}
+
+impl fmt::Display for Endpoint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Endpoint::AccountBalance(account_id) => {
+                write!(f, "/v1/account/accounts/{}/balance", account_id)
+            }
+            Endpoint::Order(order_id) => write!(f, "/v1/order/orders/{}", order_id),
+            Endpoint::SubmitCancel(order_id) => {
+                write!(f, "/v1/order/orders/{}/submitcancel", order_id)
+            }
+            _ => f.write_str(self.as_str().unwrap_or_default()),
+        }
+    }
+}
+
+// Rate limit reported by Huobi in the response headers
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ResponseMeta {
+    // Requests left in the current window
+    pub requests_remain: Option<u64>,
+    // Timestamp in ms when the current window expires
+    pub requests_expire: Option<u64>,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct APIErrorResponse<R> {
     pub status: Option<String>,
 
+    #[serde(default)]
+    #[serde(deserialize_with = "deserialize_option_number_from_string_or_number")]
     pub err_code: Option<u32>,
 
     pub err_msg: Option<String>,
 
+    #[serde(default)]
+    #[serde(deserialize_with = "deserialize_option_number_from_string_or_number")]
     pub ts: Option<u64>,
 
     pub data: Option<R>,
@@ -28,24 +332,373 @@ pub struct APIErrorResponse<R> {
     pub tick: Option<R>,
 }
 
+// Envelope of any REST response, for endpoints without a typed helper:
+// deserialize the body of Client::get_signed into ResponseEnvelope<MyType>.
+// v1 answers with "status" and "err-code", v2 with a numeric "code" and "message",
+// the swap API with "err_code" and "err_msg": they all land in the same fields
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ResponseEnvelope<T> {
+    #[serde(default)]
+    pub status: String,
+    #[serde(default)]
+    #[serde(deserialize_with = "deserialize_option_number_from_string_or_number")]
+    pub ts: Option<i64>,
+    pub data: Option<T>,
+    #[serde(
+        rename = "err-code",
+        alias = "code",
+        alias = "err_code",
+        default,
+        deserialize_with = "deserialize_option_code"
+    )]
+    pub err_code: Option<String>,
+    #[serde(rename = "err-msg", alias = "message", alias = "err_msg", default)]
+    pub err_msg: Option<String>,
+}
+
+impl<T> ResponseEnvelope<T> {
+    // v2 reports success as code 200
+    pub fn is_ok(&self) -> bool {
+        match &self.err_code {
+            Some(code) => code == "200",
+            None => self.status != "error",
+        }
+    }
+
+    pub fn into_data(self) -> Result<T, HuobiError> {
+        if !self.is_ok() {
+            return Err(HuobiError::ApiError(format!(
+                "{}: {}",
+                self.err_code.unwrap_or_default(),
+                self.err_msg.unwrap_or_default()
+            )));
+        }
+
+        self.data
+            .ok_or_else(|| HuobiError::ApiError("response without data".into()))
+    }
+}
+
+fn deserialize_option_code<'de, D>(deserializer: D) -> Result<Option<String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    match Option::<serde_json::Value>::deserialize(deserializer)? {
+        Some(serde_json::Value::String(code)) => Ok(Some(code)),
+        Some(serde_json::Value::Number(code)) => Ok(Some(code.to_string())),
+        Some(serde_json::Value::Null) | None => Ok(None),
+        Some(code) => Err(de::Error::custom(format!("unexpected code {}", code))),
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct ResultSymbol {
     pub status: String,
     pub data: Vec<Symbol>,
 }
 
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TimestampResult {
+    #[serde(deserialize_with = "deserialize_number_from_string_or_number")]
+    pub data: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CurrenciesResult {
+    pub data: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CurrencyReferenceResult {
+    pub data: Vec<CurrencyReference>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct CurrencyReference {
+    pub currency: String,
+    pub inst_status: String,
+    pub chains: Vec<CurrencyChain>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct CurrencyChain {
+    pub chain: String,
+    #[serde(default)]
+    pub display_name: String,
+    pub deposit_status: String,
+    pub withdraw_status: String,
+    #[serde(deserialize_with = "deserialize_number_from_string_or_number")]
+    pub min_withdraw_amt: f64,
+    #[serde(deserialize_with = "deserialize_number_from_string_or_number")]
+    pub max_withdraw_amt: f64,
+    #[serde(deserialize_with = "deserialize_number_from_string_or_number")]
+    pub withdraw_precision: u8,
+    pub withdraw_fee_type: String,
+    // Set only for the "fixed" fee type
+    #[serde(default)]
+    #[serde(deserialize_with = "deserialize_number_from_string_or_number")]
+    pub transact_fee_withdraw: f64,
+    #[serde(default)]
+    pub addr_with_tag: bool,
+}
+
+impl CurrencyChain {
+    pub fn check_withdraw(&self, amount: f64) -> Result<(), HuobiError> {
+        if self.withdraw_status != "allowed" {
+            return Err(HuobiError::InvalidWithdraw(format!(
+                "withdraw is not allowed on chain {}",
+                self.chain
+            )));
+        }
+
+        if amount < self.min_withdraw_amt || amount > self.max_withdraw_amt {
+            return Err(HuobiError::InvalidWithdraw(format!(
+                "amount {} is out of range [{}, {}] on chain {}",
+                amount, self.min_withdraw_amt, self.max_withdraw_amt, self.chain
+            )));
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct WithdrawResult {
+    #[serde(deserialize_with = "deserialize_number_from_string_or_number")]
+    pub data: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TransferRecordsResult {
+    pub data: Vec<TransferRecord>,
+}
+
+// Deposit or withdrawal of the deposit-withdraw history
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TransferRecord {
+    #[serde(deserialize_with = "deserialize_number_from_string_or_number")]
+    pub id: u64,
+    // "deposit" or "withdraw"
+    #[serde(rename = "type")]
+    pub type_: String,
+    pub currency: String,
+    pub chain: String,
+    // Set once the withdrawal is broadcast
+    #[serde(rename = "tx-hash", default)]
+    #[serde(deserialize_with = "deserialize_non_empty")]
+    pub tx_hash: Option<String>,
+    #[serde(deserialize_with = "deserialize_number_from_string_or_number")]
+    pub amount: f64,
+    pub address: String,
+    #[serde(rename = "address-tag", default)]
+    pub address_tag: String,
+    #[serde(deserialize_with = "deserialize_number_from_string_or_number")]
+    pub fee: f64,
+    pub state: WithdrawState,
+    #[serde(rename = "error-msg", default)]
+    pub error_msg: String,
+    #[serde(rename = "created-at")]
+    #[serde(deserialize_with = "deserialize_number_from_string_or_number")]
+    pub created_at: u64,
+    #[serde(rename = "updated-at")]
+    #[serde(deserialize_with = "deserialize_number_from_string_or_number")]
+    pub updated_at: u64,
+}
+
+// A withdrawal is checked and approved, then handed to the wallet which broadcasts it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WithdrawState {
+    Verifying,
+    Failed,
+    Submitted,
+    Reexamine,
+    Canceled,
+    Pass,
+    Reject,
+    PreTransfer,
+    WalletTransfer,
+    WalletReject,
+    Confirmed,
+    ConfirmError,
+    Repealed,
+    Unknown(String),
+}
+
+impl WithdrawState {
+    pub const ALL: [WithdrawState; 13] = [
+        WithdrawState::Verifying,
+        WithdrawState::Failed,
+        WithdrawState::Submitted,
+        WithdrawState::Reexamine,
+        WithdrawState::Canceled,
+        WithdrawState::Pass,
+        WithdrawState::Reject,
+        WithdrawState::PreTransfer,
+        WithdrawState::WalletTransfer,
+        WithdrawState::WalletReject,
+        WithdrawState::Confirmed,
+        WithdrawState::ConfirmError,
+        WithdrawState::Repealed,
+    ];
+
+    pub fn as_str(&self) -> &str {
+        match self {
+            WithdrawState::Verifying => "verifying",
+            WithdrawState::Failed => "failed",
+            WithdrawState::Submitted => "submitted",
+            WithdrawState::Reexamine => "reexamine",
+            WithdrawState::Canceled => "canceled",
+            WithdrawState::Pass => "pass",
+            WithdrawState::Reject => "reject",
+            WithdrawState::PreTransfer => "pre-transfer",
+            WithdrawState::WalletTransfer => "wallet-transfer",
+            WithdrawState::WalletReject => "wallet-reject",
+            WithdrawState::Confirmed => "confirmed",
+            WithdrawState::ConfirmError => "confirm-error",
+            WithdrawState::Repealed => "repealed",
+            WithdrawState::Unknown(state) => state,
+        }
+    }
+
+    // On chain with enough confirmations
+    pub fn is_confirmed(&self) -> bool {
+        *self == WithdrawState::Confirmed
+    }
+
+    // States after which the withdrawal does not change anymore
+    pub fn is_final(&self) -> bool {
+        matches!(
+            self,
+            WithdrawState::Failed
+                | WithdrawState::Canceled
+                | WithdrawState::Reject
+                | WithdrawState::WalletReject
+                | WithdrawState::Confirmed
+                | WithdrawState::ConfirmError
+                | WithdrawState::Repealed
+        )
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct AccountsResult {
-    data: Vec<Account>,
+    pub data: Vec<Account>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Account {
-    id: u64,
-    state: String,
+    #[serde(deserialize_with = "deserialize_number_from_string_or_number")]
+    pub id: u64,
+    pub state: AccountState,
     #[serde(rename = "type")]
-    type_: String,
-    subtype: String,
+    pub type_: String,
+    pub subtype: String,
+}
+
+// Type of an account of the user, with the symbol of an isolated margin account
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AccountType {
+    Spot,
+    // Isolated margin account of the symbol, e.g. "btcusdt"
+    Margin(String),
+    // Cross margin
+    SuperMargin,
+    Otc,
+    Point,
+    Investment,
+    Borrow,
+    // Any type missing here, as Huobi names it
+    Other(String),
+}
+
+impl AccountType {
+    pub fn as_str(&self) -> &str {
+        match self {
+            AccountType::Spot => "spot",
+            AccountType::Margin(_) => "margin",
+            AccountType::SuperMargin => "super-margin",
+            AccountType::Otc => "otc",
+            AccountType::Point => "point",
+            AccountType::Investment => "investment",
+            AccountType::Borrow => "borrow",
+            AccountType::Other(type_) => type_,
+        }
+    }
+
+    // Isolated margin accounts have the symbol as subtype, the others none
+    pub fn matches(&self, account: &Account) -> bool {
+        match self {
+            AccountType::Margin(symbol) => {
+                account.type_ == "margin" && account.subtype.eq_ignore_ascii_case(symbol)
+            }
+            _ => account.type_ == self.as_str(),
+        }
+    }
+}
+
+impl fmt::Display for AccountType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AccountType::Margin(symbol) => write!(f, "margin {}", symbol.to_lowercase()),
+            _ => write!(f, "{}", self.as_str()),
+        }
+    }
+}
+
+// States Huobi may add later deserialize to Unknown instead of failing the whole list,
+// unless strict_enums is set
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AccountState {
+    Working,
+    Lock,
+    Unknown(String),
+}
+
+impl AccountState {
+    pub const ALL: [AccountState; 2] = [AccountState::Working, AccountState::Lock];
+
+    pub fn as_str(&self) -> &str {
+        match self {
+            AccountState::Working => "working",
+            AccountState::Lock => "lock",
+            AccountState::Unknown(state) => state,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SymbolState {
+    Online,
+    Offline,
+    Suspend,
+    PreOnline,
+    Unknown(String),
+}
+
+impl SymbolState {
+    pub const ALL: [SymbolState; 4] = [
+        SymbolState::Online,
+        SymbolState::Offline,
+        SymbolState::Suspend,
+        SymbolState::PreOnline,
+    ];
+
+    pub fn as_str(&self) -> &str {
+        match self {
+            SymbolState::Online => "online",
+            SymbolState::Offline => "offline",
+            SymbolState::Suspend => "suspend",
+            SymbolState::PreOnline => "pre-online",
+            SymbolState::Unknown(state) => state,
+        }
+    }
+
+    // Orders on any other state are rejected
+    pub fn is_tradeable(&self) -> bool {
+        matches!(self, SymbolState::Online)
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -60,151 +713,2011 @@ impl Default for OpenOrdersResult {
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct OpenOrder {
+    #[serde(deserialize_with = "deserialize_number_from_string_or_number")]
     pub id: u64,
     #[serde(rename = "client-order-id")]
     pub client_order_id: String,
     pub symbol: String,
-    pub price: String,
-    pub amount: String,
+    #[serde(deserialize_with = "deserialize_decimal_from_string_or_number")]
+    pub price: Decimal,
+    #[serde(deserialize_with = "deserialize_decimal_from_string_or_number")]
+    pub amount: Decimal,
     #[serde(rename = "created-at")]
+    #[serde(deserialize_with = "deserialize_number_from_string_or_number")]
     pub created_at: u64,
     #[serde(rename = "type")]
     pub type_: String,
     #[serde(rename = "filled-amount")]
-    pub filled_amount: String,
+    #[serde(deserialize_with = "deserialize_decimal_from_string_or_number")]
+    pub filled_amount: Decimal,
     #[serde(rename = "filled-cash-amount")]
-    pub filled_cash_amount: String,
+    #[serde(deserialize_with = "deserialize_decimal_from_string_or_number")]
+    pub filled_cash_amount: Decimal,
     #[serde(rename = "filled-fees")]
-    pub filled_fees: String,
+    #[serde(deserialize_with = "deserialize_decimal_from_string_or_number")]
+    pub filled_fees: Decimal,
     pub source: String,
-    pub state: String,
+    pub state: OrderState,
     // #[serde(rename = "stop-price")]
     // stop_price: String,
     // operator: String,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
-pub struct PlaceOrderResult {
-    pub data: String,
-}
+impl OpenOrder {
+    pub fn time_in_force(&self) -> Option<TimeInForce> {
+        TimeInForce::of_order_type(&self.type_)
+    }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
-pub struct CancelOrderResult {
-    pub data: String,
-}
+    pub fn filled(&self, symbol: &Symbol) -> Money {
+        Money::new(self.filled_amount, &symbol.base)
+    }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
-pub struct CancelOrderWithIdResult {
-    pub data: u8,
+    pub fn filled_value(&self, symbol: &Symbol) -> Money {
+        Money::new(self.filled_cash_amount, &symbol.quote)
+    }
+
+    pub fn fee(&self, symbol: &Symbol) -> Money {
+        Money::new(self.filled_fees, received_currency(&self.type_, symbol))
+    }
 }
 
-#[derive(Serialize, Deserialize, Debug)]
-pub struct BalanceResult {
-    pub data: BalanceData,
+// Orders do not name the fee currency: Huobi charges spot fees from the received
+// currency, the base for a buy and the quote for a sell
+fn received_currency<'a>(order_type: &str, symbol: &'a Symbol) -> &'a str {
+    if order_type.starts_with("buy") {
+        &symbol.base
+    } else {
+        &symbol.quote
+    }
 }
 
-#[derive(Serialize, Deserialize, Debug)]
-pub struct BalanceData {
-    pub id: u32,
-    #[serde(rename = "type")]
-    pub account_type: String,
-    pub state: String,
-    pub list: Vec<Asset>,
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OrderType {
+    BuyMarket,
+    SellMarket,
+    BuyLimit,
+    SellLimit,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
-pub struct Asset {
-    pub currency: String,
+impl OrderType {
+    pub const ALL: [OrderType; 4] = [
+        OrderType::BuyMarket,
+        OrderType::SellMarket,
+        OrderType::BuyLimit,
+        OrderType::SellLimit,
+    ];
 
-    #[serde(rename = "type")]
-    pub trade_type: String,
+    pub fn is_buy(&self) -> bool {
+        matches!(self, OrderType::BuyMarket | OrderType::BuyLimit)
+    }
+
+    pub fn is_market(&self) -> bool {
+        matches!(self, OrderType::BuyMarket | OrderType::SellMarket)
+    }
 
-    #[serde(deserialize_with = "string_as_f64")]
-    pub balance: f64,
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            OrderType::BuyMarket => "buy-market",
+            OrderType::SellMarket => "sell-market",
+            OrderType::BuyLimit => "buy-limit",
+            OrderType::SellLimit => "sell-limit",
+        }
+    }
 }
 
-#[derive(Serialize, Deserialize, Debug)]
-pub struct TradeHistoryResult {
-    pub data: Vec<TradeHistory>,
+// Huobi has a single "amount" field whose meaning depends on the order type:
+// buy-market spends quote currency, every other order type is sized in base currency
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Amount {
+    Base(f64),
+    Quote(f64),
 }
 
-#[derive(Serialize, Deserialize, Debug)]
-pub struct TradeHistory {
-    pub id: u64,
-    pub symbol: String,
-    #[serde(rename = "account-id")]
-    pub account_id: u32,
-    pub amount: String,
-    pub price: String,
-    #[serde(rename = "created-at")]
-    pub created_at: u64,
-    #[serde(rename = "type")]
-    pub type_order: String,
-    #[serde(rename = "field-amount")]
-    pub field_amount: String,
-    #[serde(rename = "field-cash-amount")]
-    pub field_cash_amount: String,
-    #[serde(rename = "field-fees")]
-    pub field_fees: String,
-    #[serde(rename = "finished-at")]
-    pub finished_at: u64,
-    pub source: String,
-    pub state: String,
-    #[serde(rename = "canceled-at")]
-    pub canceled_at: u64,
+impl Amount {
+    pub fn value(&self) -> f64 {
+        match *self {
+            Amount::Base(value) | Amount::Quote(value) => value,
+        }
+    }
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
-pub struct Symbol {
-    #[serde(rename = "base-currency")]
-    pub base: String,
-    #[serde(rename = "quote-currency")]
-    pub quote: String,
-    #[serde(rename = "price-precision")]
-    pub price_precision: u8,
-    #[serde(rename = "amount-precision")]
-    pub amount_precision: u8,
-    #[serde(rename = "symbol-partition")]
-    pub partition: String,
-    pub symbol: String,
-    pub state: String,
-    #[serde(rename = "value-precision")]
-    pub value_precision: u8,
-    #[serde(rename = "min-order-amt")]
-    pub min_amount: f64,
-    #[serde(rename = "max-order-amt")]
-    pub max_amount: f64,
-    #[serde(rename = "min-order-value")]
-    pub min_value: f64,
-    #[serde(default, rename = "leverage-ratio")]
-    pub max_leverage: f32,
+// Self-trade prevention: which side is canceled when the order would match
+// another order of the same account
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StpMode {
+    CancelMaker,
+    CancelTaker,
+    CancelBoth,
 }
 
-fn string_as_f64<'de, D>(deserializer: D) -> Result<f64, D::Error>
-where
-    D: Deserializer<'de>,
+impl StpMode {
+    pub const ALL: [StpMode; 3] = [
+        StpMode::CancelMaker,
+        StpMode::CancelTaker,
+        StpMode::CancelBoth,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            StpMode::CancelMaker => "cancel_maker",
+            StpMode::CancelTaker => "cancel_taker",
+            StpMode::CancelBoth => "cancel_both",
+        }
+    }
+}
+
+// How long an order stays on the book. Huobi has no field for it, it is part of the
+// order type: buy-limit is GTC, buy-ioc IOC and buy-limit-fok FOK
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TimeInForce {
+    // Good till canceled
+    Gtc,
+    // Immediate or cancel: fills what it can, the rest is canceled
+    Ioc,
+    // Fill or kill: fills completely or is canceled without any fill
+    Fok,
+}
+
+impl TimeInForce {
+    pub const ALL: [TimeInForce; 3] = [TimeInForce::Gtc, TimeInForce::Ioc, TimeInForce::Fok];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TimeInForce::Gtc => "gtc",
+            TimeInForce::Ioc => "ioc",
+            TimeInForce::Fok => "fok",
+        }
+    }
+
+    // Time in force of a Huobi order type, e.g. "sell-limit-fok". None for market orders
+    pub fn of_order_type(order_type: &str) -> Option<TimeInForce> {
+        if order_type.ends_with("-market") {
+            None
+        } else if order_type.ends_with("-ioc") {
+            Some(TimeInForce::Ioc)
+        } else if order_type.ends_with("-fok") {
+            Some(TimeInForce::Fok)
+        } else {
+            Some(TimeInForce::Gtc)
+        }
+    }
+}
+
+// "source" of an order, which tells the account it trades on: the spot account,
+// an isolated margin account, the cross margin (super-margin) account or the
+// C2C margin (lending) account
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OrderSource {
+    SpotApi,
+    MarginApi,
+    SuperMarginApi,
+    C2cMarginApi,
+}
+
+impl OrderSource {
+    pub const ALL: [OrderSource; 4] = [
+        OrderSource::SpotApi,
+        OrderSource::MarginApi,
+        OrderSource::SuperMarginApi,
+        OrderSource::C2cMarginApi,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            OrderSource::SpotApi => "spot-api",
+            OrderSource::MarginApi => "margin-api",
+            OrderSource::SuperMarginApi => "super-margin-api",
+            OrderSource::C2cMarginApi => "c2c-margin-api",
+        }
+    }
+
+    // C2C margin takes limit orders only
+    pub fn allows(&self, order_type: OrderType) -> bool {
+        match self {
+            OrderSource::C2cMarginApi => {
+                matches!(order_type, OrderType::BuyLimit | OrderType::SellLimit)
+            }
+            _ => true,
+        }
+    }
+}
+
+// Order placement routes. Place (/v1/order/orders/place) is the endpoint Huobi
+// currently recommends for spot and margin orders and the default of Account.
+// AutoPlace (/v1/order/auto/place) is for margin orders that borrow or repay automatically
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OrderEndpoint {
+    Place,
+    AutoPlace(MarginTradePurpose),
+}
+
+impl OrderEndpoint {
+    pub fn path(&self) -> &'static str {
+        match self {
+            OrderEndpoint::Place => "/v1/order/orders/place",
+            OrderEndpoint::AutoPlace(_) => "/v1/order/auto/place",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MarginTradePurpose {
+    AutoBorrow,
+    AutoRepay,
+}
+
+impl MarginTradePurpose {
+    pub const ALL: [MarginTradePurpose; 2] = [
+        MarginTradePurpose::AutoBorrow,
+        MarginTradePurpose::AutoRepay,
+    ];
+
+    // "trade-purpose" of the auto place payload
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MarginTradePurpose::AutoBorrow => "1",
+            MarginTradePurpose::AutoRepay => "2",
+        }
+    }
+}
+
+// Built with NewOrder::builder, which rejects what Huobi would reject for the shape
+// of the order (a limit order without price...) before anything is sent
+#[derive(Debug, Clone)]
+pub struct NewOrder {
+    pub(crate) symbol: String,
+    pub(crate) order_type: OrderType,
+    pub(crate) amount: Amount,
+    pub(crate) price: Option<f64>,
+    pub(crate) client_order_id: Option<String>,
+    pub(crate) stp_mode: Option<StpMode>,
+    // Legacy "api" when not set, which Huobi treats as spot-api
+    pub(crate) source: Option<OrderSource>,
+    // Worst price a market order may fill at
+    pub(crate) protection_price: Option<f64>,
+    pub(crate) time_in_force: Option<TimeInForce>,
+}
+
+#[derive(Debug, Clone)]
+pub struct NewOrderBuilder {
+    order: NewOrder,
+}
+
+impl NewOrderBuilder {
+    pub fn price(mut self, price: f64) -> Self {
+        self.order.price = Some(price);
+        self
+    }
+
+    pub fn client_order_id(mut self, client_order_id: &str) -> Self {
+        self.order.client_order_id = Some(client_order_id.to_string());
+        self
+    }
+
+    pub fn stp_mode(mut self, stp_mode: StpMode) -> Self {
+        self.order.stp_mode = Some(stp_mode);
+        self
+    }
+
+    pub fn source(mut self, source: OrderSource) -> Self {
+        self.order.source = Some(source);
+        self
+    }
+
+    // Huobi has no price band on market orders, so a protected market order is sent as
+    // an IOC limit order (buy-ioc, sell-ioc) at the protection price: it fills what the
+    // book offers up to that price for a buy, down to it for a sell, and the rest is
+    // canceled right away. When the book has nothing within the band the order ends
+    // canceled without any fill. The amount is then Amount::Base, also for a buy.
+    // With TimeInForce::Fok it fills completely within the band or not at all
+    pub fn protection_price(mut self, protection_price: f64) -> Self {
+        self.order.protection_price = Some(protection_price);
+        self
+    }
+
+    // GTC when not set. A market order takes IOC or FOK with a protection price only
+    pub fn time_in_force(mut self, time_in_force: TimeInForce) -> Self {
+        self.order.time_in_force = Some(time_in_force);
+        self
+    }
+
+    // Protection price at most max_slippage (0.01 for 1%) away from the reference price,
+    // e.g. the last price or the best ask for a buy. Rounded to the price precision of
+    // the symbol towards the reference, down for a buy and up for a sell
+    pub fn max_slippage(self, reference_price: f64, max_slippage: f64, symbol: &Symbol) -> Self {
+        let protection_price = if self.order.order_type.is_buy() {
+            floor_to(
+                reference_price * (1. + max_slippage),
+                symbol.price_precision,
+            )
+        } else {
+            ceil_to(
+                reference_price * (1. - max_slippage),
+                symbol.price_precision,
+            )
+        };
+
+        self.protection_price(protection_price)
+    }
+
+    pub fn build(self) -> Result<NewOrder, OrderError> {
+        self.order.validate()?;
+
+        Ok(self.order)
+    }
+}
+
+impl NewOrder {
+    // The amount is Amount::Quote for a buy-market order, Amount::Base otherwise
+    pub fn builder(symbol: &str, order_type: OrderType, amount: Amount) -> NewOrderBuilder {
+        NewOrderBuilder {
+            order: NewOrder {
+                symbol: symbol.to_lowercase(),
+                order_type,
+                amount,
+                price: None,
+                client_order_id: None,
+                stp_mode: None,
+                source: None,
+                protection_price: None,
+                time_in_force: None,
+            },
+        }
+    }
+
+    pub fn symbol(&self) -> &str {
+        &self.symbol
+    }
+
+    pub fn order_type(&self) -> OrderType {
+        self.order_type
+    }
+
+    pub fn amount(&self) -> Amount {
+        self.amount
+    }
+
+    pub fn price(&self) -> Option<f64> {
+        self.price
+    }
+
+    pub fn client_order_id(&self) -> Option<&str> {
+        self.client_order_id.as_deref()
+    }
+
+    pub fn stp_mode(&self) -> Option<StpMode> {
+        self.stp_mode
+    }
+
+    pub fn source(&self) -> Option<OrderSource> {
+        self.source
+    }
+
+    pub fn protection_price(&self) -> Option<f64> {
+        self.protection_price
+    }
+
+    // None for a market order, which fills what it can right away. A protected market
+    // order is IOC unless set to FOK, a limit order GTC unless set
+    pub fn time_in_force(&self) -> Option<TimeInForce> {
+        match (self.time_in_force, self.protection_price) {
+            (_, None) if self.order_type.is_market() => None,
+            (Some(time_in_force), _) => Some(time_in_force),
+            (None, Some(_)) => Some(TimeInForce::Ioc),
+            (None, None) => Some(TimeInForce::Gtc),
+        }
+    }
+
+    // Order type Huobi gets, with the time in force in it. A protected market order
+    // goes out as a limit order at the protection price
+    pub fn wire_type(&self) -> &'static str {
+        match (self.order_type.is_buy(), self.time_in_force()) {
+            (_, None) => self.order_type.as_str(),
+            (true, Some(TimeInForce::Gtc)) => "buy-limit",
+            (true, Some(TimeInForce::Ioc)) => "buy-ioc",
+            (true, Some(TimeInForce::Fok)) => "buy-limit-fok",
+            (false, Some(TimeInForce::Gtc)) => "sell-limit",
+            (false, Some(TimeInForce::Ioc)) => "sell-ioc",
+            (false, Some(TimeInForce::Fok)) => "sell-limit-fok",
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), OrderError> {
+        let amount = self.amount.value();
+        if !amount.is_finite() || amount <= 0. {
+            return Err(OrderError::InvalidAmount(format!(
+                "amount {} is not a positive number",
+                amount
+            )));
+        }
+
+        match (self.order_type, self.price) {
+            (OrderType::BuyLimit, None) | (OrderType::SellLimit, None) => {
+                return Err(OrderError::InvalidPrice(format!(
+                    "{} order requires a price",
+                    self.order_type.as_str()
+                )))
+            }
+            (OrderType::BuyLimit, Some(price)) | (OrderType::SellLimit, Some(price))
+                if !price.is_finite() || price <= 0. =>
+            {
+                return Err(OrderError::InvalidPrice(format!(
+                    "price {} is not a positive number",
+                    price
+                )))
+            }
+            (OrderType::BuyMarket, Some(_)) | (OrderType::SellMarket, Some(_)) => {
+                return Err(OrderError::InvalidPrice(format!(
+                    "{} order takes no price",
+                    self.order_type.as_str()
+                )))
+            }
+            _ => {}
+        }
+
+        if let Some(source) = self.source {
+            if !source.allows(self.order_type) {
+                return Err(OrderError::InvalidSource(format!(
+                    "{} orders are not accepted with source {}",
+                    self.order_type.as_str(),
+                    source.as_str()
+                )));
+            }
+        }
+
+        if let Some(time_in_force) = self.time_in_force {
+            if self.order_type.is_market() && self.protection_price.is_none() {
+                return Err(OrderError::InvalidTimeInForce(format!(
+                    "{} orders fill right away, {} needs a protection price",
+                    self.order_type.as_str(),
+                    time_in_force.as_str()
+                )));
+            }
+            if self.order_type.is_market() && time_in_force == TimeInForce::Gtc {
+                return Err(OrderError::InvalidTimeInForce(format!(
+                    "protected {} order can't stay on the book, use ioc or fok",
+                    self.order_type.as_str()
+                )));
+            }
+        }
+
+        if let Some(protection_price) = self.protection_price {
+            if !self.order_type.is_market() {
+                return Err(OrderError::InvalidPrice(format!(
+                    "protection price is for market orders, the {} price is the limit already",
+                    self.order_type.as_str()
+                )));
+            }
+            if !protection_price.is_finite() || protection_price <= 0. {
+                return Err(OrderError::InvalidPrice(format!(
+                    "protection price {} is not a positive number",
+                    protection_price
+                )));
+            }
+
+            return match self.amount {
+                Amount::Base(_) => Ok(()),
+                Amount::Quote(_) => Err(OrderError::InvalidAmount(format!(
+                    "protected {} order is sent as {}, size it in base currency with Amount::Base",
+                    self.order_type.as_str(),
+                    self.wire_type()
+                ))),
+            };
+        }
+
+        match (self.order_type, self.amount) {
+            (OrderType::BuyMarket, Amount::Quote(_)) => Ok(()),
+            (OrderType::BuyMarket, Amount::Base(_)) => Err(OrderError::InvalidAmount(
+                "buy-market amount is the quote currency to spend, use Amount::Quote".into(),
+            )),
+            (_, Amount::Base(_)) => Ok(()),
+            (order_type, Amount::Quote(_)) => Err(OrderError::InvalidAmount(format!(
+                "{} amount is the base currency quantity, use Amount::Base",
+                order_type.as_str()
+            ))),
+        }
+    }
+
+    // Payload of /v1/order/orders/place
+    pub fn to_body(&self, account_id: &str) -> BTreeMap<String, String> {
+        let mut body: BTreeMap<String, String> = BTreeMap::new();
+
+        body.insert("account-id".into(), account_id.into());
+        body.insert("amount".into(), self.amount.value().to_string());
+        body.insert(
+            "source".into(),
+            self.source.map_or("api", |source| source.as_str()).into(),
+        );
+        body.insert("symbol".into(), self.symbol.to_lowercase());
+        body.insert("type".into(), self.wire_type().into());
+
+        if let Some(price) = self.price.or(self.protection_price) {
+            body.insert("price".into(), price.to_string());
+        }
+
+        if let Some(id) = &self.client_order_id {
+            body.insert("client-order-id".into(), id.clone());
+        }
+
+        if let Some(stp_mode) = self.stp_mode {
+            body.insert("self-match-prevent-new".into(), stp_mode.as_str().into());
+        }
+
+        body
+    }
+
+    // needed_balance in the currency it is taken from
+    pub fn needed(&self, symbol: &Symbol) -> Money {
+        let currency = if self.order_type.is_buy() {
+            &symbol.quote
+        } else {
+            &symbol.base
+        };
+
+        Money::new(
+            Decimal::from_f64(self.needed_balance()).unwrap_or_default(),
+            currency,
+        )
+    }
+
+    // Balance the order locks: quote currency for buys, base currency for sells.
+    // Huobi charges spot fees from the received currency, so they are not added here
+    pub fn needed_balance(&self) -> f64 {
+        match (self.order_type, self.amount) {
+            (OrderType::BuyLimit, Amount::Base(amount)) => amount * self.price.unwrap_or(0.),
+            (OrderType::BuyMarket, Amount::Base(amount)) => {
+                amount * self.protection_price.unwrap_or(0.)
+            }
+            (_, amount) => amount.value(),
+        }
+    }
+}
+
+// Protective order submitted through the algo-order API once the entry is placed.
+// The side is the opposite of the entry, no order price means a market order on trigger.
+// The amount is in base currency, except for a market buy where it is the quote to spend
+#[derive(Debug, Clone)]
+pub struct StopOrder {
+    pub stop_price: f64,
+    pub order_price: Option<f64>,
+    pub amount: f64,
+    pub client_order_id: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct LinkedOrders {
+    pub entry: Transaction,
+    pub stop_client_order_id: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AlgoOrderResult {
+    #[serde(deserialize_with = "deserialize_number_from_string_or_number")]
+    pub code: u32,
+    pub message: Option<String>,
+    pub data: Option<AlgoOrderData>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct AlgoOrderData {
+    pub client_order_id: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CancelAllAfterResult {
+    #[serde(deserialize_with = "deserialize_number_from_string_or_number")]
+    pub code: u32,
+    pub message: Option<String>,
+    pub data: Option<CancelAllAfter>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct CancelAllAfter {
+    #[serde(deserialize_with = "deserialize_number_from_string_or_number")]
+    pub current_time: u64,
+    // 0 when the switch is turned off
+    #[serde(deserialize_with = "deserialize_number_from_string_or_number")]
+    pub trigger_time: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PlaceOrderResult {
+    pub data: OrderId,
+}
+
+// Answer of /v1/order/auto/place
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AutoPlaceOrderResult {
+    pub data: AutoPlaceOrderData,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AutoPlaceOrderData {
+    #[serde(rename = "order-id")]
+    #[serde(deserialize_with = "deserialize_number_from_string_or_number")]
+    pub order_id: u64,
+    #[serde(rename = "client-order-id")]
+    pub client_order_id: Option<String>,
+}
+
+// One item per order of /v1/order/batch-orders, either the order id or the error
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct BatchOrdersResult {
+    pub data: Vec<BatchOrderItem>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct BatchOrderItem {
+    #[serde(default, rename = "order-id")]
+    pub order_id: Option<OrderId>,
+    #[serde(rename = "client-order-id")]
+    pub client_order_id: Option<String>,
+    #[serde(rename = "err-code")]
+    pub err_code: Option<String>,
+    #[serde(rename = "err-msg")]
+    pub err_msg: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct BatchCancelResult {
+    pub data: BatchCancelData,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct BatchCancelData {
+    #[serde(default)]
+    pub success: Vec<String>,
+    #[serde(default)]
+    pub failed: Vec<BatchOrderItem>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CancelOpenOrdersResult {
+    pub data: CancelOpenOrdersData,
+}
+
+// next-id is -1 once no open order is left to cancel
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct CancelOpenOrdersData {
+    #[serde(rename = "success-count")]
+    #[serde(deserialize_with = "deserialize_number_from_string_or_number")]
+    pub success_count: u64,
+    #[serde(rename = "failed-count")]
+    #[serde(deserialize_with = "deserialize_number_from_string_or_number")]
+    pub failed_count: u64,
+    #[serde(rename = "next-id")]
+    #[serde(deserialize_with = "deserialize_number_from_string_or_number")]
+    pub next_id: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CancelOrderResult {
+    pub data: OrderId,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CancelOrderWithIdResult {
+    #[serde(deserialize_with = "deserialize_number_from_string_or_number")]
+    pub data: u8,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct BalanceResult {
+    pub data: BalanceData,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct BalanceData {
+    #[serde(deserialize_with = "deserialize_number_from_string_or_number")]
+    pub id: u32,
+    #[serde(rename = "type")]
+    pub account_type: String,
+    pub state: String,
+    pub list: Vec<Asset>,
+}
+
+// An amount with its currency, so fees in base, quote or HT are not summed together.
+// Decimal like the balances and fills it comes from, sums of them do not drift.
+// Adding or subtracting amounts of two currencies is a CurrencyMismatch error
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Money {
+    pub amount: Decimal,
+    // Lowercase, as Huobi names it, e.g. "usdt"
+    pub currency: String,
+}
+
+impl Money {
+    pub fn new(amount: Decimal, currency: &str) -> Self {
+        Money {
+            amount,
+            currency: currency.to_lowercase(),
+        }
+    }
+
+    pub fn zero(currency: &str) -> Self {
+        Money::new(Decimal::ZERO, currency)
+    }
+
+    fn same_currency(&self, other: &Money) -> Result<(), HuobiError> {
+        if self.currency != other.currency {
+            return Err(HuobiError::CurrencyMismatch {
+                expected: self.currency.clone(),
+                received: other.currency.clone(),
+            });
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Display for Money {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {}", self.amount, self.currency)
+    }
+}
+
+impl Add for Money {
+    type Output = Result<Money, HuobiError>;
+
+    fn add(self, other: Money) -> Self::Output {
+        self.same_currency(&other)?;
+
+        Ok(Money::new(self.amount + other.amount, &self.currency))
+    }
+}
+
+impl Sub for Money {
+    type Output = Result<Money, HuobiError>;
+
+    fn sub(self, other: Money) -> Self::Output {
+        self.same_currency(&other)?;
+
+        Ok(Money::new(self.amount - other.amount, &self.currency))
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Asset {
+    pub currency: String,
+
+    #[serde(rename = "type")]
+    pub trade_type: String,
+
+    #[serde(deserialize_with = "deserialize_decimal_from_string_or_number")]
+    pub balance: Decimal,
+}
+
+impl Asset {
+    pub fn money(&self) -> Money {
+        Money::new(self.balance, &self.currency)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct MarginTransferResult {
+    #[serde(deserialize_with = "deserialize_number_from_string_or_number")]
+    pub data: u64,
+}
+
+// Funds moved between two accounts of the user
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub struct TransferResult {
+    #[serde(deserialize_with = "deserialize_number_from_string_or_number")]
+    pub transfer_id: u64,
+    // The funds are on the destination account. false when Huobi took the transfer
+    // without settling it yet, the destination balance is not to be relied on then
+    pub success: bool,
+}
+
+// v2 answers with an id and a success flag, both are there for a transfer Huobi took
+#[derive(Serialize, Deserialize, Debug)]
+pub struct AccountTransferResult {
+    #[serde(deserialize_with = "deserialize_number_from_string_or_number")]
+    pub code: u32,
+    #[serde(default)]
+    pub success: bool,
+    pub message: Option<String>,
+    #[serde(default)]
+    #[serde(deserialize_with = "deserialize_option_number_from_string_or_number")]
+    pub data: Option<u64>,
+}
+
+impl AccountTransferResult {
+    pub fn into_transfer(self) -> Result<TransferResult, HuobiError> {
+        match self.data {
+            Some(transfer_id) if self.code == 200 => Ok(TransferResult {
+                transfer_id,
+                success: self.success,
+            }),
+            _ => Err(HuobiError::ApiError(format!(
+                "transfer error {}: {}",
+                self.code,
+                self.message.unwrap_or_default()
+            ))),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CrossMarginLoanInfoResult {
+    pub data: Vec<CrossMarginLoanInfo>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CrossMarginLoanInfo {
+    pub currency: String,
+    #[serde(rename = "interest-rate")]
+    #[serde(deserialize_with = "deserialize_number_from_string_or_number")]
+    pub interest_rate: f64,
+    #[serde(rename = "actual-rate")]
+    #[serde(deserialize_with = "deserialize_number_from_string_or_number")]
+    pub actual_rate: f64,
+    #[serde(rename = "min-loan-amt")]
+    #[serde(deserialize_with = "deserialize_number_from_string_or_number")]
+    pub min_loan_amt: f64,
+    #[serde(rename = "max-loan-amt")]
+    #[serde(deserialize_with = "deserialize_number_from_string_or_number")]
+    pub max_loan_amt: f64,
+    #[serde(rename = "loanable-amt")]
+    #[serde(deserialize_with = "deserialize_number_from_string_or_number")]
+    pub loanable_amt: f64,
+}
+
+// Isolated margin: loan terms of both currencies of every symbol
+#[derive(Serialize, Deserialize, Debug)]
+pub struct MarginLoanInfoResult {
+    pub data: Vec<SymbolLoanInfo>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SymbolLoanInfo {
+    pub symbol: String,
+    pub currencies: Vec<CrossMarginLoanInfo>,
+}
+
+// Borrowing cost of one currency of an isolated margin symbol, rates are per day
+#[derive(Debug, Clone, PartialEq)]
+pub struct LoanInfo {
+    pub symbol: String,
+    pub currency: String,
+    pub interest_rate: f64,
+    // After the discounts of the VIP level
+    pub actual_rate: f64,
+    pub min_loan_amt: f64,
+    pub max_loan_amt: f64,
+    pub loanable_amt: f64,
+}
+
+impl SymbolLoanInfo {
+    pub fn into_loan_info(self) -> Vec<LoanInfo> {
+        let symbol = self.symbol;
+
+        self.currencies
+            .into_iter()
+            .map(|currency| LoanInfo {
+                symbol: symbol.clone(),
+                currency: currency.currency,
+                interest_rate: currency.interest_rate,
+                actual_rate: currency.actual_rate,
+                min_loan_amt: currency.min_loan_amt,
+                max_loan_amt: currency.max_loan_amt,
+                loanable_amt: currency.loanable_amt,
+            })
+            .collect()
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CrossMarginBalanceResult {
+    pub data: CrossMarginAccount,
+}
+
+// Unlike isolated margin, a cross-margin account shares one risk rate across all currencies
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CrossMarginAccount {
+    #[serde(deserialize_with = "deserialize_number_from_string_or_number")]
+    pub id: u64,
+    #[serde(rename = "type")]
+    pub account_type: String,
+    pub state: String,
+    #[serde(rename = "risk-rate")]
+    #[serde(deserialize_with = "deserialize_number_from_string_or_number")]
+    pub risk_rate: f64,
+    #[serde(rename = "acct-balance-sum")]
+    #[serde(deserialize_with = "deserialize_number_from_string_or_number")]
+    pub acct_balance_sum: f64,
+    #[serde(rename = "debt-balance-sum")]
+    #[serde(deserialize_with = "deserialize_number_from_string_or_number")]
+    pub debt_balance_sum: f64,
+    // asset type is one of "trade", "frozen", "loan", "interest",
+    // "transfer-out-available", "loan-available"
+    pub list: Vec<Asset>,
+}
+
+// Account id is the margin account to repay from, not the spot one.
+// Without transact_id the oldest loans of the currency are repaid first
+#[derive(Debug, Clone)]
+pub struct RepayRequest {
+    pub account_id: String,
+    pub currency: String,
+    pub amount: f64,
+    pub transact_id: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct RepaymentResult {
+    #[serde(deserialize_with = "deserialize_number_from_string_or_number")]
+    pub code: u32,
+    pub message: Option<String>,
+    pub data: Option<Vec<Repayment>>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Repayment {
+    #[serde(deserialize_with = "deserialize_number_from_string_or_number")]
+    pub repay_id: u64,
+    #[serde(deserialize_with = "deserialize_number_from_string_or_number")]
+    pub repay_time: u64,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct AccountHistoryQuery {
+    pub currency: Option<String>,
+    // Comma separated, e.g. "trade,transact-fee"
+    pub transact_types: Option<String>,
+    pub start_time: Option<u64>,
+    pub end_time: Option<u64>,
+    pub size: Option<u32>,
+    pub from_id: Option<u64>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct AccountHistoryResult {
+    pub data: Vec<LedgerEntry>,
+    // Set when there are more records, pass it as from_id to get the next page
+    #[serde(rename = "next-id")]
+    #[serde(default)]
+    #[serde(deserialize_with = "deserialize_option_number_from_string_or_number")]
+    pub next_id: Option<u64>,
+}
+
+// Outcome of a streamed list response, the items themselves went to the callback
+#[derive(Debug, Clone, PartialEq)]
+pub struct StreamedList {
+    pub count: usize,
+    pub next_id: Option<u64>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct LedgerEntry {
+    #[serde(rename = "account-id")]
+    #[serde(deserialize_with = "deserialize_number_from_string_or_number")]
+    pub account_id: u64,
+    pub currency: String,
+    #[serde(rename = "transact-amt")]
+    #[serde(deserialize_with = "deserialize_decimal_from_string_or_number")]
+    pub transact_amt: Decimal,
+    #[serde(rename = "transact-type")]
+    pub transact_type: TransactType,
+    #[serde(rename = "record-id")]
+    #[serde(deserialize_with = "deserialize_number_from_string_or_number")]
+    pub record_id: u64,
+    #[serde(rename = "avail-balance")]
+    #[serde(deserialize_with = "deserialize_decimal_from_string_or_number")]
+    pub avail_balance: Decimal,
+    #[serde(rename = "acct-balance")]
+    #[serde(deserialize_with = "deserialize_decimal_from_string_or_number")]
+    pub acct_balance: Decimal,
+    #[serde(rename = "transact-time")]
+    #[serde(deserialize_with = "deserialize_number_from_string_or_number")]
+    pub transact_time: u64,
+}
+
+impl LedgerEntry {
+    // Signed, negative when the funds leave the account
+    pub fn money(&self) -> Money {
+        Money::new(self.transact_amt, &self.currency)
+    }
+}
+
+// "transact-type" of a ledger entry. Types missing here keep their string in Unknown
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum TransactType {
+    Trade,
+    // Leveraged token (ETP) creation or redemption
+    Etf,
+    TransactFee,
+    // Fee paid with HT or points
+    FeeDeduction,
+    Transfer,
+    Credit,
+    Liquidation,
+    Interest,
+    Deposit,
+    Withdraw,
+    WithdrawFee,
+    Exchange,
+    Rebate,
+    OtherTypes,
+    Unknown(String),
+}
+
+impl TransactType {
+    pub fn as_str(&self) -> &str {
+        match self {
+            TransactType::Trade => "trade",
+            TransactType::Etf => "etf",
+            TransactType::TransactFee => "transact-fee",
+            TransactType::FeeDeduction => "fee-deduction",
+            TransactType::Transfer => "transfer",
+            TransactType::Credit => "credit",
+            TransactType::Liquidation => "liquidation",
+            TransactType::Interest => "interest",
+            TransactType::Deposit => "deposit",
+            TransactType::Withdraw => "withdraw",
+            TransactType::WithdrawFee => "withdraw-fee",
+            TransactType::Exchange => "exchange",
+            TransactType::Rebate => "rebate",
+            TransactType::OtherTypes => "other-types",
+            TransactType::Unknown(transact_type) => transact_type,
+        }
+    }
+
+    // Trading fees, withdraw fees are not counted
+    pub fn is_trading_fee(&self) -> bool {
+        matches!(self, TransactType::TransactFee | TransactType::FeeDeduction)
+    }
+}
+
+impl From<&str> for TransactType {
+    fn from(transact_type: &str) -> Self {
+        match transact_type {
+            "trade" => TransactType::Trade,
+            "etf" => TransactType::Etf,
+            "transact-fee" => TransactType::TransactFee,
+            "fee-deduction" => TransactType::FeeDeduction,
+            "transfer" => TransactType::Transfer,
+            "credit" => TransactType::Credit,
+            "liquidation" => TransactType::Liquidation,
+            "interest" => TransactType::Interest,
+            "deposit" => TransactType::Deposit,
+            "withdraw" => TransactType::Withdraw,
+            "withdraw-fee" => TransactType::WithdrawFee,
+            "exchange" => TransactType::Exchange,
+            "rebate" => TransactType::Rebate,
+            "other-types" => TransactType::OtherTypes,
+            other => TransactType::Unknown(other.to_string()),
+        }
+    }
+}
+
+impl fmt::Display for TransactType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl Serialize for TransactType {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for TransactType {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = String::deserialize(deserializer)?;
+
+        TransactType::from_wire(&value, strict_enums()).map_err(de::Error::custom)
+    }
+}
+
+impl TransactType {
+    fn from_wire(value: &str, strict: bool) -> Result<Self, HuobiError> {
+        match TransactType::from(value) {
+            TransactType::Unknown(_) if strict => Err(HuobiError::ApiError(format!(
+                "unknown TransactType {}",
+                value
+            ))),
+            transact_type => Ok(transact_type),
+        }
+    }
+}
+
+// Single order of /v1/order/orders/{order-id}, same fields as the history
+#[derive(Serialize, Deserialize, Debug)]
+pub struct OrderDetailResult {
+    pub data: TradeHistory,
+}
+
+// Search of past orders, Huobi requires the symbol and the states,
+// e.g. "filled,partial-canceled,canceled"
+#[derive(Debug, Clone, Default)]
+pub struct OrdersQuery {
+    pub symbol: String,
+    pub states: String,
+    pub start_time: Option<u64>,
+    pub end_time: Option<u64>,
+    // Page size, 100 at most
+    pub size: Option<u32>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct TradeHistoryResult {
+    pub data: Vec<TradeHistory>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct TradeHistory {
+    #[serde(deserialize_with = "deserialize_number_from_string_or_number")]
+    pub id: u64,
+    pub symbol: String,
+    #[serde(rename = "account-id")]
+    #[serde(deserialize_with = "deserialize_number_from_string_or_number")]
+    pub account_id: u32,
+    #[serde(deserialize_with = "deserialize_decimal_from_string_or_number")]
+    pub amount: Decimal,
+    #[serde(deserialize_with = "deserialize_decimal_from_string_or_number")]
+    pub price: Decimal,
+    #[serde(rename = "created-at")]
+    #[serde(deserialize_with = "deserialize_number_from_string_or_number")]
+    pub created_at: u64,
+    #[serde(rename = "type")]
+    pub type_order: String,
+    #[serde(rename = "field-amount")]
+    #[serde(deserialize_with = "deserialize_decimal_from_string_or_number")]
+    pub field_amount: Decimal,
+    #[serde(rename = "field-cash-amount")]
+    #[serde(deserialize_with = "deserialize_decimal_from_string_or_number")]
+    pub field_cash_amount: Decimal,
+    #[serde(rename = "field-fees")]
+    #[serde(deserialize_with = "deserialize_decimal_from_string_or_number")]
+    pub field_fees: Decimal,
+    #[serde(rename = "finished-at")]
+    #[serde(deserialize_with = "deserialize_number_from_string_or_number")]
+    pub finished_at: u64,
+    pub source: String,
+    pub state: OrderState,
+    #[serde(rename = "canceled-at")]
+    #[serde(deserialize_with = "deserialize_number_from_string_or_number")]
+    pub canceled_at: u64,
+}
+
+impl TradeHistory {
+    // None for a market order
+    pub fn time_in_force(&self) -> Option<TimeInForce> {
+        TimeInForce::of_order_type(&self.type_order)
+    }
+
+    // Amount filled so far, kept by a partial-canceled order
+    pub fn filled_amount(&self) -> Decimal {
+        self.field_amount
+    }
+
+    pub fn filled(&self, symbol: &Symbol) -> Money {
+        Money::new(self.field_amount, &symbol.base)
+    }
+
+    pub fn filled_value(&self, symbol: &Symbol) -> Money {
+        Money::new(self.field_cash_amount, &symbol.quote)
+    }
+
+    pub fn fee(&self, symbol: &Symbol) -> Money {
+        Money::new(self.field_fees, received_currency(&self.type_order, symbol))
+    }
+}
+
+// An order partially filled then canceled ends partial-canceled, not canceled
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OrderState {
+    Created,
+    Submitted,
+    PartialFilled,
+    Filled,
+    Canceling,
+    Canceled,
+    PartialCanceled,
+    Unknown(String),
+}
+
+impl OrderState {
+    pub const ALL: [OrderState; 7] = [
+        OrderState::Created,
+        OrderState::Submitted,
+        OrderState::PartialFilled,
+        OrderState::Filled,
+        OrderState::Canceling,
+        OrderState::Canceled,
+        OrderState::PartialCanceled,
+    ];
+
+    pub fn as_str(&self) -> &str {
+        match self {
+            OrderState::Created => "created",
+            OrderState::Submitted => "submitted",
+            OrderState::PartialFilled => "partial-filled",
+            OrderState::Filled => "filled",
+            OrderState::Canceling => "canceling",
+            OrderState::Canceled => "canceled",
+            OrderState::PartialCanceled => "partial-canceled",
+            OrderState::Unknown(state) => state,
+        }
+    }
+
+    // The order will not change anymore
+    pub fn is_final(&self) -> bool {
+        matches!(
+            self,
+            OrderState::Filled | OrderState::Canceled | OrderState::PartialCanceled
+        )
+    }
+}
+
+// Huobi searches fills of one symbol within 48 hours per request,
+// without symbol all the online symbols are queried one by one
+#[derive(Debug, Clone, Default)]
+pub struct MatchQuery {
+    pub symbol: Option<String>,
+    // Comma separated order types, e.g. "buy-limit,sell-limit"
+    pub types: Option<String>,
+    pub start_time: Option<u64>,
+    pub end_time: Option<u64>,
+    // Page size, 500 at most
+    pub size: Option<u32>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct MatchResultsResult {
+    pub data: Vec<MatchResult>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct MatchResult {
+    #[serde(deserialize_with = "deserialize_number_from_string_or_number")]
+    pub id: u64,
+    #[serde(rename = "order-id")]
+    #[serde(deserialize_with = "deserialize_number_from_string_or_number")]
+    pub order_id: u64,
+    #[serde(rename = "match-id")]
+    #[serde(deserialize_with = "deserialize_number_from_string_or_number")]
+    pub match_id: u64,
+    #[serde(rename = "trade-id")]
+    #[serde(deserialize_with = "deserialize_number_from_string_or_number")]
+    pub trade_id: u64,
+    pub symbol: String,
+    #[serde(rename = "type")]
+    pub order_type: String,
+    pub source: String,
+    // "maker" or "taker"
+    pub role: String,
+    #[serde(deserialize_with = "deserialize_decimal_from_string_or_number")]
+    pub price: Decimal,
+    #[serde(rename = "filled-amount")]
+    #[serde(deserialize_with = "deserialize_decimal_from_string_or_number")]
+    pub filled_amount: Decimal,
+    #[serde(rename = "filled-fees")]
+    #[serde(deserialize_with = "deserialize_decimal_from_string_or_number")]
+    pub filled_fees: Decimal,
+    #[serde(rename = "fee-currency")]
+    pub fee_currency: String,
+    #[serde(rename = "created-at")]
+    #[serde(deserialize_with = "deserialize_number_from_string_or_number")]
+    pub created_at: u64,
+}
+
+// A fill only names its symbol, the Symbol tells its base and quote currencies
+impl MatchResult {
+    pub fn filled(&self, symbol: &Symbol) -> Money {
+        Money::new(self.filled_amount, &symbol.base)
+    }
+
+    pub fn filled_value(&self, symbol: &Symbol) -> Money {
+        Money::new(self.filled_amount * self.price, &symbol.quote)
+    }
+
+    // In the received currency, or in HT or point when fees are deducted from them
+    pub fn fee(&self) -> Money {
+        Money::new(self.filled_fees, &self.fee_currency)
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Symbol {
+    #[serde(rename = "base-currency")]
+    pub base: String,
+    #[serde(rename = "quote-currency")]
+    pub quote: String,
+    #[serde(rename = "price-precision")]
+    #[serde(deserialize_with = "deserialize_number_from_string_or_number")]
+    pub price_precision: u8,
+    #[serde(rename = "amount-precision")]
+    #[serde(deserialize_with = "deserialize_number_from_string_or_number")]
+    pub amount_precision: u8,
+    #[serde(rename = "symbol-partition")]
+    pub partition: String,
+    pub symbol: String,
+    pub state: SymbolState,
+    #[serde(rename = "value-precision")]
+    #[serde(deserialize_with = "deserialize_number_from_string_or_number")]
+    pub value_precision: u8,
+    #[serde(rename = "min-order-amt")]
+    #[serde(deserialize_with = "deserialize_number_from_string_or_number")]
+    pub min_amount: f64,
+    #[serde(rename = "max-order-amt")]
+    #[serde(deserialize_with = "deserialize_number_from_string_or_number")]
+    pub max_amount: f64,
+    #[serde(rename = "min-order-value")]
+    #[serde(deserialize_with = "deserialize_number_from_string_or_number")]
+    pub min_value: f64,
+    #[serde(default, rename = "leverage-ratio")]
+    #[serde(deserialize_with = "deserialize_number_from_string_or_number")]
+    pub max_leverage: f32,
+}
+
+impl Symbol {
+    // Largest valid amount to buy with quote_balance at price,
+    // 0 if even that amount is under the minimum amount or value of an order
+    pub fn max_buy_amount(&self, quote_balance: f64, price: f64) -> f64 {
+        if price <= 0. {
+            return 0.;
+        }
+
+        let amount = floor_to(
+            (quote_balance / price).min(self.max_amount),
+            self.amount_precision,
+        );
+
+        if amount < self.min_amount || amount * price < self.min_value {
+            return 0.;
+        }
+
+        amount
+    }
+
+    // Largest valid amount to sell out of base_balance, the order value is not known
+    // without a price so only the minimum amount is checked
+    pub fn max_sell_amount(&self, base_balance: f64) -> f64 {
+        let amount = floor_to(base_balance.min(self.max_amount), self.amount_precision);
+
+        if amount < self.min_amount {
+            return 0.;
+        }
+
+        amount
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FeeRateResult {
+    pub data: Vec<FeeRate>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct FeeRate {
+    pub symbol: String,
+    #[serde(deserialize_with = "deserialize_number_from_string_or_number")]
+    pub maker_fee_rate: f64,
+    #[serde(deserialize_with = "deserialize_number_from_string_or_number")]
+    pub taker_fee_rate: f64,
+    // Rates after point deduction and other discounts
+    #[serde(deserialize_with = "deserialize_number_from_string_or_number")]
+    pub actual_maker_rate: f64,
+    #[serde(deserialize_with = "deserialize_number_from_string_or_number")]
+    pub actual_taker_rate: f64,
+}
+
+// Effective fee of the account for a symbol, in basis points.
+// Huobi does not report the VIP level number itself, only the rates it grants
+#[derive(Debug, Clone, PartialEq)]
+pub struct VipInfo {
+    pub symbol: String,
+    pub maker_bps: f64,
+    pub taker_bps: f64,
+    pub base_maker_bps: f64,
+    pub base_taker_bps: f64,
+}
+
+impl From<FeeRate> for VipInfo {
+    fn from(fee_rate: FeeRate) -> Self {
+        VipInfo {
+            symbol: fee_rate.symbol,
+            maker_bps: fee_rate.actual_maker_rate * 10_000.,
+            taker_bps: fee_rate.actual_taker_rate * 10_000.,
+            base_maker_bps: fee_rate.maker_fee_rate * 10_000.,
+            base_taker_bps: fee_rate.taker_fee_rate * 10_000.,
+        }
+    }
+}
+
+// Market endpoints wrap their payload into "tick" and echo the channel in "ch",
+// e.g. "market.btcusdt.detail.merged"
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TickResult<T> {
+    pub status: String,
+    pub ch: String,
+    #[serde(deserialize_with = "deserialize_number_from_string_or_number")]
+    pub ts: u64,
+    pub tick: T,
+}
+
+impl<T> TickResult<T> {
+    pub fn check_channel(&self, expected: &str) -> Result<(), HuobiError> {
+        if self.ch == expected {
+            Ok(())
+        } else {
+            Err(HuobiError::ChannelMismatch {
+                expected: expected.to_string(),
+                received: self.ch.clone(),
+            })
+        }
+    }
+}
+
+// Summary of the Huobi status page: incidents and planned maintenances
+#[derive(Debug, Deserialize, Clone)]
+pub struct SystemStatus {
+    pub status: StatusIndicator,
+    #[serde(default)]
+    pub incidents: Vec<Incident>,
+    #[serde(default)]
+    pub scheduled_maintenances: Vec<Incident>,
+}
+
+impl SystemStatus {
+    // Unresolved incidents of at least the given impact
+    pub fn active_incidents(&self, min_impact: Impact) -> Vec<&Incident> {
+        self.incidents
+            .iter()
+            .filter(|incident| incident.resolved_at.is_none() && incident.impact >= min_impact)
+            .collect()
+    }
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct StatusIndicator {
+    pub indicator: Impact,
+    pub description: String,
+}
+
+// Ordered from the least severe, an unknown impact is taken as the most severe
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum Impact {
+    None,
+    Maintenance,
+    Minor,
+    Major,
+    Critical,
+    #[serde(other)]
+    Unknown,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct Incident {
+    pub id: String,
+    pub name: String,
+    // investigating, identified, monitoring, resolved, scheduled, in_progress...
+    pub status: String,
+    pub impact: Impact,
+    #[serde(deserialize_with = "deserialize_rfc3339")]
+    pub created_at: DateTime<Utc>,
+    #[serde(default, deserialize_with = "deserialize_option_rfc3339")]
+    pub resolved_at: Option<DateTime<Utc>>,
+    // Maintenance window, only set for scheduled maintenances
+    #[serde(default, deserialize_with = "deserialize_option_rfc3339")]
+    pub scheduled_for: Option<DateTime<Utc>>,
+    #[serde(default, deserialize_with = "deserialize_option_rfc3339")]
+    pub scheduled_until: Option<DateTime<Utc>>,
+}
+
+fn deserialize_rfc3339<'de, D>(deserializer: D) -> Result<DateTime<Utc>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let time = String::deserialize(deserializer)?;
+
+    DateTime::parse_from_rfc3339(&time)
+        .map(|time| time.with_timezone(&Utc))
+        .map_err(de::Error::custom)
+}
+
+// Huobi sends an empty string for a value not set yet
+fn deserialize_non_empty<'de, D>(deserializer: D) -> Result<Option<String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Ok(Option::<String>::deserialize(deserializer)?.filter(|value| !value.is_empty()))
+}
+
+fn deserialize_option_rfc3339<'de, D>(deserializer: D) -> Result<Option<DateTime<Utc>>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    match Option::<String>::deserialize(deserializer)? {
+        Some(time) => DateTime::parse_from_rfc3339(&time)
+            .map(|time| Some(time.with_timezone(&Utc)))
+            .map_err(de::Error::custom),
+        None => Ok(None),
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct MergedTicker {
+    #[serde(deserialize_with = "deserialize_number_from_string_or_number")]
+    pub id: u64,
+    #[serde(deserialize_with = "deserialize_number_from_string_or_number")]
+    pub amount: f64,
+    #[serde(deserialize_with = "deserialize_number_from_string_or_number")]
+    pub count: u64,
+    #[serde(deserialize_with = "deserialize_number_from_string_or_number")]
+    pub open: f64,
+    #[serde(deserialize_with = "deserialize_number_from_string_or_number")]
+    pub close: f64,
+    #[serde(deserialize_with = "deserialize_number_from_string_or_number")]
+    pub low: f64,
+    #[serde(deserialize_with = "deserialize_number_from_string_or_number")]
+    pub high: f64,
+    #[serde(deserialize_with = "deserialize_number_from_string_or_number")]
+    pub vol: f64,
+    #[serde(deserialize_with = "deserialize_level_from_string_or_number")]
+    pub bid: [f64; 2], // [price, size]
+    #[serde(deserialize_with = "deserialize_level_from_string_or_number")]
+    pub ask: [f64; 2],
+}
+
+// Entry of the all-tickers snapshot, the last 24h of a symbol. The WS
+// "market.overview" push has no bid and ask
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SymbolTicker {
+    pub symbol: String,
+    #[serde(deserialize_with = "deserialize_number_from_string_or_number")]
+    pub open: f64,
+    #[serde(deserialize_with = "deserialize_number_from_string_or_number")]
+    pub high: f64,
+    #[serde(deserialize_with = "deserialize_number_from_string_or_number")]
+    pub low: f64,
+    #[serde(deserialize_with = "deserialize_number_from_string_or_number")]
+    pub close: f64,
+    #[serde(deserialize_with = "deserialize_number_from_string_or_number")]
+    pub amount: f64,
+    #[serde(deserialize_with = "deserialize_number_from_string_or_number")]
+    pub vol: f64,
+    #[serde(deserialize_with = "deserialize_number_from_string_or_number")]
+    pub count: u64,
+    #[serde(default)]
+    #[serde(deserialize_with = "deserialize_option_number_from_string_or_number")]
+    pub bid: Option<f64>,
+    #[serde(default)]
+    #[serde(deserialize_with = "deserialize_option_number_from_string_or_number")]
+    pub bid_size: Option<f64>,
+    #[serde(default)]
+    #[serde(deserialize_with = "deserialize_option_number_from_string_or_number")]
+    pub ask: Option<f64>,
+    #[serde(default)]
+    #[serde(deserialize_with = "deserialize_option_number_from_string_or_number")]
+    pub ask_size: Option<f64>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TickersResult {
+    #[serde(deserialize_with = "deserialize_number_from_string_or_number")]
+    pub ts: u64,
+    pub data: Vec<SymbolTicker>,
+}
+
+// Aggregation of the order book: step0 is not aggregated, every next step
+// merges the price levels further
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DepthStep {
+    Step0,
+    Step1,
+    Step2,
+    Step3,
+    Step4,
+    Step5,
+}
+
+impl DepthStep {
+    pub const ALL: [DepthStep; 6] = [
+        DepthStep::Step0,
+        DepthStep::Step1,
+        DepthStep::Step2,
+        DepthStep::Step3,
+        DepthStep::Step4,
+        DepthStep::Step5,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DepthStep::Step0 => "step0",
+            DepthStep::Step1 => "step1",
+            DepthStep::Step2 => "step2",
+            DepthStep::Step3 => "step3",
+            DepthStep::Step4 => "step4",
+            DepthStep::Step5 => "step5",
+        }
+    }
+}
+
+// Bids are sorted from the best (highest) price, asks from the best (lowest) price
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct OrderBook {
+    #[serde(deserialize_with = "deserialize_number_from_string_or_number")]
+    pub ts: u64,
+    #[serde(default)]
+    #[serde(deserialize_with = "deserialize_number_from_string_or_number")]
+    pub version: u64,
+    #[serde(deserialize_with = "deserialize_levels_from_string_or_number")]
+    pub bids: Vec<[f64; 2]>, // [price, size]
+    #[serde(deserialize_with = "deserialize_levels_from_string_or_number")]
+    pub asks: Vec<[f64; 2]>,
+}
+
+impl OrderBook {
+    pub fn best_bid(&self) -> Option<f64> {
+        self.bids.first().map(|bid| bid[0])
+    }
+
+    pub fn best_ask(&self) -> Option<f64> {
+        self.asks.first().map(|ask| ask[0])
+    }
+
+    // None when a side of the book is empty
+    pub fn mid_price(&self) -> Option<f64> {
+        Some((self.best_bid()? + self.best_ask()?) / 2.)
+    }
+
+    // Spread relative to the mid price in basis points
+    pub fn spread_bps(&self) -> Option<f64> {
+        let mid_price = self.mid_price()?;
+
+        if mid_price <= 0. {
+            return None;
+        }
+
+        Some((self.best_ask()? - self.best_bid()?) / mid_price * 10_000.)
+    }
+
+    // Fill of a market order of size (base currency) against the levels of the book:
+    // a buy takes the asks, a sell the bids. Only the levels of this book are seen,
+    // the fill may go deeper on a book of a coarser step or with more levels
+    pub fn estimate_fill(&self, side: Side, size: f64) -> FillEstimate {
+        let levels = match side {
+            Side::Buy => &self.asks,
+            Side::Sell => &self.bids,
+        };
+
+        let mut filled = 0.;
+        let mut cost = 0.;
+        let mut worst_price = None;
+
+        for level in levels {
+            let left = size - filled;
+            if left <= 0. {
+                break;
+            }
+
+            let taken = left.min(level[1]);
+            filled += taken;
+            cost += taken * level[0];
+            worst_price = Some(level[0]);
+        }
+
+        FillEstimate {
+            avg_price: if filled > 0. {
+                Some(cost / filled)
+            } else {
+                None
+            },
+            worst_price,
+            filled,
+            unfilled: (size - filled).max(0.),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Side {
+    Buy,
+    Sell,
+}
+
+impl Side {
+    pub const ALL: [Side; 2] = [Side::Buy, Side::Sell];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Side::Buy => "buy",
+            Side::Sell => "sell",
+        }
+    }
+}
+
+// avg_price and worst_price are None when nothing fills. unfilled is what the
+// book is too thin for
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FillEstimate {
+    pub avg_price: Option<f64>,
+    pub worst_price: Option<f64>,
+    pub filled: f64,
+    pub unfilled: f64,
+}
+
+impl FillEstimate {
+    // Cost of the fill compared to the reference (e.g. the mid price), positive when
+    // the fill is worse than the reference
+    pub fn slippage_bps(&self, side: Side, reference: f64) -> Option<f64> {
+        let avg_price = self.avg_price?;
+
+        if reference <= 0. {
+            return None;
+        }
+
+        let slippage = match side {
+            Side::Buy => avg_price - reference,
+            Side::Sell => reference - avg_price,
+        };
+
+        Some(slippage / reference * 10_000.)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum KlinePeriod {
+    Min1,
+    Min5,
+    Min15,
+    Min30,
+    Min60,
+    Hour4,
+    Day1,
+    Week1,
+    Mon1,
+    Year1,
+}
+
+impl KlinePeriod {
+    pub const ALL: [KlinePeriod; 10] = [
+        KlinePeriod::Min1,
+        KlinePeriod::Min5,
+        KlinePeriod::Min15,
+        KlinePeriod::Min30,
+        KlinePeriod::Min60,
+        KlinePeriod::Hour4,
+        KlinePeriod::Day1,
+        KlinePeriod::Week1,
+        KlinePeriod::Mon1,
+        KlinePeriod::Year1,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            KlinePeriod::Min1 => "1min",
+            KlinePeriod::Min5 => "5min",
+            KlinePeriod::Min15 => "15min",
+            KlinePeriod::Min30 => "30min",
+            KlinePeriod::Min60 => "60min",
+            KlinePeriod::Hour4 => "4hour",
+            KlinePeriod::Day1 => "1day",
+            KlinePeriod::Week1 => "1week",
+            KlinePeriod::Mon1 => "1mon",
+            KlinePeriod::Year1 => "1year",
+        }
+    }
+}
+
+// id is the open time of the candle in seconds. The last candle is still open,
+// it comes again with the same id on every update
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Kline {
+    #[serde(deserialize_with = "deserialize_number_from_string_or_number")]
+    pub id: u64,
+    #[serde(deserialize_with = "deserialize_number_from_string_or_number")]
+    pub open: f64,
+    #[serde(deserialize_with = "deserialize_number_from_string_or_number")]
+    pub close: f64,
+    #[serde(deserialize_with = "deserialize_number_from_string_or_number")]
+    pub low: f64,
+    #[serde(deserialize_with = "deserialize_number_from_string_or_number")]
+    pub high: f64,
+    // base currency volume
+    #[serde(deserialize_with = "deserialize_number_from_string_or_number")]
+    pub amount: f64,
+    // quote currency volume
+    #[serde(deserialize_with = "deserialize_number_from_string_or_number")]
+    pub vol: f64,
+    #[serde(deserialize_with = "deserialize_number_from_string_or_number")]
+    pub count: u64,
+}
+
+// Huobi is not consistent about numbers: the same field may come as 1.5 or as "1.5"
+// depending on the endpoint, so numeric fields accept both shapes
+pub fn deserialize_number_from_string_or_number<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: FromStr + Deserialize<'de>,
+    <T as FromStr>::Err: fmt::Display,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum StringOrNumber<T> {
+        String(String),
+        Number(T),
+    }
+
+    match StringOrNumber::<T>::deserialize(deserializer)? {
+        StringOrNumber::String(value) => value.trim().parse::<T>().map_err(de::Error::custom),
+        StringOrNumber::Number(value) => Ok(value),
+    }
+}
+
+pub fn deserialize_option_number_from_string_or_number<'de, D, T>(
+    deserializer: D,
+) -> Result<Option<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: FromStr + Deserialize<'de>,
+    <T as FromStr>::Err: fmt::Display,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum StringOrNumber<T> {
+        String(String),
+        Number(T),
+    }
+
+    match Option::<StringOrNumber<T>>::deserialize(deserializer)? {
+        Some(StringOrNumber::String(value)) => value
+            .trim()
+            .parse::<T>()
+            .map(Some)
+            .map_err(de::Error::custom),
+        Some(StringOrNumber::Number(value)) => Ok(Some(value)),
+        None => Ok(None),
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum LevelValue {
+    String(String),
+    Number(f64),
+}
+
+impl LevelValue {
+    fn parse<E: de::Error>(self) -> Result<f64, E> {
+        match self {
+            LevelValue::String(value) => value.trim().parse().map_err(de::Error::custom),
+            LevelValue::Number(value) => Ok(value),
+        }
+    }
+}
+
+// [price, size] of a book level
+pub fn deserialize_level_from_string_or_number<'de, D>(
+    deserializer: D,
+) -> Result<[f64; 2], D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let [price, size] = <[LevelValue; 2]>::deserialize(deserializer)?;
+
+    Ok([price.parse()?, size.parse()?])
+}
+
+pub fn deserialize_levels_from_string_or_number<'de, D>(
+    deserializer: D,
+) -> Result<Vec<[f64; 2]>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Vec::<[LevelValue; 2]>::deserialize(deserializer)?
+        .into_iter()
+        .map(|[price, size]| Ok([price.parse()?, size.parse()?]))
+        .collect()
+}
+
+// Same shapes for Decimal fields, dust amounts also come in scientific notation ("1.0E-8")
+fn parse_decimal(value: &str) -> Result<Decimal, rust_decimal::Error> {
+    let value = value.trim();
+
+    Decimal::from_str(value).or_else(|_| Decimal::from_scientific(value))
+}
+
+pub fn deserialize_decimal_from_string_or_number<'de, D>(
+    deserializer: D,
+) -> Result<Decimal, D::Error>
+where
+    D: Deserializer<'de>,
 {
-    deserializer.deserialize_any(F64Visitor)
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum StringOrNumber {
+        String(String),
+        Number(f64),
+    }
+
+    match StringOrNumber::deserialize(deserializer)? {
+        StringOrNumber::String(value) => parse_decimal(&value).map_err(de::Error::custom),
+        StringOrNumber::Number(value) => Decimal::from_f64(value)
+            .ok_or_else(|| de::Error::custom(format!("invalid decimal {}", value))),
+    }
 }
 
-struct F64Visitor;
-impl<'de> Visitor<'de> for F64Visitor {
-    type Value = f64;
-    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
-        formatter.write_str("a string representation of a f64")
+pub fn deserialize_option_decimal_from_string_or_number<'de, D>(
+    deserializer: D,
+) -> Result<Option<Decimal>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum StringOrNumber {
+        String(String),
+        Number(f64),
     }
-    fn visit_str<E>(self, value: &str) -> Result<f64, E>
-    where
-        E: de::Error,
-    {
-        if let Ok(integer) = value.parse::<i32>() {
-            Ok(integer as f64)
-        } else {
-            value.parse::<f64>().map_err(|_err| {
-                E::invalid_value(Unexpected::Str(value), &"a string representation of a f64")
-            })
+
+    match Option::<StringOrNumber>::deserialize(deserializer)? {
+        Some(StringOrNumber::String(value)) => {
+            parse_decimal(&value).map(Some).map_err(de::Error::custom)
         }
+        Some(StringOrNumber::Number(value)) => Decimal::from_f64(value)
+            .map(Some)
+            .ok_or_else(|| de::Error::custom(format!("invalid decimal {}", value))),
+        None => Ok(None),
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AccountSubs {
+    pub action: String,
+    pub ch: String,
+    pub data: AccountUpdate,
+}
+
+// Push of "accounts.update#mode", balances are absolute values not deltas.
+// Depending on the mode only one of balance and available may be set
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct AccountUpdate {
+    pub currency: String,
+    #[serde(deserialize_with = "deserialize_number_from_string_or_number")]
+    pub account_id: u64,
+    #[serde(default)]
+    #[serde(deserialize_with = "deserialize_option_decimal_from_string_or_number")]
+    pub balance: Option<Decimal>,
+    #[serde(default)]
+    #[serde(deserialize_with = "deserialize_option_decimal_from_string_or_number")]
+    pub available: Option<Decimal>,
+    pub change_type: Option<String>,
+    pub account_type: Option<String>,
+    // Not set for the balances pushed right after subscription
+    #[serde(default)]
+    #[serde(deserialize_with = "deserialize_option_number_from_string_or_number")]
+    pub change_time: Option<u64>,
+    #[serde(default)]
+    #[serde(deserialize_with = "deserialize_option_number_from_string_or_number")]
+    pub seq_num: Option<u64>,
+}
+
+impl AccountUpdate {
+    pub fn change_datetime(&self) -> Option<DateTime<Utc>> {
+        self.change_time.and_then(millis_to_datetime)
+    }
+}
+
+// WS pushes carry times in ms, they are kept as is in the models
+// and converted on demand without losing the milliseconds
+pub fn millis_to_datetime(millis: u64) -> Option<DateTime<Utc>> {
+    if millis > i64::MAX as u64 {
+        return None;
     }
+
+    Utc.timestamp_millis_opt(millis as i64).single()
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -222,15 +2735,66 @@ pub enum EventType {
     Trade(Trade),
 }
 
+impl EventType {
+    pub fn order_id(&self) -> u64 {
+        match self {
+            EventType::Creation(creation) => creation.order_id,
+            EventType::Cancellation(cancellation) => cancellation.order_id,
+            EventType::Trade(trade) => trade.order_id,
+        }
+    }
+
+    // Order pushes have no sequence number: the trade id identifies a fill, the
+    // creation and cancellation times identify the other updates of an order
+    pub fn sequence(&self) -> u64 {
+        match self {
+            EventType::Creation(creation) => creation.order_create_time,
+            EventType::Cancellation(cancellation) => cancellation.last_act_time,
+            EventType::Trade(trade) => trade.trade_id,
+        }
+    }
+
+    // Time of the update in ms as pushed: creation time, last activity of a
+    // cancellation or time of the fill
+    pub fn time_millis(&self) -> u64 {
+        match self {
+            EventType::Creation(creation) => creation.order_create_time,
+            EventType::Cancellation(cancellation) => cancellation.last_act_time,
+            EventType::Trade(trade) => trade.trade_time,
+        }
+    }
+
+    // Same time with its millisecond part, None if it is out of range
+    pub fn time(&self) -> Option<DateTime<Utc>> {
+        millis_to_datetime(self.time_millis())
+    }
+
+    // Same key for a redelivered update
+    pub fn dedup_key(&self) -> (u64, &'static str, u64) {
+        let kind = match self {
+            EventType::Creation(_) => "creation",
+            EventType::Cancellation(_) => "cancellation",
+            EventType::Trade(_) => "trade",
+        };
+
+        (self.order_id(), kind, self.sequence())
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct Creation {
-    pub order_size: String,
+    #[serde(deserialize_with = "deserialize_decimal_from_string_or_number")]
+    pub order_size: Decimal,
+    #[serde(deserialize_with = "deserialize_number_from_string_or_number")]
     pub order_create_time: u64,
+    #[serde(deserialize_with = "deserialize_number_from_string_or_number")]
     pub account_id: u32,
-    pub order_price: String,
+    #[serde(deserialize_with = "deserialize_decimal_from_string_or_number")]
+    pub order_price: Decimal,
     #[serde(rename = "type")]
     pub type_: String,
+    #[serde(deserialize_with = "deserialize_number_from_string_or_number")]
     pub order_id: u64,
     pub client_order_id: String,
     pub order_source: String,
@@ -239,19 +2803,36 @@ pub struct Creation {
     pub event_type: String,
 }
 
+impl Creation {
+    // In base currency, a market buy is sized in quote
+    pub fn size(&self, symbol: &Symbol) -> Money {
+        if self.type_ == "buy-market" {
+            Money::new(self.order_size, &symbol.quote)
+        } else {
+            Money::new(self.order_size, &symbol.base)
+        }
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct Cancellation {
+    #[serde(deserialize_with = "deserialize_number_from_string_or_number")]
     pub last_act_time: u64,
-    pub remain_amt: String,
-    pub exec_amt: String,
+    #[serde(deserialize_with = "deserialize_decimal_from_string_or_number")]
+    pub remain_amt: Decimal,
+    #[serde(deserialize_with = "deserialize_decimal_from_string_or_number")]
+    pub exec_amt: Decimal,
+    #[serde(deserialize_with = "deserialize_number_from_string_or_number")]
     pub order_id: u64,
     #[serde(rename = "type")]
     pub type_: String,
     pub client_order_id: String,
     pub order_source: String,
-    pub order_price: String,
-    pub order_size: String,
+    #[serde(deserialize_with = "deserialize_decimal_from_string_or_number")]
+    pub order_price: Decimal,
+    #[serde(deserialize_with = "deserialize_decimal_from_string_or_number")]
+    pub order_size: Decimal,
     pub order_status: String,
     pub symbol: String,
     pub event_type: String,
@@ -260,21 +2841,1017 @@ pub struct Cancellation {
 #[derive(Debug, Serialize, Deserialize, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct Trade {
-    pub trade_price: String,
-    pub trade_volume: String,
+    #[serde(deserialize_with = "deserialize_decimal_from_string_or_number")]
+    pub trade_price: Decimal,
+    #[serde(deserialize_with = "deserialize_decimal_from_string_or_number")]
+    pub trade_volume: Decimal,
+    #[serde(deserialize_with = "deserialize_number_from_string_or_number")]
     pub trade_id: u64,
+    #[serde(deserialize_with = "deserialize_number_from_string_or_number")]
     pub trade_time: u64,
     pub aggressor: bool,
-    pub remain_amt: String,
-    pub exec_amt: String,
+    #[serde(deserialize_with = "deserialize_decimal_from_string_or_number")]
+    pub remain_amt: Decimal,
+    #[serde(deserialize_with = "deserialize_decimal_from_string_or_number")]
+    pub exec_amt: Decimal,
+    #[serde(deserialize_with = "deserialize_number_from_string_or_number")]
     pub order_id: u64,
     #[serde(rename = "type")]
     pub type_: String,
     pub client_order_id: String,
     pub order_source: String,
-    pub order_price: String,
-    pub order_size: String,
+    #[serde(deserialize_with = "deserialize_decimal_from_string_or_number")]
+    pub order_price: Decimal,
+    #[serde(deserialize_with = "deserialize_decimal_from_string_or_number")]
+    pub order_size: Decimal,
     pub order_status: String,
     pub symbol: String,
     pub event_type: String,
 }
+
+// The push has no fee, it comes with the match results or the trade.clearing channel
+impl Trade {
+    pub fn filled(&self, symbol: &Symbol) -> Money {
+        Money::new(self.trade_volume, &symbol.base)
+    }
+
+    pub fn filled_value(&self, symbol: &Symbol) -> Money {
+        Money::new(self.trade_volume * self.trade_price, &symbol.quote)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use serde::de::DeserializeOwned;
+
+    // Display then FromStr gives back every variant, and the strings are the serde ones
+    #[test]
+    fn wire_strings_round_trip() {
+        fn round_trip<T>(values: &[T])
+        where
+            T: PartialEq + fmt::Debug + fmt::Display + FromStr,
+        {
+            for value in values {
+                match value.to_string().parse::<T>() {
+                    Ok(parsed) => assert_eq!(parsed, *value),
+                    Err(_) => panic!("{} does not parse back", value),
+                }
+            }
+        }
+
+        round_trip(&ApiVersion::ALL);
+        round_trip(&AccountState::ALL);
+        round_trip(&OrderState::ALL);
+        round_trip(&WithdrawState::ALL);
+        round_trip(&SymbolState::ALL);
+        round_trip(&OrderType::ALL);
+        round_trip(&StpMode::ALL);
+        round_trip(&TimeInForce::ALL);
+        round_trip(&OrderSource::ALL);
+        round_trip(&MarginTradePurpose::ALL);
+        round_trip(&DepthStep::ALL);
+        round_trip(&KlinePeriod::ALL);
+        round_trip(&Side::ALL);
+
+        for state in SymbolState::ALL.iter() {
+            assert_eq!(
+                serde_json::to_string(state).unwrap(),
+                format!("\"{}\"", state)
+            );
+        }
+        for state in AccountState::ALL.iter() {
+            assert_eq!(
+                serde_json::to_string(state).unwrap(),
+                format!("\"{}\"", state)
+            );
+        }
+
+        assert_eq!(
+            "BUY-LIMIT".parse::<OrderType>().unwrap(),
+            OrderType::BuyLimit
+        );
+        assert!("buy-stop".parse::<OrderType>().is_err());
+        assert!("unknown".parse::<SymbolState>().is_err());
+    }
+
+    #[test]
+    fn ledger_transact_types() {
+        let entry: LedgerEntry = serde_json::from_str(r#"{"account-id":5260185,"currency":"usdt","transact-amt":"-0.2","transact-type":"transact-fee","record-id":89373333576,"avail-balance":"100.1","acct-balance":"100.1","transact-time":1630000000000}"#).unwrap();
+        assert_eq!(entry.transact_type, TransactType::TransactFee);
+        assert!(entry.transact_type.is_trading_fee());
+
+        for transact_type in ["trade", "withdraw-fee", "other-types", "rebate"].iter() {
+            assert_eq!(TransactType::from(*transact_type).as_str(), *transact_type);
+        }
+
+        let unknown = TransactType::from("staking-reward");
+        assert_eq!(unknown, TransactType::Unknown("staking-reward".into()));
+        assert_eq!(
+            serde_json::to_string(&unknown).unwrap(),
+            r#""staking-reward""#
+        );
+    }
+
+    #[test]
+    fn event_time_keeps_millis() {
+        let trade: EventType = serde_json::from_str(r#"{"tradePrice":"76.000000000000000000","tradeVolume":"1.013157894736842100","tradeId":301,"tradeTime":1583854188883,"aggressor":true,"remainAmt":"0.000000000000000400000000000000000000","execAmt":"2","orderId":27163536,"type":"sell-limit","clientOrderId":"abc123","orderSource":"spot-api","orderPrice":"15000","orderSize":"0.01","orderStatus":"filled","symbol":"btcusdt","eventType":"trade"}"#).unwrap();
+
+        assert_eq!(trade.time_millis(), 1583854188883);
+        let time = trade.time().unwrap();
+        assert_eq!(time.timestamp_millis(), 1583854188883);
+        assert_eq!(time.timestamp_subsec_millis(), 883);
+
+        assert_eq!(millis_to_datetime(u64::MAX), None);
+    }
+
+    // Numbers between ' in the template come quoted then bare
+    fn both_forms<T: DeserializeOwned>(template: &str) -> Vec<T> {
+        [template.replace('\'', "\""), template.replace('\'', "")]
+            .iter()
+            .map(|json| serde_json::from_str(json).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn order_amounts_from_string_or_number() {
+        for order in both_forms::<OpenOrder>(
+            r#"{"id":11,"client-order-id":"","symbol":"btcusdt","price":'45000.5',"amount":'0.02',"created-at":1630000000000,"type":"buy-limit","filled-amount":'0.015',"filled-cash-amount":'675.0075',"filled-fees":'0.00003',"source":"spot-api","state":"partial-filled"}"#,
+        ) {
+            assert_eq!(order.price, Decimal::new(450005, 1));
+            assert_eq!(order.amount, Decimal::new(2, 2));
+            assert_eq!(order.filled_amount, Decimal::new(15, 3));
+            assert_eq!(order.filled_cash_amount, Decimal::new(6750075, 4));
+            assert_eq!(order.filled_fees, Decimal::new(3, 5));
+        }
+
+        for order in both_forms::<TradeHistory>(
+            r#"{"id":13,"symbol":"btcusdt","account-id":100009,"amount":'0.02',"price":'45000',"created-at":1630000000000,"type":"buy-limit","field-amount":'0.02',"field-cash-amount":'900',"field-fees":'0.00004',"finished-at":1630000300000,"source":"spot-api","state":"filled","canceled-at":0}"#,
+        ) {
+            assert_eq!(order.amount, Decimal::new(2, 2));
+            assert_eq!(order.price, Decimal::from(45000));
+            assert_eq!(order.filled_amount(), Decimal::new(2, 2));
+            assert_eq!(order.field_cash_amount, Decimal::from(900));
+            assert_eq!(order.field_fees, Decimal::new(4, 5));
+        }
+    }
+
+    #[test]
+    fn order_event_amounts_from_string_or_number() {
+        for creation in both_forms::<Creation>(
+            r#"{"orderSize":'0.01',"orderCreateTime":1583853365586,"accountId":992701,"orderPrice":'15000',"type":"sell-limit","orderId":27163533,"clientOrderId":"abc123","orderSource":"spot-api","orderStatus":"submitted","symbol":"btcusdt","eventType":"creation"}"#,
+        ) {
+            assert_eq!(creation.order_size, Decimal::new(1, 2));
+            assert_eq!(creation.order_price, Decimal::from(15000));
+        }
+
+        for cancellation in both_forms::<Cancellation>(
+            r#"{"lastActTime":1583853475406,"remainAmt":'0.006',"execAmt":'0.004',"orderId":27163533,"type":"sell-limit","clientOrderId":"abc123","orderSource":"spot-api","orderPrice":'15000',"orderSize":'0.01',"orderStatus":"partial-canceled","symbol":"btcusdt","eventType":"cancellation"}"#,
+        ) {
+            assert_eq!(cancellation.remain_amt, Decimal::new(6, 3));
+            assert_eq!(cancellation.exec_amt, Decimal::new(4, 3));
+            assert_eq!(cancellation.order_price, Decimal::from(15000));
+            assert_eq!(cancellation.order_size, Decimal::new(1, 2));
+        }
+
+        let trade = r#"{"tradePrice":'15000.5',"tradeVolume":'0.004',"tradeId":301,"tradeTime":1583854188883,"aggressor":true,"remainAmt":'0.006',"execAmt":'0.004',"orderId":27163536,"type":"sell-limit","clientOrderId":"abc123","orderSource":"spot-api","orderPrice":'15000',"orderSize":'0.01',"orderStatus":"partial-filled","symbol":"btcusdt","eventType":"trade"}"#;
+
+        for trade in both_forms::<Trade>(trade) {
+            assert_eq!(trade.trade_price, Decimal::new(150005, 1));
+            assert_eq!(trade.trade_volume, Decimal::new(4, 3));
+            assert_eq!(trade.remain_amt, Decimal::new(6, 3));
+            assert_eq!(trade.exec_amt, Decimal::new(4, 3));
+            assert_eq!(trade.order_price, Decimal::from(15000));
+            assert_eq!(trade.order_size, Decimal::new(1, 2));
+        }
+
+        let malformed = trade.replace("'15000.5'", "'15000.5x'").replace('\'', "\"");
+        assert!(serde_json::from_str::<Trade>(&malformed).is_err());
+    }
+
+    fn is_equal(value: f64, other_value: f64) -> bool {
+        (value - other_value).abs() < 1.0e-8
+    }
+
+    #[test]
+    fn api_version_path() {
+        assert_eq!(
+            ApiVersion::V1.path("/account/accounts"),
+            "/v1/account/accounts"
+        );
+        assert_eq!(
+            ApiVersion::V2.path("reference/currencies"),
+            "/v2/reference/currencies"
+        );
+    }
+
+    #[test]
+    fn order_book_from_depth() {
+        let json = r#"{"ch":"market.btcusdt.depth.step1","status":"ok","ts":1629789963000,
+            "tick":{"ts":1629789962800,"version":136998419000,
+            "bids":[[49010.0,0.5],[49000.0,1.2]],"asks":[[49020.0,0.3]]}}"#;
+
+        let depth: TickResult<OrderBook> = serde_json::from_str(json).unwrap();
+
+        assert!(depth
+            .check_channel(&format!(
+                "market.btcusdt.depth.{}",
+                DepthStep::Step1.as_str()
+            ))
+            .is_ok());
+        assert_eq!(depth.tick.version, 136998419000);
+        assert_eq!(depth.tick.bids[0], [49010.0, 0.5]);
+        assert_eq!(depth.tick.asks.len(), 1);
+        assert_eq!(DepthStep::ALL.len(), 6);
+    }
+
+    #[test]
+    fn order_book_mid_price_and_spread() {
+        let book = OrderBook {
+            ts: 1629789962800,
+            version: 1,
+            bids: vec![[99.9, 1.], [99.8, 2.]],
+            asks: vec![[100.1, 1.]],
+        };
+
+        assert!(is_equal(book.mid_price().unwrap(), 100.));
+        assert!(is_equal(book.spread_bps().unwrap(), 20.));
+
+        let one_sided = OrderBook {
+            asks: vec![],
+            ..book
+        };
+
+        assert_eq!(one_sided.mid_price(), None);
+        assert_eq!(one_sided.spread_bps(), None);
+    }
+
+    #[test]
+    fn order_book_estimate_fill() {
+        let book = OrderBook {
+            ts: 1629789962800,
+            version: 1,
+            bids: vec![[99.9, 1.], [99.8, 2.]],
+            asks: vec![[100.1, 1.], [100.3, 1.]],
+        };
+
+        let buy = book.estimate_fill(Side::Buy, 1.5);
+        assert!(is_equal(
+            buy.avg_price.unwrap(),
+            (100.1 + 0.5 * 100.3) / 1.5
+        ));
+        assert_eq!(buy.worst_price, Some(100.3));
+        assert!(is_equal(buy.filled, 1.5));
+        assert!(is_equal(buy.unfilled, 0.));
+        assert!(
+            buy.slippage_bps(Side::Buy, book.mid_price().unwrap())
+                .unwrap()
+                > 0.
+        );
+
+        let sell = book.estimate_fill(Side::Sell, 5.);
+        assert_eq!(sell.worst_price, Some(99.8));
+        assert!(is_equal(sell.filled, 3.));
+        assert!(is_equal(sell.unfilled, 2.));
+
+        let empty = OrderBook {
+            asks: vec![],
+            ..book
+        }
+        .estimate_fill(Side::Buy, 1.);
+        assert_eq!(empty.avg_price, None);
+        assert!(is_equal(empty.unfilled, 1.));
+    }
+
+    #[test]
+    fn system_status_incidents() {
+        let json = r#"{"page":{"id":"p1","name":"Huobi"},"status":{"indicator":"minor","description":"Minor Service Outage"},
+            "incidents":[
+                {"id":"i1","name":"Delayed deposits of ETH","status":"investigating","impact":"minor","created_at":"2021-08-24T07:06:03.123+08:00","resolved_at":null},
+                {"id":"i2","name":"WebSocket disconnects","status":"resolved","impact":"major","created_at":"2021-08-23T01:00:00Z","resolved_at":"2021-08-23T02:00:00Z"}],
+            "scheduled_maintenances":[
+                {"id":"m1","name":"System upgrade","status":"scheduled","impact":"maintenance","created_at":"2021-08-20T00:00:00Z","scheduled_for":"2021-08-30T00:00:00Z","scheduled_until":"2021-08-30T02:00:00Z"}]}"#;
+
+        let status: SystemStatus = serde_json::from_str(json).unwrap();
+
+        assert_eq!(status.status.indicator, Impact::Minor);
+        assert_eq!(status.incidents[0].created_at.timestamp(), 1629759963);
+        assert_eq!(status.active_incidents(Impact::Minor).len(), 1);
+        assert_eq!(status.active_incidents(Impact::Major).len(), 0);
+        assert!(status.scheduled_maintenances[0].scheduled_for.is_some());
+    }
+
+    #[test]
+    fn response_envelope_v1_and_v2() {
+        let v1: ResponseEnvelope<Vec<u64>> =
+            serde_json::from_str(r#"{"status":"ok","ts":1629788763000,"data":[1,2]}"#).unwrap();
+        assert_eq!(v1.ts, Some(1629788763000));
+        assert_eq!(v1.into_data().unwrap(), vec![1, 2]);
+
+        let v1_error: ResponseEnvelope<Vec<u64>> = serde_json::from_str(
+            r#"{"status":"error","err-code":"base-symbol-error","err-msg":"invalid symbol","data":null}"#,
+        )
+        .unwrap();
+        assert_eq!(v1_error.err_code.as_deref(), Some("base-symbol-error"));
+        assert!(v1_error.into_data().is_err());
+
+        let v2: ResponseEnvelope<Vec<u64>> =
+            serde_json::from_str(r#"{"code":200,"data":[3]}"#).unwrap();
+        assert_eq!(v2.into_data().unwrap(), vec![3]);
+
+        let v2_error: ResponseEnvelope<Vec<u64>> =
+            serde_json::from_str(r#"{"code":2002,"message":"invalid field value"}"#).unwrap();
+        assert_eq!(
+            v2_error.into_data().unwrap_err().to_string(),
+            HuobiError::ApiError("2002: invalid field value".into()).to_string()
+        );
+    }
+
+    #[test]
+    fn endpoint_path() {
+        assert_eq!(Endpoint::Accounts.as_str(), Some("/v1/account/accounts"));
+        assert_eq!(Endpoint::Accounts.to_string(), "/v1/account/accounts");
+        assert_eq!(
+            Endpoint::TransactFeeRate.to_string(),
+            ApiVersion::V2.path("/reference/transact-fee-rate")
+        );
+        assert_eq!(Endpoint::SubmitCancel(42).as_str(), None);
+        assert_eq!(
+            Endpoint::SubmitCancel(42).to_string(),
+            "/v1/order/orders/42/submitcancel"
+        );
+        assert_eq!(
+            Endpoint::AccountBalance("100009".to_string()).to_string(),
+            "/v1/account/accounts/100009/balance"
+        );
+    }
+
+    #[test]
+    fn fee_rate_to_vip_info() {
+        let data = r#"{"code":200,"data":[{"symbol":"btcusdt","actualMakerRate":"0.0018","makerFeeRate":"0.002","takerFeeRate":"0.002","actualTakerRate":"0.0019"}],"success":true}"#;
+
+        let fee_rates: FeeRateResult = serde_json::from_str(data).unwrap();
+        let vip_info = VipInfo::from(fee_rates.data[0].clone());
+
+        assert_eq!(vip_info.symbol, "btcusdt");
+        assert!(is_equal(vip_info.maker_bps, 18.));
+        assert!(is_equal(vip_info.taker_bps, 19.));
+        assert!(is_equal(vip_info.base_maker_bps, 20.));
+        assert!(is_equal(vip_info.base_taker_bps, 20.));
+    }
+
+    #[test]
+    fn number_from_string_or_number() {
+        let from_string: Asset =
+            serde_json::from_str(r#"{"currency":"usdt","type":"trade","balance":"91.85"}"#)
+                .unwrap();
+        let from_number: Asset =
+            serde_json::from_str(r#"{"currency":"usdt","type":"trade","balance":91.85}"#).unwrap();
+
+        assert_eq!(from_string.balance, Decimal::new(9185, 2));
+        assert_eq!(from_number.balance, Decimal::new(9185, 2));
+    }
+
+    #[test]
+    fn number_in_scientific_notation() {
+        // dust balances come as "1.0E-8"
+        for balance in &[r#""1.0E-8""#, r#""1e-8""#, "1.0E-8"] {
+            let asset: Asset = serde_json::from_str(&format!(
+                r#"{{"currency":"btc","type":"trade","balance":{}}}"#,
+                balance
+            ))
+            .unwrap();
+
+            assert_eq!(asset.balance, Decimal::new(1, 8));
+        }
+
+        let update: AccountUpdate = serde_json::from_str(
+            r#"{"currency":"btc","accountId":1,"balance":"2.5E-7","available":"2.5E-7"}"#,
+        )
+        .unwrap();
+
+        assert_eq!(update.available, Some(Decimal::new(25, 8)));
+    }
+
+    #[test]
+    fn book_levels_from_string_or_number() {
+        let book: OrderBook = serde_json::from_str(
+            r#"{"ts":1630000075000,"bids":[["47000.5","0.2"],[46999,1.5]],"asks":[[47001.0,"0.3"]]}"#,
+        )
+        .unwrap();
+
+        assert_eq!(book.bids, vec![[47000.5, 0.2], [46999., 1.5]]);
+        assert_eq!(book.asks, vec![[47001., 0.3]]);
+
+        let result: CancelAllAfterResult = serde_json::from_str(
+            r#"{"code":"200","data":{"currentTime":"1630000075000","triggerTime":0}}"#,
+        )
+        .unwrap();
+
+        assert_eq!(result.code, 200);
+        assert_eq!(result.data.unwrap().current_time, 1630000075000);
+    }
+
+    #[test]
+    fn number_from_invalid_string() {
+        let asset = serde_json::from_str::<Asset>(
+            r#"{"currency":"usdt","type":"trade","balance":"not a number"}"#,
+        );
+
+        assert!(asset.is_err());
+    }
+
+    const CURRENCY_REFERENCE: &str = r#"{"code":200,"data":[{"chains":[{"chain":"trc20usdt","displayName":"TRC20","baseChain":"TRX","baseChainProtocol":"TRC20","isDynamic":false,"numOfConfirmations":1,"numOfFastConfirmations":1,"depositStatus":"allowed","minDepositAmt":"1","withdrawStatus":"allowed","minWithdrawAmt":"1","withdrawPrecision":6,"maxWithdrawAmt":"280000.00000000","withdrawQuotaPerDay":"280000","withdrawQuotaPerYear":null,"withdrawQuotaTotal":null,"withdrawFeeType":"fixed","transactFeeWithdraw":"1.0","addrWithTag":false,"addrDepositTag":false},{"chain":"usdterc20","displayName":"ERC20","depositStatus":"allowed","minDepositAmt":"1","withdrawStatus":"prohibited","minWithdrawAmt":"10","withdrawPrecision":6,"maxWithdrawAmt":"280000.00000000","withdrawFeeType":"circulated","addrWithTag":false}],"currency":"usdt","instStatus":"normal"}]}"#;
+
+    #[test]
+    fn currency_chain_check_withdraw() {
+        let reference: CurrencyReferenceResult = serde_json::from_str(CURRENCY_REFERENCE).unwrap();
+        let chains = &reference.data[0].chains;
+
+        assert!(is_equal(chains[0].transact_fee_withdraw, 1.));
+        assert!(chains[0].check_withdraw(10.).is_ok());
+        assert!(chains[0].check_withdraw(0.5).is_err());
+        // withdraw is prohibited on the chain
+        assert!(chains[1].check_withdraw(100.).is_err());
+    }
+
+    fn btcusdt() -> Symbol {
+        serde_json::from_str(r#"{"base-currency":"btc","quote-currency":"usdt","price-precision":2,"amount-precision":6,"symbol-partition":"main","symbol":"btcusdt","state":"online","value-precision":8,"min-order-amt":0.0001,"max-order-amt":1000,"min-order-value":5,"leverage-ratio":5}"#).unwrap()
+    }
+
+    #[test]
+    fn account_and_symbol_states() {
+        let accounts: AccountsResult = serde_json::from_str(r#"{"status":"ok","data":[{"id":100009,"type":"spot","subtype":"","state":"working"},{"id":100010,"type":"margin","subtype":"btcusdt","state":"lock"},{"id":100011,"type":"otc","subtype":"","state":"frozen"}]}"#).unwrap();
+
+        assert_eq!(
+            accounts
+                .data
+                .iter()
+                .map(|account| account.state.clone())
+                .collect::<Vec<_>>(),
+            vec![
+                AccountState::Working,
+                AccountState::Lock,
+                AccountState::Unknown("frozen".into())
+            ]
+        );
+
+        let symbol = btcusdt();
+
+        assert_eq!(symbol.state, SymbolState::Online);
+        assert!(symbol.state.is_tradeable());
+        assert_eq!(
+            serde_json::from_str::<SymbolState>(r#""pre-online""#).unwrap(),
+            SymbolState::PreOnline
+        );
+        assert!(!SymbolState::Suspend.is_tradeable());
+    }
+
+    #[test]
+    fn unknown_values_kept_or_rejected() {
+        assert_eq!(
+            OrderState::from_wire("pending-trigger", false).unwrap(),
+            OrderState::Unknown("pending-trigger".into())
+        );
+        assert_eq!(
+            OrderState::Unknown("pending-trigger".into()).to_string(),
+            "pending-trigger"
+        );
+        assert!(OrderState::from_wire("pending-trigger", true).is_err());
+        assert_eq!(
+            OrderState::from_wire("partial-canceled", true).unwrap(),
+            OrderState::PartialCanceled
+        );
+
+        assert!(SymbolState::from_wire("delisted", true).is_err());
+        assert!(AccountState::from_wire("frozen", true).is_err());
+        assert!(TransactType::from_wire("staking-reward", true).is_err());
+        assert_eq!(
+            TransactType::from_wire("staking-reward", false).unwrap(),
+            TransactType::Unknown("staking-reward".into())
+        );
+
+        // The default is the resilient mode
+        assert!(!strict_enums());
+        assert_eq!(
+            serde_json::to_string(&AccountState::Unknown("frozen".into())).unwrap(),
+            r#""frozen""#
+        );
+    }
+
+    #[test]
+    fn symbol_max_buy_amount() {
+        let symbol = btcusdt();
+
+        assert!(is_equal(symbol.max_buy_amount(100., 45000.), 0.002222));
+        // 4.5 usdt is under the min order value
+        assert!(is_equal(symbol.max_buy_amount(4.5, 45000.), 0.));
+        assert!(is_equal(symbol.max_buy_amount(1.0e9, 1.), 1000.));
+    }
+
+    #[test]
+    fn symbol_max_sell_amount() {
+        let symbol = btcusdt();
+
+        assert!(is_equal(symbol.max_sell_amount(0.12345678), 0.123456));
+        assert!(is_equal(symbol.max_sell_amount(0.00005), 0.));
+    }
+
+    #[test]
+    fn order_endpoints() {
+        assert_eq!(OrderEndpoint::Place.path(), "/v1/order/orders/place");
+        assert_eq!(
+            OrderEndpoint::AutoPlace(MarginTradePurpose::AutoBorrow).path(),
+            "/v1/order/auto/place"
+        );
+
+        let result: AutoPlaceOrderResult = serde_json::from_str(
+            r#"{"success":true,"code":200,"data":{"order-id":1234567,"client-order-id":"m1"}}"#,
+        )
+        .unwrap();
+
+        assert_eq!(result.data.order_id, 1234567);
+    }
+
+    #[test]
+    fn order_id_of_every_place_answer() {
+        for data in [
+            r#"{"status":"ok","data":"356501383558845"}"#,
+            r#"{"status":"ok","data":356501383558845}"#,
+            r#"{"status":"ok","data":{"order-id":356501383558845}}"#,
+            r#"{"status":"ok","data":{"orderId":"356501383558845"}}"#,
+        ]
+        .iter()
+        {
+            let result: PlaceOrderResult = serde_json::from_str(data).unwrap();
+
+            assert_eq!(result.data, OrderId(356501383558845));
+        }
+
+        assert!(serde_json::from_str::<PlaceOrderResult>(r#"{"data":"abc"}"#).is_err());
+        assert!(serde_json::from_str::<PlaceOrderResult>(r#"{"data":{"id":1}}"#).is_err());
+        assert_eq!(serde_json::to_string(&OrderId(7)).unwrap(), "7");
+    }
+
+    #[test]
+    fn batch_orders_partial_success() {
+        let data = r#"{"status":"ok","data":[{"order-id":61713400772,"client-order-id":"c1"},{"client-order-id":"c2","err-code":"order-value-min-error","err-msg":"Order total cannot be lower than: 5"}]}"#;
+
+        let result: BatchOrdersResult = serde_json::from_str(data).unwrap();
+
+        assert_eq!(result.data[0].order_id, Some(OrderId(61713400772)));
+        assert_eq!(result.data[1].order_id, None);
+        assert_eq!(
+            result.data[1].err_code.as_deref(),
+            Some("order-value-min-error")
+        );
+    }
+
+    #[test]
+    fn match_results() {
+        let data = r#"{"status":"ok","data":[{"symbol":"btcusdt","fee-currency":"btc","source":"spot-api","price":"45000.10","created-at":1630000000000,"role":"taker","order-id":345487249132375,"match-id":5014,"trade-id":1085,"filled-amount":"0.001","filled-fees":"0.000002","filled-points":"0.0","fee-deduct-currency":"","fee-deduct-state":"done","id":5015,"type":"buy-market"}]}"#;
+
+        let match_results: MatchResultsResult = serde_json::from_str(data).unwrap();
+        let fill = &match_results.data[0];
+
+        assert_eq!(fill.id, 5015);
+        assert_eq!(fill.order_id, 345487249132375);
+        assert_eq!(fill.price, Decimal::new(450001, 1));
+        assert_eq!(fill.filled_fees, Decimal::new(2, 6));
+    }
+
+    #[test]
+    fn margin_loan_info() {
+        let data = r#"{"status":"ok","data":[{"symbol":"btcusdt","currencies":[{"currency":"btc","interest-rate":"0.00098","min-loan-amt":"0.020000000000000000","max-loan-amt":"550.000000000000000000","loanable-amt":"0.045696000000000000","actual-rate":"0.00098"},{"currency":"usdt","interest-rate":"0.00098","min-loan-amt":"100.000000000000000000","max-loan-amt":"4000000.000000000000000000","loanable-amt":"400.000000000000000000","actual-rate":"0.00098"}]}]}"#;
+
+        let loan_info: MarginLoanInfoResult = serde_json::from_str(data).unwrap();
+        let loan_info: Vec<LoanInfo> = loan_info
+            .data
+            .into_iter()
+            .flat_map(SymbolLoanInfo::into_loan_info)
+            .collect();
+
+        assert_eq!(loan_info.len(), 2);
+        assert_eq!(loan_info[1].symbol, "btcusdt");
+        assert_eq!(loan_info[1].currency, "usdt");
+        assert!(is_equal(loan_info[1].loanable_amt, 400.));
+        assert!(is_equal(loan_info[0].interest_rate, 0.00098));
+    }
+
+    #[test]
+    fn cross_margin_balance() {
+        let data = r#"{"status":"ok","data":{"id":18264,"type":"cross-margin","state":"working","risk-rate":"1000","acct-balance-sum":"12312.123123","debt-balance-sum":"1231.2123123","list":[{"currency":"btc","type":"trade","balance":"1313.1313"},{"currency":"btc","type":"loan","balance":"-1231.2123123"}]}}"#;
+
+        let balance: CrossMarginBalanceResult = serde_json::from_str(data).unwrap();
+
+        assert_eq!(balance.data.account_type, "cross-margin");
+        assert!(is_equal(balance.data.risk_rate, 1000.));
+        assert_eq!(balance.data.list[1].balance, Decimal::new(-12312123123, 7));
+    }
+
+    const MERGED_TICKER: &str = r#"{"ch":"market.btcusdt.detail.merged","status":"ok","ts":1629788763750,"tick":{"id":272156789143,"version":272156789143,"open":50080.0,"close":49820.92,"low":48767.0,"high":50500.0,"amount":12055.365781937457,"vol":5.985618685709001E8,"count":420573,"bid":[49819.48,2.58112],"ask":[49819.49,0.002411]}}"#;
+
+    #[test]
+    fn tick_channel_match() {
+        let ticker: TickResult<MergedTicker> = serde_json::from_str(MERGED_TICKER).unwrap();
+
+        assert!(ticker.check_channel("market.btcusdt.detail.merged").is_ok());
+        assert!(is_equal(ticker.tick.close, 49820.92));
+    }
+
+    #[test]
+    fn tick_channel_mismatch() {
+        let ticker: TickResult<MergedTicker> = serde_json::from_str(MERGED_TICKER).unwrap();
+
+        match ticker.check_channel("market.ethusdt.detail.merged") {
+            Err(HuobiError::ChannelMismatch { expected, received }) => {
+                assert_eq!(expected, "market.ethusdt.detail.merged");
+                assert_eq!(received, "market.btcusdt.detail.merged");
+            }
+            _ => panic!("channel mismatch is not detected"),
+        }
+    }
+
+    fn new_order(order_type: OrderType, amount: Amount) -> NewOrder {
+        NewOrder {
+            symbol: "btcusdt".to_string(),
+            order_type,
+            amount,
+            price: None,
+            client_order_id: None,
+            stp_mode: None,
+            source: None,
+            protection_price: None,
+            time_in_force: None,
+        }
+    }
+
+    #[test]
+    fn new_order_source() {
+        let mut order = new_order(OrderType::SellLimit, Amount::Base(0.5));
+        order.price = Some(40000.);
+        assert_eq!(order.to_body("100009").get("source").unwrap(), "api");
+
+        order.source = Some(OrderSource::C2cMarginApi);
+        assert!(order.validate().is_ok());
+        assert_eq!(
+            order.to_body("100009").get("source").unwrap(),
+            "c2c-margin-api"
+        );
+
+        let mut market = new_order(OrderType::SellMarket, Amount::Base(0.5));
+        market.source = Some(OrderSource::C2cMarginApi);
+        assert!(market.validate().is_err());
+        market.source = Some(OrderSource::SuperMarginApi);
+        assert!(market.validate().is_ok());
+    }
+
+    #[test]
+    fn new_order_body_with_stp_mode() {
+        let mut order = new_order(OrderType::BuyLimit, Amount::Base(0.5));
+        order.price = Some(40000.);
+        order.stp_mode = Some(StpMode::CancelTaker);
+
+        let body = order.to_body("100009");
+
+        assert_eq!(body.get("account-id").unwrap(), "100009");
+        assert_eq!(body.get("type").unwrap(), "buy-limit");
+        assert_eq!(body.get("amount").unwrap(), "0.5");
+        assert_eq!(body.get("price").unwrap(), "40000");
+        assert_eq!(body.get("self-match-prevent-new").unwrap(), "cancel_taker");
+    }
+
+    #[test]
+    fn new_order_body_without_stp_mode() {
+        let body = new_order(OrderType::SellMarket, Amount::Base(0.5)).to_body("100009");
+
+        assert!(!body.contains_key("self-match-prevent-new"));
+        assert!(!body.contains_key("price"));
+    }
+
+    #[test]
+    fn market_buy_requires_quote_amount() {
+        assert!(new_order(OrderType::BuyMarket, Amount::Quote(100.))
+            .validate()
+            .is_ok());
+        assert!(new_order(OrderType::BuyMarket, Amount::Base(0.01))
+            .validate()
+            .is_err());
+    }
+
+    #[test]
+    fn market_sell_requires_base_amount() {
+        assert!(new_order(OrderType::SellMarket, Amount::Base(0.01))
+            .validate()
+            .is_ok());
+        assert!(new_order(OrderType::SellMarket, Amount::Quote(100.))
+            .validate()
+            .is_err());
+    }
+
+    #[test]
+    fn needed_balance() {
+        let mut limit_buy = new_order(OrderType::BuyLimit, Amount::Base(0.5));
+        limit_buy.price = Some(40000.);
+
+        assert!(is_equal(limit_buy.needed_balance(), 20000.));
+        assert!(is_equal(
+            new_order(OrderType::BuyMarket, Amount::Quote(100.)).needed_balance(),
+            100.
+        ));
+        assert!(is_equal(
+            new_order(OrderType::SellLimit, Amount::Base(0.5)).needed_balance(),
+            0.5
+        ));
+    }
+
+    #[test]
+    fn limit_order_requires_base_amount() {
+        let mut limit_buy = new_order(OrderType::BuyLimit, Amount::Base(0.01));
+        limit_buy.price = Some(40000.);
+        assert!(limit_buy.validate().is_ok());
+
+        let mut limit_sell = new_order(OrderType::SellLimit, Amount::Quote(100.));
+        limit_sell.price = Some(40000.);
+        assert!(limit_sell.validate().is_err());
+    }
+
+    #[test]
+    fn protected_market_order_is_sent_as_ioc() {
+        let buy = NewOrder::builder("btcusdt", OrderType::BuyMarket, Amount::Base(0.5))
+            .max_slippage(40000., 0.01, &btcusdt())
+            .build()
+            .unwrap();
+
+        let body = buy.to_body("100009");
+        assert_eq!(body.get("type").unwrap(), "buy-ioc");
+        assert_eq!(body.get("price").unwrap(), "40400");
+        assert_eq!(body.get("amount").unwrap(), "0.5");
+        assert!(is_equal(buy.needed_balance(), 20200.));
+
+        let sell = NewOrder::builder("btcusdt", OrderType::SellMarket, Amount::Base(0.5))
+            .max_slippage(40000., 0.01, &btcusdt())
+            .build()
+            .unwrap();
+        assert_eq!(sell.wire_type(), "sell-ioc");
+        assert_eq!(sell.to_body("100009").get("price").unwrap(), "39600");
+
+        // 1.246845 and 1.222155 at 4 decimals, never further than 1% from 1.2345
+        let mut symbol = btcusdt();
+        symbol.price_precision = 4;
+        let buy = NewOrder::builder("btcusdt", OrderType::BuyMarket, Amount::Base(10.))
+            .max_slippage(1.2345, 0.01, &symbol)
+            .build()
+            .unwrap();
+        assert_eq!(buy.protection_price(), Some(1.2468));
+        let sell = NewOrder::builder("btcusdt", OrderType::SellMarket, Amount::Base(10.))
+            .max_slippage(1.2345, 0.01, &symbol)
+            .build()
+            .unwrap();
+        assert_eq!(sell.protection_price(), Some(1.2222));
+
+        match NewOrder::builder("btcusdt", OrderType::BuyMarket, Amount::Quote(100.))
+            .protection_price(40400.)
+            .build()
+        {
+            Err(OrderError::InvalidAmount(_)) => {}
+            other => panic!("protected buy sized in quote is built: {:?}", other),
+        }
+        match NewOrder::builder("btcusdt", OrderType::BuyLimit, Amount::Base(0.5))
+            .price(40000.)
+            .protection_price(40400.)
+            .build()
+        {
+            Err(OrderError::InvalidPrice(_)) => {}
+            other => panic!("protected limit order is built: {:?}", other),
+        }
+        assert!(
+            NewOrder::builder("btcusdt", OrderType::SellMarket, Amount::Base(0.5))
+                .max_slippage(40000., 1.5, &btcusdt())
+                .build()
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn time_in_force_in_the_order_type() {
+        let limit = |time_in_force: Option<TimeInForce>, order_type: OrderType| {
+            let mut order = new_order(order_type, Amount::Base(0.5));
+            order.price = Some(40000.);
+            order.time_in_force = time_in_force;
+            assert!(order.validate().is_ok());
+            order.wire_type()
+        };
+
+        assert_eq!(limit(None, OrderType::BuyLimit), "buy-limit");
+        assert_eq!(
+            limit(Some(TimeInForce::Gtc), OrderType::SellLimit),
+            "sell-limit"
+        );
+        assert_eq!(
+            limit(Some(TimeInForce::Ioc), OrderType::BuyLimit),
+            "buy-ioc"
+        );
+        assert_eq!(
+            limit(Some(TimeInForce::Fok), OrderType::SellLimit),
+            "sell-limit-fok"
+        );
+
+        let fok = NewOrder::builder("btcusdt", OrderType::BuyMarket, Amount::Base(0.5))
+            .protection_price(40400.)
+            .time_in_force(TimeInForce::Fok)
+            .build()
+            .unwrap();
+        assert_eq!(fok.to_body("100009").get("type").unwrap(), "buy-limit-fok");
+
+        let market = new_order(OrderType::SellMarket, Amount::Base(0.5));
+        assert_eq!(market.time_in_force(), None);
+        assert_eq!(market.wire_type(), "sell-market");
+
+        for time_in_force in TimeInForce::ALL.iter() {
+            match NewOrder::builder("btcusdt", OrderType::SellMarket, Amount::Base(0.5))
+                .time_in_force(*time_in_force)
+                .build()
+            {
+                Err(OrderError::InvalidTimeInForce(_)) => {}
+                other => panic!("market order with {} is built: {:?}", time_in_force, other),
+            }
+        }
+        assert!(
+            NewOrder::builder("btcusdt", OrderType::SellMarket, Amount::Base(0.5))
+                .protection_price(39600.)
+                .time_in_force(TimeInForce::Gtc)
+                .build()
+                .is_err()
+        );
+
+        assert_eq!(TimeInForce::of_order_type("buy-market"), None);
+        assert_eq!(
+            TimeInForce::of_order_type("sell-limit-fok"),
+            Some(TimeInForce::Fok)
+        );
+        assert_eq!(
+            TimeInForce::of_order_type("buy-ioc"),
+            Some(TimeInForce::Ioc)
+        );
+        assert_eq!(
+            TimeInForce::of_order_type("buy-limit-maker"),
+            Some(TimeInForce::Gtc)
+        );
+    }
+
+    #[test]
+    fn builder_rejects_invalid_orders() {
+        let order = NewOrder::builder("BTCUSDT", OrderType::BuyLimit, Amount::Base(0.5))
+            .price(40000.)
+            .client_order_id("c1")
+            .build()
+            .unwrap();
+        assert_eq!(order.symbol(), "btcusdt");
+        assert_eq!(order.client_order_id(), Some("c1"));
+
+        match NewOrder::builder("btcusdt", OrderType::SellLimit, Amount::Base(0.5)).build() {
+            Err(OrderError::InvalidPrice(_)) => {}
+            other => panic!("limit order without price is built: {:?}", other),
+        }
+        assert!(
+            NewOrder::builder("btcusdt", OrderType::BuyMarket, Amount::Quote(100.))
+                .price(40000.)
+                .build()
+                .is_err()
+        );
+        assert!(
+            NewOrder::builder("btcusdt", OrderType::BuyMarket, Amount::Base(0.5))
+                .build()
+                .is_err()
+        );
+        assert!(
+            NewOrder::builder("btcusdt", OrderType::SellMarket, Amount::Base(0.))
+                .build()
+                .is_err()
+        );
+        assert!(
+            NewOrder::builder("btcusdt", OrderType::SellLimit, Amount::Base(0.5))
+                .price(f64::NAN)
+                .build()
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn money_of_one_currency_only() {
+        let fills: MatchResultsResult = serde_json::from_str(r#"{"status":"ok","data":[
+            {"id":1,"order-id":11,"match-id":101,"trade-id":1001,"symbol":"btcusdt","type":"buy-limit","source":"spot-api","role":"maker","price":"40000","filled-amount":"0.5","filled-fees":"0.001","fee-currency":"btc","created-at":1630000000000},
+            {"id":2,"order-id":11,"match-id":102,"trade-id":1002,"symbol":"btcusdt","type":"buy-limit","source":"spot-api","role":"taker","price":"40000","filled-amount":"0.25","filled-fees":"0.012","fee-currency":"ht","created-at":1630000060000}
+        ]}"#).unwrap();
+        let symbol = Symbol {
+            base: "btc".into(),
+            quote: "usdt".into(),
+            price_precision: 2,
+            amount_precision: 6,
+            partition: "main".into(),
+            symbol: "btcusdt".into(),
+            state: SymbolState::Online,
+            value_precision: 8,
+            min_amount: 0.0001,
+            max_amount: 1000.,
+            min_value: 5.,
+            max_leverage: 0.,
+        };
+
+        let filled = (fills.data[0].filled(&symbol) + fills.data[1].filled(&symbol)).unwrap();
+        assert_eq!(filled, Money::new(Decimal::new(75, 2), "BTC"));
+        assert_eq!(
+            fills.data[1].filled_value(&symbol),
+            Money::new(Decimal::from(10000), "usdt")
+        );
+
+        match fills.data[0].fee() + fills.data[1].fee() {
+            Err(HuobiError::CurrencyMismatch { expected, received }) => {
+                assert_eq!(expected, "btc");
+                assert_eq!(received, "ht");
+            }
+            other => panic!("fees in two currencies are added: {:?}", other),
+        }
+        assert_eq!(
+            (filled - fills.data[0].fee()).unwrap().to_string(),
+            "0.749 btc"
+        );
+    }
+
+    #[test]
+    fn money_of_orders_and_fills() {
+        let symbol = Symbol {
+            base: "btc".into(),
+            quote: "usdt".into(),
+            price_precision: 2,
+            amount_precision: 6,
+            partition: "main".into(),
+            symbol: "btcusdt".into(),
+            state: SymbolState::Online,
+            value_precision: 8,
+            min_amount: 0.0001,
+            max_amount: 1000.,
+            min_value: 5.,
+            max_leverage: 0.,
+        };
+
+        let open: OpenOrder = serde_json::from_str(r#"{"id":11,"client-order-id":"","symbol":"btcusdt","price":"45000","amount":"0.02","created-at":1630000000000,"type":"buy-limit","filled-amount":"0.015","filled-cash-amount":"675","filled-fees":"0.00003","source":"spot-api","state":"partial-filled"}"#).unwrap();
+        assert_eq!(open.filled(&symbol), Money::new(Decimal::new(15, 3), "btc"));
+        assert_eq!(
+            open.filled_value(&symbol),
+            Money::new(Decimal::from(675), "usdt")
+        );
+        assert_eq!(open.fee(&symbol), Money::new(Decimal::new(3, 5), "btc"));
+
+        let closed: TradeHistory = serde_json::from_str(r#"{"id":13,"symbol":"btcusdt","account-id":100009,"amount":"0.02","price":"45000","created-at":1630000000000,"type":"sell-limit","field-amount":"0.02","field-cash-amount":"900","field-fees":"1.8","finished-at":1630000300000,"source":"spot-api","state":"filled","canceled-at":0}"#).unwrap();
+        assert_eq!(
+            closed.filled(&symbol),
+            Money::new(Decimal::new(2, 2), "btc")
+        );
+        assert_eq!(
+            closed.filled_value(&symbol),
+            Money::new(Decimal::from(900), "usdt")
+        );
+        assert_eq!(closed.fee(&symbol), Money::new(Decimal::new(18, 1), "usdt"));
+
+        let creation: Creation = serde_json::from_str(r#"{"orderSize":"100","orderCreateTime":1583853365586,"accountId":992701,"orderPrice":"0","type":"buy-market","orderId":27163533,"clientOrderId":"","orderSource":"spot-api","orderStatus":"submitted","symbol":"btcusdt","eventType":"creation"}"#).unwrap();
+        assert_eq!(
+            creation.size(&symbol),
+            Money::new(Decimal::from(100), "usdt")
+        );
+
+        let trade: Trade = serde_json::from_str(r#"{"tradePrice":"15000.5","tradeVolume":"0.004","tradeId":301,"tradeTime":1583854188883,"aggressor":true,"remainAmt":"0.006","execAmt":"0.004","orderId":27163536,"type":"sell-limit","clientOrderId":"","orderSource":"spot-api","orderPrice":"15000","orderSize":"0.01","orderStatus":"partial-filled","symbol":"btcusdt","eventType":"trade"}"#).unwrap();
+        assert_eq!(trade.filled(&symbol), Money::new(Decimal::new(4, 3), "btc"));
+        assert_eq!(
+            trade.filled_value(&symbol),
+            Money::new(Decimal::new(60002, 3), "usdt")
+        );
+    }
+
+    #[test]
+    fn transfer_results() {
+        let done: AccountTransferResult = serde_json::from_str(
+            r#"{"success":true,"data":176104252,"code":200,"message":"Succeed"}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            done.into_transfer().unwrap(),
+            TransferResult {
+                transfer_id: 176104252,
+                success: true,
+            }
+        );
+
+        let pending: AccountTransferResult =
+            serde_json::from_str(r#"{"success":false,"data":176104253,"code":200}"#).unwrap();
+        assert!(!pending.into_transfer().unwrap().success);
+
+        let rejected: AccountTransferResult = serde_json::from_str(
+            r#"{"success":false,"data":null,"code":1303,"message":"The single transfer-out amount must be no less than 10USDT"}"#,
+        )
+        .unwrap();
+        assert!(rejected.into_transfer().is_err());
+    }
+}
@@ -0,0 +1,442 @@
+use super::{
+    account::Account,
+    models::{
+        APIResult, AccountHistoryQuery, Endpoint, LedgerEntry, MatchQuery, MatchResult,
+        MatchResultsResult, OpenOrder, OpenOrdersResult, OrdersQuery, TradeHistory,
+        TradeHistoryResult,
+    },
+};
+use futures_util::stream::{self, Stream};
+use serde_json;
+use std::collections::{BTreeMap, VecDeque};
+use std::future::Future;
+
+type FetchPage<'a, T, C> = Box<dyn FnMut(Option<&C>) -> APIResult<(Vec<T>, Option<C>)> + 'a>;
+
+// Lazy iterator over a paginated endpoint: the next page is fetched only once the items
+// of the current one are consumed, through the rate limiter of the Client if it has one.
+// fetch gets the cursor of the page to load (None for the first one) and returns the
+// items and the cursor of the next page. After an error the iterator ends
+pub struct Paginator<'a, T, C> {
+    fetch: FetchPage<'a, T, C>,
+    buffer: VecDeque<T>,
+    cursor: Option<C>,
+    started: bool,
+    done: bool,
+}
+
+impl<'a, T, C: PartialEq> Paginator<'a, T, C> {
+    pub fn new<F>(fetch: F) -> Self
+    where
+        F: FnMut(Option<&C>) -> APIResult<(Vec<T>, Option<C>)> + 'a,
+    {
+        Paginator {
+            fetch: Box::new(fetch),
+            buffer: VecDeque::new(),
+            cursor: None,
+            started: false,
+            done: false,
+        }
+    }
+}
+
+impl<'a, T, C: PartialEq> Iterator for Paginator<'a, T, C> {
+    type Item = APIResult<T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(item) = self.buffer.pop_front() {
+                return Some(Ok(item));
+            }
+
+            if self.done || (self.started && self.cursor.is_none()) {
+                self.done = true;
+                return None;
+            }
+
+            match (self.fetch)(self.cursor.as_ref()) {
+                Ok((items, next_cursor)) => {
+                    // An empty page that points to itself would loop forever
+                    if items.is_empty() && self.started && next_cursor == self.cursor {
+                        self.done = true;
+                    }
+
+                    self.started = true;
+                    self.cursor = next_cursor;
+                    self.buffer.extend(items);
+                }
+                Err(e) => {
+                    self.done = true;
+                    return Some(Err(e));
+                }
+            }
+        }
+    }
+}
+
+struct PageState<F, T, C> {
+    fetch: F,
+    buffer: VecDeque<T>,
+    cursor: Option<C>,
+    started: bool,
+    done: bool,
+}
+
+// Paginator for async fetches, as a futures Stream: the next page is awaited only once
+// the items of the current one are consumed. fetch gets the cursor of the page to load
+// and resolves to the items and the cursor of the next page. After an error it ends
+pub fn page_stream<T, C, F, Fut>(fetch: F) -> impl Stream<Item = APIResult<T>>
+where
+    C: Clone + PartialEq,
+    F: FnMut(Option<C>) -> Fut,
+    Fut: Future<Output = APIResult<(Vec<T>, Option<C>)>>,
+{
+    let state = PageState {
+        fetch,
+        buffer: VecDeque::new(),
+        cursor: None,
+        started: false,
+        done: false,
+    };
+
+    stream::unfold(state, |mut state| async move {
+        loop {
+            if let Some(item) = state.buffer.pop_front() {
+                return Some((Ok(item), state));
+            }
+
+            if state.done || (state.started && state.cursor.is_none()) {
+                return None;
+            }
+
+            match (state.fetch)(state.cursor.clone()).await {
+                Ok((items, next_cursor)) => {
+                    // An empty page that points to itself would loop forever
+                    if items.is_empty() && state.started && next_cursor == state.cursor {
+                        state.done = true;
+                    }
+
+                    state.started = true;
+                    state.cursor = next_cursor;
+                    state.buffer.extend(items);
+                }
+                Err(e) => {
+                    state.done = true;
+                    return Some((Err(e), state));
+                }
+            }
+        }
+    })
+}
+
+impl Account {
+    pub fn iter_account_history(
+        &self,
+        query: AccountHistoryQuery,
+    ) -> Paginator<'_, LedgerEntry, u64> {
+        Paginator::new(move |from_id: Option<&u64>| {
+            let page_query = AccountHistoryQuery {
+                from_id: from_id.copied().or(query.from_id),
+                ..query.clone()
+            };
+
+            let history = self.get_account_history(&page_query)?;
+
+            Ok((history.data, history.next_id))
+        })
+    }
+
+    // Without symbol in the query every online symbol is paged through in turn.
+    // A start..end range longer than 48 hours is paged through window by window
+    pub fn iter_matchresults(&self, query: MatchQuery) -> Paginator<'_, MatchResult, MatchCursor> {
+        let size = query.size.unwrap_or(500);
+        let windows = match_windows(query.start_time, query.end_time);
+        let mut symbols: Option<Vec<String>> = None;
+
+        Paginator::new(move |cursor: Option<&MatchCursor>| {
+            if symbols.is_none() {
+                symbols = Some(match &query.symbol {
+                    Some(symbol) => vec![symbol.to_lowercase()],
+                    None => self
+                        .client
+                        .get_symbols()?
+                        .into_iter()
+                        .filter(|symbol_info| symbol_info.state.is_tradeable())
+                        .map(|symbol_info| symbol_info.symbol)
+                        .collect(),
+                });
+            }
+            let symbols = symbols.as_ref().unwrap();
+
+            let (index, window, from) = match cursor {
+                Some(cursor) => (cursor.symbol_index, cursor.window_index, cursor.from),
+                None => (0, 0, None),
+            };
+            let (start_time, end_time) = windows[window];
+
+            let symbol = match symbols.get(index) {
+                Some(symbol) => symbol,
+                None => return Ok((vec![], None)),
+            };
+
+            let mut params: BTreeMap<String, String> = BTreeMap::new();
+
+            params.insert("symbol".into(), symbol.clone());
+            params.insert("size".into(), size.to_string());
+
+            if let Some(types) = &query.types {
+                params.insert("types".into(), types.clone());
+            }
+            if let Some(start_time) = start_time {
+                params.insert("start-time".into(), start_time.to_string());
+            }
+            if let Some(end_time) = end_time {
+                params.insert("end-time".into(), end_time.to_string());
+            }
+            if let Some(from) = from {
+                params.insert("from".into(), from.to_string());
+                params.insert("direct".into(), "next".into());
+            }
+
+            let data = self.client.get_signed(Endpoint::MatchResults, params)?;
+
+            let page: MatchResultsResult = serde_json::from_str(data.as_str())?;
+            let is_full = page.data.len() >= size as usize;
+            let last_id = page.data.last().map(|fill| fill.id);
+
+            // The fill at from may come again as the first one of the next page
+            let fills: Vec<MatchResult> = page
+                .data
+                .into_iter()
+                .filter(|fill| Some(fill.id) != from)
+                .collect();
+
+            let next_cursor = if is_full && last_id.is_some() && last_id != from {
+                Some(MatchCursor {
+                    symbol_index: index,
+                    window_index: window,
+                    from: last_id,
+                })
+            } else if window + 1 < windows.len() {
+                Some(MatchCursor {
+                    symbol_index: index,
+                    window_index: window + 1,
+                    from: None,
+                })
+            } else if index + 1 < symbols.len() {
+                Some(MatchCursor {
+                    symbol_index: index + 1,
+                    window_index: 0,
+                    from: None,
+                })
+            } else {
+                None
+            };
+
+            Ok((fills, next_cursor))
+        })
+    }
+
+    // Past orders of /v1/order/orders, the newest first
+    pub fn iter_orders(&self, query: OrdersQuery) -> Paginator<'_, TradeHistory, u64> {
+        let size = query.size.unwrap_or(100);
+
+        Paginator::new(move |from: Option<&u64>| {
+            let mut params: BTreeMap<String, String> = BTreeMap::new();
+
+            params.insert("symbol".into(), query.symbol.to_lowercase());
+            params.insert("states".into(), query.states.clone());
+            params.insert("size".into(), size.to_string());
+
+            if let Some(start_time) = query.start_time {
+                params.insert("start-time".into(), start_time.to_string());
+            }
+            if let Some(end_time) = query.end_time {
+                params.insert("end-time".into(), end_time.to_string());
+            }
+            if let Some(from) = from {
+                params.insert("from".into(), from.to_string());
+                params.insert("direct".into(), "next".into());
+            }
+
+            let data = self.client.get_signed(Endpoint::Orders, params)?;
+
+            let page: TradeHistoryResult = serde_json::from_str(data.as_str())?;
+            let is_full = page.data.len() >= size as usize;
+            let last_id = page.data.last().map(|order| order.id);
+
+            let orders: Vec<TradeHistory> = page
+                .data
+                .into_iter()
+                .filter(|order| Some(&order.id) != from)
+                .collect();
+
+            let next_cursor = if is_full && last_id.as_ref() != from {
+                last_id
+            } else {
+                None
+            };
+
+            Ok((orders, next_cursor))
+        })
+    }
+
+    // Every open order of the account, of one symbol only when it is given.
+    // Pages of 500, the most Huobi returns
+    pub fn iter_open_orders<'a>(
+        &'a self,
+        symbol: Option<&'a str>,
+    ) -> Paginator<'a, OpenOrder, u64> {
+        let size = 500;
+
+        Paginator::new(move |from: Option<&u64>| {
+            let mut params: BTreeMap<String, String> = BTreeMap::new();
+
+            params.insert("account-id".into(), self.account_id()?);
+            params.insert("size".into(), size.to_string());
+
+            if let Some(symbol) = symbol {
+                params.insert("symbol".into(), symbol.to_lowercase());
+            }
+            if let Some(from) = from {
+                params.insert("from".into(), from.to_string());
+                params.insert("direct".into(), "next".into());
+            }
+
+            let data = self.client.get_signed(Endpoint::OpenOrders, params)?;
+
+            let page: OpenOrdersResult = serde_json::from_str(data.as_str())?;
+            let is_full = page.data.len() >= size;
+            let last_id = page.data.last().map(|order| order.id);
+
+            let orders: Vec<OpenOrder> = page
+                .data
+                .into_iter()
+                .filter(|order| Some(&order.id) != from)
+                .collect();
+
+            let next_cursor = if is_full && last_id.as_ref() != from {
+                last_id
+            } else {
+                None
+            };
+
+            Ok((orders, next_cursor))
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MatchCursor {
+    symbol_index: usize,
+    window_index: usize,
+    from: Option<u64>,
+}
+
+// Longest start..end range of one match results request
+const MATCH_WINDOW_MS: u64 = 48 * 60 * 60 * 1000;
+
+// Consecutive start..end windows (ms, both included) of at most 48 hours covering the
+// range. Without both ends Huobi applies its own range, the query is sent as it is
+fn match_windows(
+    start_time: Option<u64>,
+    end_time: Option<u64>,
+) -> Vec<(Option<u64>, Option<u64>)> {
+    let (start, end) = match (start_time, end_time) {
+        (Some(start), Some(end)) if end > start => (start, end),
+        _ => return vec![(start_time, end_time)],
+    };
+
+    let mut windows = vec![];
+    let mut window_start = start;
+
+    while window_start <= end {
+        let window_end = end.min(window_start + MATCH_WINDOW_MS - 1);
+
+        windows.push((Some(window_start), Some(window_end)));
+        window_start = window_end + 1;
+    }
+
+    windows
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use std::cell::Cell;
+
+    #[test]
+    fn pages_are_fetched_on_demand() {
+        let fetched = Cell::new(0);
+
+        let mut pages = Paginator::new(|cursor: Option<&u32>| {
+            fetched.set(fetched.get() + 1);
+
+            match cursor {
+                None => Ok((vec![1, 2], Some(1))),
+                Some(1) => Ok((vec![3], Some(2))),
+                _ => Ok((vec![4], None)),
+            }
+        });
+
+        assert_eq!(pages.next().unwrap().unwrap(), 1);
+        assert_eq!(fetched.get(), 1);
+        assert_eq!(pages.next().unwrap().unwrap(), 2);
+        assert_eq!(fetched.get(), 1);
+
+        let rest: Vec<u32> = pages.map(|item| item.unwrap()).collect();
+
+        assert_eq!(rest, vec![3, 4]);
+        assert_eq!(fetched.get(), 3);
+    }
+
+    #[test]
+    fn iterator_ends_after_error() {
+        let pages: Paginator<u32, u32> = Paginator::new(|cursor: Option<&u32>| match cursor {
+            None => Ok((vec![1], Some(1))),
+            _ => Err("network is down".into()),
+        });
+
+        let items: Vec<APIResult<u32>> = pages.collect();
+
+        assert_eq!(items.len(), 2);
+        assert!(items[1].is_err());
+    }
+
+    #[test]
+    fn match_range_split_in_48_hour_windows() {
+        let day = 24 * 60 * 60 * 1000;
+        let windows = match_windows(Some(1000), Some(1000 + 5 * day));
+
+        assert_eq!(windows.len(), 3);
+        assert_eq!(windows[0], (Some(1000), Some(1000 + 2 * day - 1)));
+        assert_eq!(windows[1].0, Some(1000 + 2 * day));
+        assert_eq!(windows[2], (Some(1000 + 4 * day), Some(1000 + 5 * day)));
+
+        assert_eq!(
+            match_windows(Some(1000), Some(2000)),
+            vec![(Some(1000), Some(2000))]
+        );
+        assert_eq!(match_windows(None, Some(2000)), vec![(None, Some(2000))]);
+    }
+
+    #[test]
+    fn stream_pages_until_the_last_one() {
+        use futures_util::StreamExt;
+
+        let pages = page_stream(|cursor: Option<u32>| async move {
+            match cursor {
+                None => Ok((vec![1, 2], Some(1))),
+                Some(1) => Ok((vec![3], Some(2))),
+                Some(2) => Ok((vec![4], None)),
+                _ => Err("read past the last page".into()),
+            }
+        });
+
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let items: Vec<u32> = runtime.block_on(pages.map(|item| item.unwrap()).collect());
+
+        assert_eq!(items, vec![1, 2, 3, 4]);
+    }
+}
@@ -0,0 +1,78 @@
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+// Sliding window limiter: at most max_requests are let through in any window
+pub struct RateLimiter {
+    max_requests: usize,
+    window: Duration,
+    requests: Mutex<VecDeque<Instant>>,
+}
+
+impl RateLimiter {
+    pub fn new(max_requests: usize, window: Duration) -> Self {
+        RateLimiter {
+            max_requests: max_requests.max(1),
+            window,
+            requests: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    // Blocks until a request can be sent
+    pub fn acquire(&self) {
+        loop {
+            let wait = {
+                let mut requests = self.requests.lock().unwrap();
+                let now = Instant::now();
+
+                while let Some(sent_at) = requests.front() {
+                    if now.duration_since(*sent_at) >= self.window {
+                        requests.pop_front();
+                    } else {
+                        break;
+                    }
+                }
+
+                if requests.len() < self.max_requests {
+                    requests.push_back(now);
+                    return;
+                }
+
+                self.window - now.duration_since(requests[0])
+            };
+
+            thread::sleep(wait);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn acquire_within_limit() {
+        let rate_limiter = RateLimiter::new(3, Duration::from_secs(10));
+        let started_at = Instant::now();
+
+        for _ in 0..3 {
+            rate_limiter.acquire();
+        }
+
+        assert!(started_at.elapsed() < Duration::from_secs(1));
+    }
+
+    #[test]
+    fn acquire_over_limit_waits_for_window() {
+        let rate_limiter = RateLimiter::new(2, Duration::from_millis(100));
+        let started_at = Instant::now();
+
+        for _ in 0..3 {
+            rate_limiter.acquire();
+        }
+
+        assert!(started_at.elapsed() >= Duration::from_millis(100));
+    }
+}
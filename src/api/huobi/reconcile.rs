@@ -0,0 +1,288 @@
+// Catching up after a disconnect: the orders the caller believes open are compared to
+// the open orders of the exchange, and the ones that are not open anymore are looked up
+// to tell the filled from the canceled
+use super::{
+    account::{Account, ORDER_LOOKUP_CONCURRENCY},
+    error::OrderError,
+    models::{APIResult, MatchQuery, MatchResult, OpenOrder, OrderId, OrderState, TradeHistory},
+};
+use rust_decimal::Decimal;
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+// An order the caller believes open, with what it knows of its fills
+#[derive(Debug, Clone, PartialEq)]
+pub struct LocalOrder {
+    pub order_id: OrderId,
+    pub symbol: String,
+    pub filled_amount: Decimal,
+}
+
+#[derive(Debug, Clone)]
+pub struct OrderChange {
+    pub order_id: OrderId,
+    pub symbol: String,
+    pub state: OrderState,
+    // On the exchange
+    pub filled_amount: Decimal,
+    // Filled since the local view, what the caller missed
+    pub missed_amount: Decimal,
+    // Fills of the order Huobi still lists, match results go back 48 hours
+    pub fills: Vec<MatchResult>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ReconcileReport {
+    // Still open, nothing filled since the local view
+    pub unchanged: Vec<OrderId>,
+    // Still open, with fills the caller missed
+    pub partially_filled: Vec<OrderChange>,
+    // Filled, canceled or partial-canceled meanwhile
+    pub closed: Vec<OrderChange>,
+    // Neither open nor known to Huobi, which drops canceled orders without fill
+    // after a few hours
+    pub vanished: Vec<OrderId>,
+    // Open on the exchange but not in the local view, e.g. placed by another process
+    // or whose placement answer was lost
+    pub untracked: Vec<OpenOrder>,
+}
+
+impl Account {
+    // The open orders are read first, so an order closed while the report is built
+    // shows up as open with the fills known at that time
+    pub fn reconcile(&self, local: &[LocalOrder]) -> APIResult<ReconcileReport> {
+        let open = self
+            .iter_open_orders(None)
+            .collect::<APIResult<Vec<OpenOrder>>>()?;
+
+        let open_ids: HashSet<u64> = open.iter().map(|order| order.id).collect();
+        let gone: Vec<OrderId> = unique_orders(local)
+            .iter()
+            .map(|order| order.order_id)
+            .filter(|id| !open_ids.contains(&id.get()))
+            .collect();
+
+        let lookups = self
+            .client
+            .batch_get(&gone, ORDER_LOOKUP_CONCURRENCY, |_, &id| {
+                match self.get_order(id) {
+                    Ok(order) => Ok(Some(order)),
+                    Err(e) => match e.downcast_ref::<OrderError>() {
+                        Some(OrderError::NotFound(_)) => Ok(None),
+                        _ => Err(e),
+                    },
+                }
+            });
+
+        let mut closed = HashMap::new();
+        for lookup in lookups {
+            if let Some(order) = lookup? {
+                closed.insert(OrderId(order.id), order);
+            }
+        }
+
+        let symbols: BTreeSet<String> = local
+            .iter()
+            .map(|order| order.symbol.to_lowercase())
+            .collect();
+
+        let mut fills = Vec::new();
+        for symbol in symbols {
+            fills.extend(self.get_all_matchresults(MatchQuery {
+                symbol: Some(symbol),
+                ..MatchQuery::default()
+            })?);
+        }
+
+        Ok(diff_orders(local, open, closed, fills))
+    }
+}
+
+// Each order once, at its first appearance
+fn unique_orders(local: &[LocalOrder]) -> Vec<&LocalOrder> {
+    let mut seen = HashSet::new();
+
+    local
+        .iter()
+        .filter(|order| seen.insert(order.order_id))
+        .collect()
+}
+
+// closed holds the lookup of every local order missing from open, an order
+// Huobi does not know anymore is absent from it
+fn diff_orders(
+    local: &[LocalOrder],
+    open: Vec<OpenOrder>,
+    mut closed: HashMap<OrderId, TradeHistory>,
+    fills: Vec<MatchResult>,
+) -> ReconcileReport {
+    let local_orders = unique_orders(local);
+    let local_ids: HashSet<u64> = local_orders
+        .iter()
+        .map(|order| order.order_id.get())
+        .collect();
+
+    let mut fills_by_order: HashMap<u64, Vec<MatchResult>> = HashMap::new();
+    for fill in fills {
+        if local_ids.contains(&fill.order_id) {
+            fills_by_order.entry(fill.order_id).or_default().push(fill);
+        }
+    }
+    for order_fills in fills_by_order.values_mut() {
+        order_fills.sort_by_key(|fill| (fill.created_at, fill.id));
+    }
+
+    let (tracked, untracked): (Vec<OpenOrder>, Vec<OpenOrder>) = open
+        .into_iter()
+        .partition(|order| local_ids.contains(&order.id));
+    let mut open_by_id: HashMap<u64, OpenOrder> =
+        tracked.into_iter().map(|order| (order.id, order)).collect();
+
+    let mut report = ReconcileReport {
+        untracked,
+        ..ReconcileReport::default()
+    };
+
+    for order in local_orders {
+        let id = order.order_id;
+
+        let (state, filled_amount, is_open) = if let Some(open) = open_by_id.remove(&id.get()) {
+            (open.state, open.filled_amount, true)
+        } else if let Some(history) = closed.remove(&id) {
+            (history.state, history.field_amount, false)
+        } else {
+            report.vanished.push(id);
+            continue;
+        };
+
+        let missed_amount = filled_amount - order.filled_amount;
+        if is_open && missed_amount <= Decimal::ZERO {
+            report.unchanged.push(id);
+            continue;
+        }
+
+        let change = OrderChange {
+            order_id: id,
+            symbol: order.symbol.to_lowercase(),
+            state,
+            filled_amount,
+            missed_amount: missed_amount.max(Decimal::ZERO),
+            fills: fills_by_order.remove(&id.get()).unwrap_or_default(),
+        };
+
+        if is_open {
+            report.partially_filled.push(change);
+        } else {
+            report.closed.push(change);
+        }
+    }
+
+    report
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::super::models::{OpenOrdersResult, OrderDetailResult};
+    use super::*;
+
+    fn local(id: u64, filled_amount: &str) -> LocalOrder {
+        LocalOrder {
+            order_id: OrderId(id),
+            symbol: "BTCUSDT".into(),
+            filled_amount: filled_amount.parse().unwrap(),
+        }
+    }
+
+    fn closed_order(id: u64, state: &str, field_amount: &str) -> (OrderId, TradeHistory) {
+        let order: OrderDetailResult = serde_json::from_str(&format!(
+            r#"{{"status":"ok","data":{{"id":{},"symbol":"btcusdt","account-id":100009,"amount":"0.02","price":"45000","created-at":1630000000000,"type":"buy-limit","field-amount":"{}","field-cash-amount":"0","field-fees":"0","finished-at":1630000300000,"source":"spot-api","state":"{}","canceled-at":0}}}}"#,
+            id, field_amount, state
+        ))
+        .unwrap();
+
+        (OrderId(id), order.data)
+    }
+
+    fn fill(id: u64, order_id: u64, filled_amount: f64, created_at: u64) -> MatchResult {
+        serde_json::from_value(serde_json::json!({
+            "id": id,
+            "order-id": order_id,
+            "match-id": id,
+            "trade-id": id,
+            "symbol": "btcusdt",
+            "type": "buy-limit",
+            "source": "spot-api",
+            "role": "maker",
+            "price": "45000",
+            "filled-amount": filled_amount.to_string(),
+            "filled-fees": "0",
+            "fee-currency": "btc",
+            "created-at": created_at,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn orders_diffed_against_the_exchange() {
+        let open: OpenOrdersResult = serde_json::from_str(r#"{"status":"ok","data":[
+            {"id":11,"client-order-id":"","symbol":"btcusdt","price":"45000","amount":"0.02","created-at":1630000000000,"type":"buy-limit","filled-amount":"0","filled-cash-amount":"0","filled-fees":"0","source":"spot-api","state":"submitted"},
+            {"id":12,"client-order-id":"","symbol":"btcusdt","price":"45000","amount":"0.02","created-at":1630000000000,"type":"buy-limit","filled-amount":"0.015","filled-cash-amount":"675","filled-fees":"0","source":"spot-api","state":"partial-filled"},
+            {"id":99,"client-order-id":"","symbol":"ethusdt","price":"3000","amount":"0.1","created-at":1630000000000,"type":"sell-limit","filled-amount":"0","filled-cash-amount":"0","filled-fees":"0","source":"spot-api","state":"submitted"}
+        ]}"#).unwrap();
+
+        let closed: HashMap<OrderId, TradeHistory> = vec![
+            closed_order(13, "filled", "0.02"),
+            closed_order(14, "partial-canceled", "0.005"),
+        ]
+        .into_iter()
+        .collect();
+
+        let fills = vec![
+            fill(3, 12, 0.005, 1630000200000),
+            fill(1, 13, 0.02, 1630000100000),
+            fill(2, 12, 0.01, 1630000100000),
+            fill(4, 77, 1., 1630000100000),
+        ];
+
+        let local = [
+            local(11, "0"),
+            local(12, "0.01"),
+            local(13, "0"),
+            local(14, "0"),
+            local(15, "0"),
+            local(11, "0"),
+        ];
+
+        let report = diff_orders(&local, open.data, closed, fills);
+
+        assert_eq!(report.unchanged, vec![OrderId(11)]);
+
+        assert_eq!(report.partially_filled.len(), 1);
+        let partial = &report.partially_filled[0];
+        assert_eq!(partial.order_id, OrderId(12));
+        assert_eq!(partial.state, OrderState::PartialFilled);
+        assert_eq!(partial.missed_amount, Decimal::new(5, 3));
+        let fill_ids: Vec<u64> = partial.fills.iter().map(|fill| fill.id).collect();
+        assert_eq!(fill_ids, vec![2, 3]);
+
+        let closed: Vec<(OrderId, OrderState)> = report
+            .closed
+            .iter()
+            .map(|change| (change.order_id, change.state.clone()))
+            .collect();
+        assert_eq!(
+            closed,
+            vec![
+                (OrderId(13), OrderState::Filled),
+                (OrderId(14), OrderState::PartialCanceled)
+            ]
+        );
+        assert_eq!(report.closed[1].missed_amount, Decimal::new(5, 3));
+        assert!(report.closed[1].fills.is_empty());
+
+        assert_eq!(report.vanished, vec![OrderId(15)]);
+
+        assert_eq!(report.untracked.len(), 1);
+        assert_eq!(report.untracked[0].id, 99);
+    }
+}
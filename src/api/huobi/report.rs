@@ -0,0 +1,413 @@
+use super::{
+    account::Account,
+    models::{APIResult, MatchQuery, MatchResult, Symbol},
+};
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::Decimal;
+use std::collections::{BTreeMap, HashMap};
+
+// Realized result of trading one symbol over a time window, valued in the quote currency.
+// Sells are matched against the average cost of the buys before them,
+// bought amounts that are still held are left open and do not count
+#[derive(Debug, Clone, PartialEq)]
+pub struct PnlEntry {
+    pub symbol: String,
+    pub quote: String,
+    // Before fees
+    pub realized: Decimal,
+    // Bought in the window and not sold yet, at average_cost
+    pub open_amount: Decimal,
+    pub average_cost: Decimal,
+    // Sold without buys before it in the window, its cost is unknown
+    pub unmatched_sold: Decimal,
+    // Paid per fee currency (base, quote, HT or point), positive
+    pub fees: BTreeMap<String, Decimal>,
+}
+
+impl PnlEntry {
+    fn new(symbol: &Symbol) -> Self {
+        PnlEntry {
+            symbol: symbol.symbol.clone(),
+            quote: symbol.quote.clone(),
+            realized: Decimal::ZERO,
+            open_amount: Decimal::ZERO,
+            average_cost: Decimal::ZERO,
+            unmatched_sold: Decimal::ZERO,
+            fees: BTreeMap::new(),
+        }
+    }
+
+    fn add_fill(&mut self, fill: &MatchResult) {
+        let amount = fill.filled_amount;
+
+        if fill.order_type.starts_with("buy") {
+            let cost = self.open_amount * self.average_cost + amount * fill.price;
+            self.open_amount += amount;
+            if self.open_amount > Decimal::ZERO {
+                self.average_cost = cost / self.open_amount;
+            }
+        } else {
+            let matched = amount.min(self.open_amount);
+            self.realized += matched * (fill.price - self.average_cost);
+            self.open_amount -= matched;
+            self.unmatched_sold += amount - matched;
+            if self.open_amount <= Decimal::ZERO {
+                self.open_amount = Decimal::ZERO;
+                self.average_cost = Decimal::ZERO;
+            }
+        }
+
+        if !fill.filled_fees.is_zero() {
+            *self
+                .fees
+                .entry(fill.fee_currency.clone())
+                .or_insert(Decimal::ZERO) += fill.filled_fees;
+        }
+    }
+}
+
+// Fills of symbols that are not in symbols are left out
+pub fn aggregate_pnl(fills: &[MatchResult], symbols: &[Symbol]) -> Vec<PnlEntry> {
+    let mut fills: Vec<&MatchResult> = fills.iter().collect();
+    fills.sort_by_key(|fill| (fill.created_at, fill.id));
+
+    let mut pnl: BTreeMap<String, PnlEntry> = BTreeMap::new();
+
+    for fill in fills {
+        let symbol = match symbols.iter().find(|symbol| symbol.symbol == fill.symbol) {
+            Some(symbol) => symbol,
+            None => continue,
+        };
+
+        pnl.entry(fill.symbol.clone())
+            .or_insert_with(|| PnlEntry::new(symbol))
+            .add_fill(fill);
+    }
+
+    pnl.into_values().collect()
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AssetValuation {
+    pub currency: String,
+    // Available and frozen
+    pub balance: f64,
+    // Last price in the quote currency of the snapshot
+    pub price: f64,
+    pub value: f64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PortfolioSnapshot {
+    pub quote: String,
+    pub total_value: f64,
+    // The largest value first
+    pub assets: Vec<AssetValuation>,
+    // No pair to price them, not even through a bridge currency
+    pub unpriced: Vec<String>,
+}
+
+// Currencies without a pair against the quote are priced through these
+const BRIDGE_CURRENCIES: [&str; 2] = ["btc", "usdt"];
+
+// Pair to convert from one currency to the other, inverse if it is quoted in `from`
+fn find_leg<'a>(from: &str, to: &str, symbols: &'a [Symbol]) -> Option<(&'a Symbol, bool)> {
+    symbols.iter().find_map(|symbol| {
+        if symbol.base == from && symbol.quote == to {
+            Some((symbol, false))
+        } else if symbol.base == to && symbol.quote == from {
+            Some((symbol, true))
+        } else {
+            None
+        }
+    })
+}
+
+fn leg_price<F>(leg: (&Symbol, bool), last_price: &mut F) -> APIResult<f64>
+where
+    F: FnMut(&str) -> APIResult<f64>,
+{
+    let (symbol, inverse) = leg;
+    let price = last_price(&symbol.symbol)?;
+
+    if inverse {
+        Ok(if price > 0. { 1. / price } else { 0. })
+    } else {
+        Ok(price)
+    }
+}
+
+// A balance that can't be sold on its first leg, under the min amount or value
+fn is_dust(balance: f64, leg: (&Symbol, bool)) -> bool {
+    let (symbol, inverse) = leg;
+
+    if inverse {
+        balance < symbol.min_value
+    } else {
+        balance < symbol.min_amount
+    }
+}
+
+// Values balances in quote with last_price(symbol), directly or through a bridge currency.
+// Dust is left out of the snapshot
+pub fn value_portfolio<F>(
+    quote: &str,
+    balances: &BTreeMap<String, f64>,
+    symbols: &[Symbol],
+    mut last_price: F,
+) -> APIResult<PortfolioSnapshot>
+where
+    F: FnMut(&str) -> APIResult<f64>,
+{
+    let quote = quote.to_lowercase();
+    let mut assets = vec![];
+    let mut unpriced = vec![];
+
+    for (currency, &balance) in balances {
+        if balance <= 0. {
+            continue;
+        }
+
+        let price = if *currency == quote {
+            Some(1.)
+        } else if let Some(leg) = find_leg(currency, &quote, symbols) {
+            if is_dust(balance, leg) {
+                continue;
+            }
+            Some(leg_price(leg, &mut last_price)?)
+        } else {
+            let route = BRIDGE_CURRENCIES
+                .iter()
+                .filter(|bridge| **bridge != currency.as_str() && **bridge != quote)
+                .find_map(|bridge| {
+                    match (
+                        find_leg(currency, bridge, symbols),
+                        find_leg(bridge, &quote, symbols),
+                    ) {
+                        (Some(first), Some(second)) => Some((first, second)),
+                        _ => None,
+                    }
+                });
+
+            match route {
+                Some((first, second)) => {
+                    if is_dust(balance, first) {
+                        continue;
+                    }
+                    Some(leg_price(first, &mut last_price)? * leg_price(second, &mut last_price)?)
+                }
+                None => None,
+            }
+        };
+
+        match price {
+            Some(price) => assets.push(AssetValuation {
+                currency: currency.clone(),
+                balance,
+                price,
+                value: balance * price,
+            }),
+            None => unpriced.push(currency.clone()),
+        }
+    }
+
+    assets.sort_by(|a, b| {
+        b.value
+            .partial_cmp(&a.value)
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+
+    Ok(PortfolioSnapshot {
+        total_value: assets.iter().map(|asset| asset.value).sum(),
+        quote,
+        assets,
+        unpriced,
+    })
+}
+
+impl Account {
+    // Spot balances valued in quote at the last prices, one ticker request per pair used
+    pub fn portfolio_value(&self, quote: &str) -> APIResult<PortfolioSnapshot> {
+        let mut balances: BTreeMap<String, f64> = BTreeMap::new();
+
+        for asset in self.get_all_balances()?.data.list {
+            // Valued with f64 last prices, the precision of the balance does not matter here
+            *balances.entry(asset.currency).or_insert(0.) += asset.balance.to_f64().unwrap_or(0.);
+        }
+
+        let symbols: Vec<Symbol> = self
+            .client
+            .get_symbols()?
+            .into_iter()
+            .filter(|symbol| symbol.state.is_tradeable())
+            .collect();
+
+        let mut prices: HashMap<String, f64> = HashMap::new();
+
+        value_portfolio(quote, &balances, &symbols, |symbol| {
+            if let Some(price) = prices.get(symbol) {
+                return Ok(*price);
+            }
+
+            let price = self.client.get_ticker(symbol)?.close;
+            prices.insert(symbol.to_string(), price);
+
+            Ok(price)
+        })
+    }
+
+    // Realized PnL of these symbols between start_time and end_time (ms), from their fills.
+    // Built from match results and not from the account ledger: ledger entries are per
+    // currency, without pair nor price, so the buys and sells of a pair can't be matched.
+    // Match results are queried per symbol and 48 hours window, the symbols passed here
+    // bound the number of requests
+    pub fn realized_pnl(
+        &self,
+        symbols: &[&str],
+        start_time: u64,
+        end_time: u64,
+    ) -> APIResult<Vec<PnlEntry>> {
+        let mut fills = vec![];
+
+        for symbol in symbols {
+            fills.extend(self.get_all_matchresults(MatchQuery {
+                symbol: Some(symbol.to_string()),
+                start_time: Some(start_time),
+                end_time: Some(end_time),
+                ..Default::default()
+            })?);
+        }
+
+        Ok(aggregate_pnl(&fills, &self.client.get_symbols()?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use super::super::models::SymbolState;
+    use std::str::FromStr;
+
+    fn fill(
+        id: u64,
+        order_type: &str,
+        price: &str,
+        amount: &str,
+        fee: &str,
+        fee_currency: &str,
+    ) -> MatchResult {
+        MatchResult {
+            id,
+            order_id: id,
+            match_id: id,
+            trade_id: id,
+            symbol: "btcusdt".to_string(),
+            order_type: order_type.to_string(),
+            source: "spot-api".to_string(),
+            role: "taker".to_string(),
+            price: Decimal::from_str(price).unwrap(),
+            filled_amount: Decimal::from_str(amount).unwrap(),
+            filled_fees: Decimal::from_str(fee).unwrap(),
+            fee_currency: fee_currency.to_string(),
+            created_at: id,
+        }
+    }
+
+    fn symbol(base: &str, quote: &str, min_amount: f64) -> Symbol {
+        Symbol {
+            base: base.to_string(),
+            quote: quote.to_string(),
+            price_precision: 2,
+            amount_precision: 4,
+            partition: "main".to_string(),
+            symbol: format!("{}{}", base, quote),
+            state: SymbolState::Online,
+            value_precision: 8,
+            min_amount,
+            max_amount: 1.0e6,
+            min_value: 5.,
+            max_leverage: 0.,
+        }
+    }
+
+    #[test]
+    fn portfolio_valued_directly_and_through_bridge() {
+        let symbols = vec![
+            symbol("btc", "usdt", 0.0001),
+            symbol("eth", "usdt", 0.001),
+            // no xyzusdt pair
+            symbol("xyz", "btc", 1.),
+        ];
+        let prices: HashMap<&str, f64> =
+            vec![("btcusdt", 40000.), ("ethusdt", 3000.), ("xyzbtc", 0.0001)]
+                .into_iter()
+                .collect();
+
+        let mut balances: BTreeMap<String, f64> = BTreeMap::new();
+        balances.insert("usdt".into(), 100.);
+        balances.insert("btc".into(), 0.01);
+        balances.insert("xyz".into(), 50.);
+        // dust, under the min order amount
+        balances.insert("eth".into(), 0.0001);
+        balances.insert("abc".into(), 10.);
+
+        let snapshot =
+            value_portfolio("USDT", &balances, &symbols, |symbol| Ok(prices[symbol])).unwrap();
+
+        assert_eq!(snapshot.quote, "usdt");
+        assert_eq!(
+            snapshot
+                .assets
+                .iter()
+                .map(|asset| asset.currency.as_str())
+                .collect::<Vec<_>>(),
+            vec!["btc", "xyz", "usdt"]
+        );
+        assert!((snapshot.assets[1].value - 200.).abs() < 1.0e-8);
+        assert!((snapshot.total_value - 700.).abs() < 1.0e-8);
+        assert_eq!(snapshot.unpriced, vec!["abc"]);
+    }
+
+    #[test]
+    fn aggregate_matches_sells_against_average_cost() {
+        let symbols = vec![symbol("btc", "usdt", 0.0001)];
+        let fills = vec![
+            // sold before the window bought it, the cost is unknown
+            fill(1, "sell-limit", "39000", "0.5", "39", "usdt"),
+            fill(2, "buy-limit", "40000", "1", "0.002", "btc"),
+            fill(3, "buy-market", "42000", "1", "0.002", "btc"),
+            // average cost 41000
+            fill(4, "sell-limit", "43000", "1.5", "129", "usdt"),
+            fill(5, "buy-ioc", "100", "1", "0", "btc"),
+        ];
+
+        let pnl = aggregate_pnl(&fills, &symbols);
+
+        assert_eq!(pnl.len(), 1);
+        assert_eq!(pnl[0].symbol, "btcusdt");
+        assert_eq!(pnl[0].quote, "usdt");
+        assert_eq!(pnl[0].realized, Decimal::from(3000));
+        assert_eq!(pnl[0].unmatched_sold, Decimal::new(5, 1));
+        assert_eq!(pnl[0].open_amount, Decimal::new(15, 1));
+        assert_eq!(
+            pnl[0].average_cost.round_dp(4),
+            Decimal::from_str("13733.3333").unwrap()
+        );
+        assert_eq!(pnl[0].fees["btc"], Decimal::new(4, 3));
+        assert_eq!(pnl[0].fees["usdt"], Decimal::from(168));
+    }
+
+    #[test]
+    fn aggregate_buy_without_sell_realizes_nothing() {
+        let symbols = vec![symbol("btc", "usdt", 0.0001)];
+        let fills = vec![fill(1, "buy-limit", "40000", "1", "0.002", "btc")];
+
+        let pnl = aggregate_pnl(&fills, &symbols);
+
+        assert_eq!(pnl.len(), 1);
+        assert_eq!(pnl[0].realized, Decimal::ZERO);
+        assert_eq!(pnl[0].open_amount, Decimal::from(1));
+        assert_eq!(pnl[0].fees["btc"], Decimal::new(2, 3));
+    }
+}
@@ -0,0 +1,173 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+// Opt-in cache of raw bodies for reference data that rarely changes (symbols, currencies,
+// fee rates), keyed by endpoint and params. On disk it survives process restarts
+pub struct ResponseCache {
+    ttl: Duration,
+    dir: Option<PathBuf>,
+    entries: Mutex<HashMap<String, (SystemTime, String)>>,
+}
+
+impl ResponseCache {
+    pub fn in_memory(ttl: Duration) -> Self {
+        ResponseCache {
+            ttl,
+            dir: None,
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn on_disk(dir: PathBuf, ttl: Duration) -> io::Result<Self> {
+        fs::create_dir_all(&dir)?;
+
+        Ok(ResponseCache {
+            ttl,
+            dir: Some(dir),
+            entries: Mutex::new(HashMap::new()),
+        })
+    }
+
+    pub fn key(endpoint: &str, params: &BTreeMap<String, String>) -> String {
+        let params = params
+            .iter()
+            .map(|(key, value)| format!("{}={}", key, value))
+            .collect::<Vec<_>>()
+            .join("&");
+
+        format!("{}?{}", endpoint, params)
+    }
+
+    pub fn get(&self, key: &str) -> Option<String> {
+        self.lookup(key).map(|(body, _)| body)
+    }
+
+    // The body and whether it was read from the disk, for the Client to log
+    pub(crate) fn lookup(&self, key: &str) -> Option<(String, bool)> {
+        let now = SystemTime::now();
+
+        if let Some((stored_at, body)) = self.entries.lock().unwrap().get(key) {
+            if self.is_fresh(*stored_at, now) {
+                return Some((body.clone(), false));
+            }
+        }
+
+        let (stored_at, body) = self.read_file(key)?;
+
+        if !self.is_fresh(stored_at, now) {
+            return None;
+        }
+
+        self.entries
+            .lock()
+            .unwrap()
+            .insert(key.to_string(), (stored_at, body.clone()));
+
+        Some((body, true))
+    }
+
+    pub fn put(&self, key: &str, body: &str) {
+        // The cache is an optimization, a failed write only costs a request later
+        let _ = self.store(key, body);
+    }
+
+    // Fails when the file can't be written, the body is kept in memory anyway
+    pub(crate) fn store(&self, key: &str, body: &str) -> io::Result<()> {
+        let now = SystemTime::now();
+
+        self.entries
+            .lock()
+            .unwrap()
+            .insert(key.to_string(), (now, body.to_string()));
+
+        if let Some(path) = self.file_path(key) {
+            let stored_at = now
+                .duration_since(UNIX_EPOCH)
+                .map(|duration| duration.as_secs())
+                .unwrap_or(0);
+
+            fs::write(&path, format!("{}\n{}", stored_at, body))?;
+        }
+
+        Ok(())
+    }
+
+    fn is_fresh(&self, stored_at: SystemTime, now: SystemTime) -> bool {
+        match now.duration_since(stored_at) {
+            Ok(age) => age < self.ttl,
+            // Stored in the future, the clock went back
+            Err(_) => false,
+        }
+    }
+
+    // First line is the store time in unix seconds, the body follows
+    fn read_file(&self, key: &str) -> Option<(SystemTime, String)> {
+        let content = fs::read_to_string(self.file_path(key)?).ok()?;
+        let (stored_at, body) = content.split_once('\n')?;
+        let stored_at = UNIX_EPOCH + Duration::from_secs(stored_at.parse().ok()?);
+
+        Some((stored_at, body.to_string()))
+    }
+
+    fn file_path(&self, key: &str) -> Option<PathBuf> {
+        let file_name: String = key
+            .chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() || c == '-' || c == '.' {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .collect();
+
+        self.dir
+            .as_ref()
+            .map(|dir| dir.join(format!("{}.json", file_name)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn key() -> String {
+        let mut params: BTreeMap<String, String> = BTreeMap::new();
+        params.insert("currency".into(), "usdt".into());
+
+        ResponseCache::key("/v2/reference/currencies", &params)
+    }
+
+    #[test]
+    fn in_memory_cache_expires() {
+        let cache = ResponseCache::in_memory(Duration::from_millis(50));
+
+        cache.put(&key(), r#"{"code":200,"data":[]}"#);
+
+        assert_eq!(cache.get(&key()).unwrap(), r#"{"code":200,"data":[]}"#);
+
+        std::thread::sleep(Duration::from_millis(60));
+
+        assert!(cache.get(&key()).is_none());
+    }
+
+    #[test]
+    fn disk_cache_survives_restart() {
+        let dir = std::env::temp_dir().join(format!("huobi-cache-test-{}", std::process::id()));
+
+        ResponseCache::on_disk(dir.clone(), Duration::from_secs(60))
+            .unwrap()
+            .put(&key(), r#"{"code":200,"data":[]}"#);
+
+        let restarted = ResponseCache::on_disk(dir.clone(), Duration::from_secs(60)).unwrap();
+
+        assert_eq!(restarted.get(&key()).unwrap(), r#"{"code":200,"data":[]}"#);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
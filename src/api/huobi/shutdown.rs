@@ -0,0 +1,146 @@
+use super::{
+    account::Account, models::APIResult, websocket_account::WebSockets,
+    websocket_data::MarketStream,
+};
+use tracing::error;
+
+// A stream that graceful_shutdown can close
+pub trait Disconnect {
+    fn disconnect(&mut self) -> APIResult<()>;
+}
+
+impl<'a> Disconnect for WebSockets<'a> {
+    fn disconnect(&mut self) -> APIResult<()> {
+        WebSockets::disconnect(self)
+    }
+}
+
+impl Disconnect for MarketStream {
+    fn disconnect(&mut self) -> APIResult<()> {
+        MarketStream::disconnect(self)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ShutdownOptions {
+    // Cancel the open orders, set to false to leave them resting
+    pub cancel_orders: bool,
+    // Cancel the open orders of this symbol only
+    pub symbol: Option<String>,
+}
+
+impl Default for ShutdownOptions {
+    fn default() -> Self {
+        ShutdownOptions {
+            cancel_orders: true,
+            symbol: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ShutdownSummary {
+    pub canceled_orders: u64,
+    pub failed_cancels: u64,
+    pub closed_streams: usize,
+    // A failed step does not stop the next ones, its error is kept here
+    pub errors: Vec<String>,
+}
+
+impl ShutdownSummary {
+    pub fn is_clean(&self) -> bool {
+        self.failed_cancels == 0 && self.errors.is_empty()
+    }
+}
+
+impl Account {
+    // Clean stop of a bot: cancels the open orders first, so nothing is left resting
+    // while the streams that would report their fills are closed, then closes the streams
+    pub fn graceful_shutdown(
+        &self,
+        options: &ShutdownOptions,
+        streams: &mut [&mut dyn Disconnect],
+    ) -> ShutdownSummary {
+        let mut summary = ShutdownSummary::default();
+
+        if options.cancel_orders {
+            match self.cancel_all_open_orders(options.symbol.as_deref()) {
+                Ok(canceled) => {
+                    summary.canceled_orders = canceled.success_count;
+                    summary.failed_cancels = canceled.failed_count;
+                }
+                Err(e) => summary.errors.push(format!("cancel open orders: {}", e)),
+            }
+        }
+
+        for stream in streams.iter_mut() {
+            match stream.disconnect() {
+                Ok(()) => summary.closed_streams += 1,
+                Err(e) => summary.errors.push(format!("close stream: {}", e)),
+            }
+        }
+
+        // A failed step is an error whatever the LogPolicy, orders may be left resting
+        if summary.is_clean() {
+            self.client
+                .log_policy()
+                .event(format_args!("[Huobi] Shutdown: {:?}", summary));
+        } else {
+            error!("[Huobi] Shutdown with errors: {:?}", summary);
+        }
+
+        summary
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::super::error::HuobiError;
+    use super::*;
+
+    struct FakeStream {
+        fails: bool,
+        closed: bool,
+    }
+
+    impl Disconnect for FakeStream {
+        fn disconnect(&mut self) -> APIResult<()> {
+            if self.fails {
+                return Err(Box::new(HuobiError::ApiError("already closed".into())));
+            }
+
+            self.closed = true;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn shutdown_leaving_orders_closes_every_stream() {
+        let account = Account::new(
+            "100009",
+            Some("test-access-key".into()),
+            Some("test-secret-key".into()),
+        );
+        let options = ShutdownOptions {
+            cancel_orders: false,
+            symbol: None,
+        };
+        let mut open = FakeStream {
+            fails: false,
+            closed: false,
+        };
+        let mut broken = FakeStream {
+            fails: true,
+            closed: false,
+        };
+
+        let summary = account.graceful_shutdown(&options, &mut [&mut broken, &mut open]);
+
+        assert!(open.closed);
+        assert_eq!(summary.closed_streams, 1);
+        assert_eq!(summary.canceled_orders, 0);
+        assert_eq!(summary.errors.len(), 1);
+        assert!(!summary.is_clean());
+    }
+}
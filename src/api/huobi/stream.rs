@@ -0,0 +1,204 @@
+use super::error::HuobiError;
+use super::models::{APIResult, StreamedList};
+use serde::de::{
+    self, DeserializeOwned, DeserializeSeed, Deserializer, IgnoredAny, MapAccess, SeqAccess,
+    Visitor,
+};
+use std::fmt;
+use std::io::Read;
+use std::marker::PhantomData;
+
+// Parses a list response while it is read: every item of "data" is handed to on_item
+// as soon as it is parsed, so memory stays bounded by one item whatever the body size.
+// Invalid UTF-8 in the body is an error as with the buffered responses
+pub fn for_each_data<R, T, F>(reader: R, on_item: F) -> APIResult<StreamedList>
+where
+    R: Read,
+    T: DeserializeOwned,
+    F: FnMut(T),
+{
+    let mut deserializer = serde_json::Deserializer::from_reader(reader);
+
+    let response = ResponseSeed {
+        on_item,
+        marker: PhantomData,
+    }
+    .deserialize(&mut deserializer)?;
+
+    deserializer.end()?;
+
+    if response.status.as_deref() == Some("error") {
+        return Err(Box::new(HuobiError::ApiError(format!(
+            "{}: {}",
+            response.err_code.unwrap_or_default(),
+            response.err_msg.unwrap_or_default()
+        ))));
+    }
+
+    Ok(StreamedList {
+        count: response.count,
+        next_id: response.next_id,
+    })
+}
+
+#[derive(Default)]
+struct StreamedResponse {
+    status: Option<String>,
+    err_code: Option<String>,
+    err_msg: Option<String>,
+    count: usize,
+    next_id: Option<u64>,
+}
+
+struct ResponseSeed<T, F> {
+    on_item: F,
+    marker: PhantomData<T>,
+}
+
+impl<'de, T, F> DeserializeSeed<'de> for ResponseSeed<T, F>
+where
+    T: DeserializeOwned,
+    F: FnMut(T),
+{
+    type Value = StreamedResponse;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_map(self)
+    }
+}
+
+impl<'de, T, F> Visitor<'de> for ResponseSeed<T, F>
+where
+    T: DeserializeOwned,
+    F: FnMut(T),
+{
+    type Value = StreamedResponse;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a Huobi response object")
+    }
+
+    fn visit_map<A>(mut self, mut map: A) -> Result<Self::Value, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        let mut response = StreamedResponse::default();
+
+        while let Some(key) = map.next_key::<String>()? {
+            match key.as_str() {
+                "status" => response.status = map.next_value()?,
+                "err-code" => response.err_code = map.next_value()?,
+                "err-msg" => response.err_msg = map.next_value()?,
+                "next-id" => response.next_id = map.next_value()?,
+                "data" => {
+                    response.count = map.next_value_seed(DataSeed {
+                        on_item: &mut self.on_item,
+                        marker: PhantomData,
+                    })?
+                }
+                _ => {
+                    map.next_value::<IgnoredAny>()?;
+                }
+            }
+        }
+
+        Ok(response)
+    }
+}
+
+struct DataSeed<'a, T, F> {
+    on_item: &'a mut F,
+    marker: PhantomData<T>,
+}
+
+impl<'de, 'a, T, F> DeserializeSeed<'de> for DataSeed<'a, T, F>
+where
+    T: DeserializeOwned,
+    F: FnMut(T),
+{
+    type Value = usize;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        // data is null in error responses
+        deserializer.deserialize_any(self)
+    }
+}
+
+impl<'de, 'a, T, F> Visitor<'de> for DataSeed<'a, T, F>
+where
+    T: DeserializeOwned,
+    F: FnMut(T),
+{
+    type Value = usize;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a list of items")
+    }
+
+    fn visit_unit<E>(self) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        Ok(0)
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        let mut count = 0;
+
+        while let Some(item) = seq.next_element::<T>()? {
+            (self.on_item)(item);
+            count += 1;
+        }
+
+        Ok(count)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use super::super::models::LedgerEntry;
+
+    #[test]
+    fn stream_items_of_data() {
+        let body = r#"{
+            "status": "ok",
+            "data": [
+                {"account-id": 1, "currency": "usdt", "transact-amt": "-1.5", "transact-type": "trade",
+                 "record-id": 10, "avail-balance": "98.5", "acct-balance": "98.5", "transact-time": 1},
+                {"account-id": 1, "currency": "btc", "transact-amt": "0.01", "transact-type": "trade",
+                 "record-id": 11, "avail-balance": "0.01", "acct-balance": "0.01", "transact-time": 2}
+            ],
+            "next-id": 12
+        }"#;
+
+        let mut currencies = vec![];
+        let streamed = for_each_data(body.as_bytes(), |entry: LedgerEntry| {
+            currencies.push(entry.currency)
+        })
+        .unwrap();
+
+        assert_eq!(currencies, vec!["usdt", "btc"]);
+        assert_eq!(streamed.count, 2);
+        assert_eq!(streamed.next_id, Some(12));
+    }
+
+    #[test]
+    fn stream_error_response() {
+        let body = r#"{"status":"error","err-code":"invalid-parameter","err-msg":"bad start-time","data":null}"#;
+
+        let result = for_each_data(body.as_bytes(), |_entry: LedgerEntry| {});
+
+        assert!(result.is_err());
+    }
+}
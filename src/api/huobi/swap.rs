@@ -0,0 +1,442 @@
+// USDT-margined perpetual swaps, e.g. BTC-USDT. They are served by their own host
+// with v1 style envelopes using "err_code" and "err_msg"
+use super::{
+    client::Client,
+    error::HuobiError,
+    models::{
+        deserialize_number_from_string_or_number, deserialize_option_number_from_string_or_number,
+        APIResult, KlinePeriod, ResponseEnvelope,
+    },
+};
+use serde::{Deserialize, Serialize};
+use serde_json;
+use std::collections::BTreeMap;
+
+const HUOBI_SWAP_URL: &str = "https://api.hbdm.com";
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FundingRate {
+    pub symbol: String,
+    pub contract_code: String,
+    pub fee_asset: String,
+    // Rate of the current period, paid at funding_time
+    #[serde(deserialize_with = "deserialize_number_from_string_or_number")]
+    pub funding_rate: f64,
+    // Not published by Huobi anymore for some contracts
+    #[serde(default)]
+    #[serde(deserialize_with = "deserialize_option_number_from_string_or_number")]
+    pub estimated_rate: Option<f64>,
+    // ms
+    #[serde(deserialize_with = "deserialize_number_from_string_or_number")]
+    pub funding_time: u64,
+    #[serde(default)]
+    #[serde(deserialize_with = "deserialize_option_number_from_string_or_number")]
+    pub next_funding_time: Option<u64>,
+}
+
+// Funding of a settled period
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FundingSettlement {
+    pub symbol: String,
+    pub contract_code: String,
+    pub fee_asset: String,
+    #[serde(deserialize_with = "deserialize_number_from_string_or_number")]
+    pub funding_rate: f64,
+    // Rate actually paid, funding_rate may be capped
+    #[serde(deserialize_with = "deserialize_number_from_string_or_number")]
+    pub realized_rate: f64,
+    #[serde(deserialize_with = "deserialize_number_from_string_or_number")]
+    pub avg_premium_index: f64,
+    // ms
+    #[serde(deserialize_with = "deserialize_number_from_string_or_number")]
+    pub funding_time: u64,
+}
+
+// Open positions of a contract, in contracts (volume), in the base currency (amount)
+// and in USDT (value)
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct OpenInterest {
+    pub symbol: String,
+    pub contract_code: String,
+    #[serde(deserialize_with = "deserialize_number_from_string_or_number")]
+    pub volume: f64,
+    #[serde(deserialize_with = "deserialize_number_from_string_or_number")]
+    pub amount: f64,
+    #[serde(deserialize_with = "deserialize_number_from_string_or_number")]
+    pub value: f64,
+}
+
+// Price of the index taken for the basis of each period
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BasisPriceType {
+    Open,
+    Close,
+    High,
+    Low,
+    Average,
+}
+
+impl BasisPriceType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BasisPriceType::Open => "open",
+            BasisPriceType::Close => "close",
+            BasisPriceType::High => "high",
+            BasisPriceType::Low => "low",
+            BasisPriceType::Average => "average",
+        }
+    }
+}
+
+// Basis of a period: contract price minus index price, basis_rate is basis / index price
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Basis {
+    // Start of the period, s
+    #[serde(deserialize_with = "deserialize_number_from_string_or_number")]
+    pub id: u64,
+    #[serde(deserialize_with = "deserialize_number_from_string_or_number")]
+    pub contract_price: f64,
+    #[serde(deserialize_with = "deserialize_number_from_string_or_number")]
+    pub index_price: f64,
+    #[serde(deserialize_with = "deserialize_number_from_string_or_number")]
+    pub basis: f64,
+    #[serde(deserialize_with = "deserialize_number_from_string_or_number")]
+    pub basis_rate: f64,
+}
+
+// Price liquidations are computed at, which is not the last trade price
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct MarkPrice {
+    pub contract_code: String,
+    #[serde(deserialize_with = "deserialize_number_from_string_or_number")]
+    pub mark_price: f64,
+    // ms, start of the period for the history
+    #[serde(deserialize_with = "deserialize_number_from_string_or_number")]
+    pub ts: u64,
+}
+
+// Huobi only publishes the mark price as klines, the close is the mark price
+#[derive(Debug, Deserialize, Clone)]
+struct MarkPriceKline {
+    // Start of the period, s
+    #[serde(deserialize_with = "deserialize_number_from_string_or_number")]
+    id: u64,
+    #[serde(deserialize_with = "deserialize_number_from_string_or_number")]
+    close: f64,
+}
+
+// Settlement price of the current period, the estimate until the period is settled
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct EstimatedSettlement {
+    pub contract_code: String,
+    #[serde(default)]
+    #[serde(deserialize_with = "deserialize_option_number_from_string_or_number")]
+    pub estimated_settlement_price: Option<f64>,
+    #[serde(default)]
+    #[serde(deserialize_with = "deserialize_option_number_from_string_or_number")]
+    pub settlement_price: Option<f64>,
+    // "settlement" or "delivery"
+    pub settlement_type: String,
+}
+
+// Pages are numbered from 1, newest settlements first
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FundingSettlementPage {
+    #[serde(deserialize_with = "deserialize_number_from_string_or_number")]
+    pub total_page: u32,
+    #[serde(deserialize_with = "deserialize_number_from_string_or_number")]
+    pub current_page: u32,
+    #[serde(deserialize_with = "deserialize_number_from_string_or_number")]
+    pub total_size: u32,
+    pub data: Vec<FundingSettlement>,
+}
+
+impl Client {
+    pub fn get_swap_funding_rate(&self, contract_code: &str) -> APIResult<FundingRate> {
+        let mut params: BTreeMap<String, String> = BTreeMap::new();
+
+        params.insert("contract_code".into(), contract_code.to_uppercase());
+
+        let data = self.get_swap("/linear-swap-api/v1/swap_funding_rate", &params)?;
+
+        let funding_rate: ResponseEnvelope<FundingRate> = serde_json::from_str(data.as_str())?;
+
+        Ok(funding_rate.into_data()?)
+    }
+
+    // page_size is 50 at most
+    pub fn get_swap_funding_history(
+        &self,
+        contract_code: &str,
+        page_index: u32,
+        page_size: u32,
+    ) -> APIResult<FundingSettlementPage> {
+        let mut params: BTreeMap<String, String> = BTreeMap::new();
+
+        params.insert("contract_code".into(), contract_code.to_uppercase());
+        params.insert("page_index".into(), page_index.to_string());
+        params.insert("page_size".into(), page_size.to_string());
+
+        let data = self.get_swap("/linear-swap-api/v1/swap_historical_funding_rate", &params)?;
+
+        let page: ResponseEnvelope<FundingSettlementPage> = serde_json::from_str(data.as_str())?;
+
+        Ok(page.into_data()?)
+    }
+
+    pub fn get_swap_open_interest(&self, contract_code: &str) -> APIResult<OpenInterest> {
+        let mut params: BTreeMap<String, String> = BTreeMap::new();
+
+        params.insert("contract_code".into(), contract_code.to_uppercase());
+
+        let data = self.get_swap("/linear-swap-api/v1/swap_open_interest", &params)?;
+
+        let open_interest: ResponseEnvelope<Vec<OpenInterest>> =
+            serde_json::from_str(data.as_str())?;
+
+        select_open_interest(open_interest.into_data()?, contract_code)
+    }
+
+    // Basis of the last periods, oldest first. size is 2000 at most, Year1 is not a
+    // basis period
+    pub fn get_swap_basis(
+        &self,
+        contract_code: &str,
+        period: KlinePeriod,
+        price_type: BasisPriceType,
+        size: u32,
+    ) -> APIResult<Vec<Basis>> {
+        let mut params: BTreeMap<String, String> = BTreeMap::new();
+
+        params.insert("contract_code".into(), contract_code.to_uppercase());
+        params.insert("period".into(), period.as_str().into());
+        params.insert("basis_price_type".into(), price_type.as_str().into());
+        params.insert("size".into(), size.to_string());
+
+        let data = self.get_swap("/index/market/history/linear_swap_basis", &params)?;
+
+        let basis: ResponseEnvelope<Vec<Basis>> = serde_json::from_str(data.as_str())?;
+
+        Ok(basis.into_data()?)
+    }
+
+    // Mark price of the last periods, oldest first. size is 2000 at most
+    pub fn get_swap_mark_prices(
+        &self,
+        contract_code: &str,
+        period: KlinePeriod,
+        size: u32,
+    ) -> APIResult<Vec<MarkPrice>> {
+        let mut params: BTreeMap<String, String> = BTreeMap::new();
+
+        params.insert("contract_code".into(), contract_code.to_uppercase());
+        params.insert("period".into(), period.as_str().into());
+        params.insert("size".into(), size.to_string());
+
+        let data = self.get_swap(
+            "/index/market/history/linear_swap_mark_price_kline",
+            &params,
+        )?;
+
+        let klines: ResponseEnvelope<Vec<MarkPriceKline>> = serde_json::from_str(data.as_str())?;
+
+        Ok(mark_prices(
+            &contract_code.to_uppercase(),
+            klines.into_data()?,
+        ))
+    }
+
+    // Current mark price, the close of the running 1min period
+    pub fn get_swap_mark_price(&self, contract_code: &str) -> APIResult<MarkPrice> {
+        match self
+            .get_swap_mark_prices(contract_code, KlinePeriod::Min1, 1)?
+            .pop()
+        {
+            Some(mark_price) => Ok(mark_price),
+            None => Err(Box::new(HuobiError::ApiError(format!(
+                "no mark price for {}",
+                contract_code
+            )))),
+        }
+    }
+
+    pub fn get_swap_estimated_settlement(
+        &self,
+        contract_code: &str,
+    ) -> APIResult<EstimatedSettlement> {
+        let mut params: BTreeMap<String, String> = BTreeMap::new();
+
+        params.insert("contract_code".into(), contract_code.to_uppercase());
+
+        let data = self.get_swap(
+            "/linear-swap-api/v1/swap_estimated_settlement_price",
+            &params,
+        )?;
+
+        let settlements: ResponseEnvelope<Vec<EstimatedSettlement>> =
+            serde_json::from_str(data.as_str())?;
+
+        match settlements
+            .into_data()?
+            .into_iter()
+            .find(|settlement| settlement.contract_code.eq_ignore_ascii_case(contract_code))
+        {
+            Some(settlement) => Ok(settlement),
+            None => Err(Box::new(HuobiError::ApiError(format!(
+                "no estimated settlement for {}",
+                contract_code
+            )))),
+        }
+    }
+
+    fn get_swap(&self, path: &str, params: &BTreeMap<String, String>) -> APIResult<String> {
+        self.get_external(&format!(
+            "{}{}?{}",
+            HUOBI_SWAP_URL,
+            path,
+            Client::build_request(params)
+        ))
+    }
+}
+
+fn mark_prices(contract_code: &str, klines: Vec<MarkPriceKline>) -> Vec<MarkPrice> {
+    klines
+        .into_iter()
+        .map(|kline| MarkPrice {
+            contract_code: contract_code.to_string(),
+            mark_price: kline.close,
+            ts: kline.id * 1000,
+        })
+        .collect()
+}
+
+// Huobi answers with a list even for one contract
+fn select_open_interest(
+    open_interests: Vec<OpenInterest>,
+    contract_code: &str,
+) -> APIResult<OpenInterest> {
+    match open_interests.into_iter().find(|open_interest| {
+        open_interest
+            .contract_code
+            .eq_ignore_ascii_case(contract_code)
+    }) {
+        Some(open_interest) => Ok(open_interest),
+        None => Err(Box::new(HuobiError::ApiError(format!(
+            "no open interest for {}",
+            contract_code
+        )))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn parse_funding_rate() {
+        let data = r#"{"status":"ok","data":{"estimated_rate":null,"funding_rate":"0.000100000000000000","contract_code":"BTC-USDT","symbol":"BTC","fee_asset":"USDT","funding_time":"1603872000000","next_funding_time":null},"ts":1603866304635}"#;
+
+        let funding_rate: FundingRate = serde_json::from_str::<ResponseEnvelope<FundingRate>>(data)
+            .unwrap()
+            .into_data()
+            .unwrap();
+
+        assert_eq!(funding_rate.contract_code, "BTC-USDT");
+        assert!((funding_rate.funding_rate - 0.0001).abs() < 1e-12);
+        assert_eq!(funding_rate.estimated_rate, None);
+        assert_eq!(funding_rate.funding_time, 1603872000000);
+        assert_eq!(funding_rate.next_funding_time, None);
+    }
+
+    #[test]
+    fn parse_funding_history() {
+        let data = r#"{"status":"ok","data":{"total_page":62,"current_page":1,"total_size":62,"data":[{"avg_premium_index":"0.000049895833333333","funding_rate":"0.000100000000000000","realized_rate":"0.000100000000000000","funding_time":"1603843200000","contract_code":"BTC-USDT","symbol":"BTC","fee_asset":"USDT"}]},"ts":1603866348745}"#;
+
+        let page: FundingSettlementPage =
+            serde_json::from_str::<ResponseEnvelope<FundingSettlementPage>>(data)
+                .unwrap()
+                .into_data()
+                .unwrap();
+
+        assert_eq!(page.total_page, 62);
+        assert_eq!(page.data[0].funding_time, 1603843200000);
+        assert!((page.data[0].realized_rate - 0.0001).abs() < 1e-12);
+    }
+
+    #[test]
+    fn parse_open_interest() {
+        let data = r#"{"status":"ok","data":[{"volume":7192610.0,"amount":7192.61,"symbol":"BTC","value":445583401.5,"contract_code":"BTC-USDT","trade_amount":0,"trade_volume":0,"trade_turnover":0,"business_type":"swap","pair":"BTC-USDT","contract_type":"swap"}],"ts":1603866304635}"#;
+
+        let open_interests = serde_json::from_str::<ResponseEnvelope<Vec<OpenInterest>>>(data)
+            .unwrap()
+            .into_data()
+            .unwrap();
+
+        let open_interest = select_open_interest(open_interests.clone(), "btc-usdt").unwrap();
+        assert_eq!(open_interest.symbol, "BTC");
+        assert!((open_interest.amount - 7192.61).abs() < 1e-9);
+        assert!((open_interest.value - 445583401.5).abs() < 1e-6);
+
+        assert!(select_open_interest(open_interests, "ETH-USDT").is_err());
+    }
+
+    #[test]
+    fn parse_basis() {
+        let data = r#"{"ch":"market.BTC-USDT.basis.1min.open","data":[{"basis":"-27.593412766666666666","basis_rate":"-0.0010234","contract_price":26930.5,"id":1603871940,"index_price":"26958.093412766666666666"}],"status":"ok","ts":1603871986602}"#;
+
+        let basis = serde_json::from_str::<ResponseEnvelope<Vec<Basis>>>(data)
+            .unwrap()
+            .into_data()
+            .unwrap();
+
+        assert_eq!(basis[0].id, 1603871940);
+        assert!((basis[0].basis + 27.593412766666666).abs() < 1e-9);
+        assert!((basis[0].contract_price - 26930.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn parse_mark_prices() {
+        let data = r#"{"ch":"market.BTC-USDT.mark_price.1min","data":[{"amount":"0","close":"31078.68","count":"0","high":"31078.68","id":1611105300,"low":"31078.68","open":"31078.68","trade_turnover":"0","vol":"0"},{"amount":"0","close":"31082.5","count":"0","high":"31082.5","id":1611105360,"low":"31078.68","open":"31078.68","trade_turnover":"0","vol":"0"}],"status":"ok","ts":1611105393469}"#;
+
+        let klines = serde_json::from_str::<ResponseEnvelope<Vec<MarkPriceKline>>>(data)
+            .unwrap()
+            .into_data()
+            .unwrap();
+
+        let mark_prices = mark_prices("BTC-USDT", klines);
+
+        assert_eq!(
+            mark_prices.last().unwrap(),
+            &MarkPrice {
+                contract_code: "BTC-USDT".into(),
+                mark_price: 31082.5,
+                ts: 1611105360000,
+            }
+        );
+    }
+
+    #[test]
+    fn parse_estimated_settlement() {
+        let data = r#"{"status":"ok","data":[{"contract_code":"BTC-USDT","settlement_type":"settlement","settlement_price":null,"estimated_settlement_price":"31078.09","business_type":"swap","pair":"BTC-USDT","contract_type":"swap"}],"ts":1611105393469}"#;
+
+        let settlements = serde_json::from_str::<ResponseEnvelope<Vec<EstimatedSettlement>>>(data)
+            .unwrap()
+            .into_data()
+            .unwrap();
+
+        assert_eq!(settlements[0].estimated_settlement_price, Some(31078.09));
+        assert_eq!(settlements[0].settlement_price, None);
+        assert_eq!(settlements[0].settlement_type, "settlement");
+    }
+
+    #[test]
+    fn swap_error_envelope() {
+        let data = r#"{"status":"error","err_code":1332,"err_msg":"The contract doesn't exist.","ts":1603866348745}"#;
+
+        let envelope: ResponseEnvelope<FundingRate> = serde_json::from_str(data).unwrap();
+
+        assert_eq!(envelope.err_code.as_deref(), Some("1332"));
+        assert!(envelope.into_data().is_err());
+    }
+}
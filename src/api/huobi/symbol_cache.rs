@@ -0,0 +1,324 @@
+use super::{
+    client::Client,
+    error::HuobiError,
+    log_policy::LogPolicy,
+    models::{APIResult, Symbol},
+};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+// Symbols missing from the cache trigger at most one refresh per interval
+pub(crate) const REFRESH_INTERVAL: Duration = Duration::from_secs(10);
+
+type Refresh = Box<dyn Fn() -> APIResult<Vec<Symbol>> + Send + Sync>;
+
+// Symbol metadata kept in memory for the rounding of order prices and amounts.
+// Rounding never falls back to a default precision, a guessed precision gets the order
+// rejected. Without metadata of the symbol it fails with PrecisionUnavailable, or when
+// the cache can refresh, with SymbolNotFound if the refreshed list does not have it
+pub struct SymbolCache {
+    symbols: Mutex<HashMap<String, Symbol>>,
+    refresh: Option<Refresh>,
+    last_refresh: Mutex<Option<Instant>>,
+    // Of the Client given to with_client
+    log_policy: LogPolicy,
+}
+
+impl Default for SymbolCache {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SymbolCache {
+    pub fn new() -> Self {
+        SymbolCache {
+            symbols: Mutex::new(HashMap::new()),
+            refresh: None,
+            last_refresh: Mutex::new(None),
+            log_policy: LogPolicy::default(),
+        }
+    }
+
+    // Symbols listed after the load, as new listings lag in the symbols list, are
+    // fetched with Client::refresh_symbols on first use
+    pub fn with_client(mut self, client: &Client) -> Self {
+        self.log_policy = *client.log_policy();
+        let client = client.clone();
+
+        self.with_refresh(move || client.refresh_symbols())
+    }
+
+    pub fn with_refresh<F>(mut self, refresh: F) -> Self
+    where
+        F: Fn() -> APIResult<Vec<Symbol>> + Send + Sync + 'static,
+    {
+        self.refresh = Some(Box::new(refresh));
+        self
+    }
+
+    // On error the cache keeps what it had
+    pub fn load(&self, client: &Client) -> APIResult<()> {
+        let symbols = client.get_symbols()?;
+
+        client.log_policy().detail(format_args!(
+            "[Huobi] Loaded {} symbols into the cache",
+            symbols.len()
+        ));
+
+        self.update(symbols);
+
+        Ok(())
+    }
+
+    pub fn update(&self, symbols: Vec<Symbol>) {
+        let mut cached = self.symbols.lock().unwrap();
+
+        for symbol in symbols {
+            cached.insert(symbol.symbol.clone(), symbol);
+        }
+    }
+
+    pub fn get(&self, symbol: &str) -> Option<Symbol> {
+        self.symbols
+            .lock()
+            .unwrap()
+            .get(&symbol.to_lowercase())
+            .cloned()
+    }
+
+    // get, refreshing the cache once when the symbol is missing
+    pub fn lookup(&self, symbol: &str) -> Result<Symbol, HuobiError> {
+        if let Some(symbol_info) = self.get(symbol) {
+            return Ok(symbol_info);
+        }
+
+        let refresh = match &self.refresh {
+            Some(refresh) => refresh,
+            None => return Err(HuobiError::PrecisionUnavailable(symbol.to_string())),
+        };
+
+        {
+            let mut last_refresh = self.last_refresh.lock().unwrap();
+
+            let is_due = match *last_refresh {
+                Some(last) => last.elapsed() >= REFRESH_INTERVAL,
+                None => true,
+            };
+            // A failed refresh is not counted, the next miss tries again
+            if is_due {
+                let symbols = refresh().map_err(|e| match e.downcast::<HuobiError>() {
+                    Ok(e) => *e,
+                    Err(e) => HuobiError::ApiError(e.to_string()),
+                })?;
+
+                self.log_policy.detail(format_args!(
+                    "[Huobi] Refreshed {} symbols for {}",
+                    symbols.len(),
+                    symbol
+                ));
+
+                self.update(symbols);
+                *last_refresh = Some(Instant::now());
+            }
+        }
+
+        self.get(symbol)
+            .ok_or_else(|| HuobiError::SymbolNotFound(symbol.to_lowercase()))
+    }
+
+    pub fn price_precision(&self, symbol: &str) -> Result<u8, HuobiError> {
+        self.lookup(symbol)
+            .map(|symbol_info| symbol_info.price_precision)
+    }
+
+    pub fn amount_precision(&self, symbol: &str) -> Result<u8, HuobiError> {
+        self.lookup(symbol)
+            .map(|symbol_info| symbol_info.amount_precision)
+    }
+
+    pub fn round_price(&self, symbol: &str, price: f64) -> Result<f64, HuobiError> {
+        self.round_price_with(symbol, price, RoundingMode::Nearest)
+    }
+
+    // Rounded down, never more than the amount that was asked for
+    pub fn round_amount(&self, symbol: &str, amount: f64) -> Result<f64, HuobiError> {
+        self.round_amount_with(symbol, amount, RoundingMode::Floor)
+    }
+
+    pub fn round_price_with(
+        &self,
+        symbol: &str,
+        price: f64,
+        mode: RoundingMode,
+    ) -> Result<f64, HuobiError> {
+        Ok(mode.round(price, self.price_precision(symbol)?))
+    }
+
+    pub fn round_amount_with(
+        &self,
+        symbol: &str,
+        amount: f64,
+        mode: RoundingMode,
+    ) -> Result<f64, HuobiError> {
+        Ok(mode.round(amount, self.amount_precision(symbol)?))
+    }
+}
+
+// Floor for sell amounts, so the order never exceeds the balance. Ceil for amounts
+// that must reach the min order value. Nearest for prices and display
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RoundingMode {
+    Floor,
+    Ceil,
+    Nearest,
+}
+
+impl RoundingMode {
+    pub fn round(&self, value: f64, precision: u8) -> f64 {
+        match self {
+            RoundingMode::Floor => floor_to(value, precision),
+            RoundingMode::Ceil => ceil_to(value, precision),
+            RoundingMode::Nearest => round_to(value, precision),
+        }
+    }
+}
+
+pub fn round_to(value: f64, precision: u8) -> f64 {
+    let factor = 10f64.powi(precision as i32);
+
+    (value * factor).round() / factor
+}
+
+pub fn floor_to(value: f64, precision: u8) -> f64 {
+    let factor = 10f64.powi(precision as i32);
+
+    // Tolerance for the error of the multiplication, 0.29 * 100 is 28.999999999999996
+    (value * factor + 1e-9).floor() / factor
+}
+
+pub fn ceil_to(value: f64, precision: u8) -> f64 {
+    let factor = 10f64.powi(precision as i32);
+
+    // Same tolerance as floor_to, 0.57 * 100 is 56.99999999999999
+    (value * factor - 1e-9).ceil() / factor
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use super::super::models::SymbolState;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    fn symbol(name: &str, price_precision: u8, amount_precision: u8) -> Symbol {
+        Symbol {
+            base: "btc".to_string(),
+            quote: "usdt".to_string(),
+            price_precision,
+            amount_precision,
+            partition: "main".to_string(),
+            symbol: name.to_string(),
+            state: SymbolState::Online,
+            value_precision: 8,
+            min_amount: 0.0001,
+            max_amount: 1000.,
+            min_value: 5.,
+            max_leverage: 0.,
+        }
+    }
+
+    #[test]
+    fn round_with_cached_precision() {
+        let cache = SymbolCache::new();
+
+        cache.update(vec![symbol("btcusdt", 2, 6)]);
+
+        assert_eq!(cache.round_price("BTCUSDT", 45123.456).unwrap(), 45123.46);
+        assert_eq!(cache.round_amount("btcusdt", 0.1234567).unwrap(), 0.123456);
+        assert_eq!(floor_to(0.29, 2), 0.29);
+    }
+
+    #[test]
+    fn round_with_mode() {
+        let cache = SymbolCache::new();
+
+        cache.update(vec![symbol("btcusdt", 2, 4)]);
+
+        assert_eq!(
+            cache
+                .round_amount_with("btcusdt", 0.00012, RoundingMode::Floor)
+                .unwrap(),
+            0.0001
+        );
+        assert_eq!(
+            cache
+                .round_amount_with("btcusdt", 0.00012, RoundingMode::Ceil)
+                .unwrap(),
+            0.0002
+        );
+        assert_eq!(
+            cache
+                .round_price_with("btcusdt", 45123.456, RoundingMode::Floor)
+                .unwrap(),
+            45123.45
+        );
+        assert_eq!(RoundingMode::Nearest.round(0.125, 2), 0.13);
+        // Exact values are left as they are
+        assert_eq!(RoundingMode::Ceil.round(0.57, 2), 0.57);
+        assert_eq!(RoundingMode::Floor.round(0.29, 2), 0.29);
+    }
+
+    #[test]
+    fn missing_symbol_refreshed_once() {
+        let refreshes = Arc::new(AtomicUsize::new(0));
+        let counted = refreshes.clone();
+        let cache = SymbolCache::new().with_refresh(move || {
+            counted.fetch_add(1, Ordering::Relaxed);
+            Ok(vec![symbol("btcusdt", 2, 6), symbol("newusdt", 4, 2)])
+        });
+
+        assert_eq!(cache.round_amount("NEWUSDT", 12.345).unwrap(), 12.34);
+        assert_eq!(cache.price_precision("newusdt").unwrap(), 4);
+
+        match cache.price_precision("dogeusdt") {
+            Err(HuobiError::SymbolNotFound(symbol)) => assert_eq!(symbol, "dogeusdt"),
+            result => panic!("unexpected result {:?}", result),
+        }
+        // The second miss comes within the refresh interval
+        assert!(cache.amount_precision("dogeusdt").is_err());
+
+        assert_eq!(refreshes.load(Ordering::Relaxed), 1);
+    }
+
+    #[test]
+    fn failed_refresh_tried_again() {
+        let refreshes = Arc::new(AtomicUsize::new(0));
+        let counted = refreshes.clone();
+        let cache = SymbolCache::new().with_refresh(move || {
+            counted.fetch_add(1, Ordering::Relaxed);
+            Err(Box::new(HuobiError::ApiError("timeout".into())))
+        });
+
+        for _ in 0..2 {
+            match cache.price_precision("newusdt") {
+                Err(HuobiError::ApiError(why)) => assert_eq!(why, "timeout"),
+                result => panic!("unexpected result {:?}", result),
+            }
+        }
+
+        assert_eq!(refreshes.load(Ordering::Relaxed), 2);
+    }
+
+    #[test]
+    fn precision_unavailable_without_metadata() {
+        let cache = SymbolCache::new();
+
+        match cache.round_price("btcusdt", 45123.456) {
+            Err(HuobiError::PrecisionUnavailable(symbol)) => assert_eq!(symbol, "btcusdt"),
+            result => panic!("unexpected result {:?}", result),
+        }
+    }
+}
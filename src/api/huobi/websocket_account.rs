@@ -1,12 +1,12 @@
 use super::client::{build_query_string, get_timestamp, sign_hmac_sha256_base64};
+use super::log_policy::LogPolicy;
 use super::models::*;
 use lazy_static::lazy_static;
 use serde::Deserialize;
 use serde_json::json;
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, HashSet, VecDeque};
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Mutex;
-use tracing::{debug, info};
 use tungstenite::client::AutoStream;
 use tungstenite::handshake::client::Response;
 use tungstenite::protocol::WebSocket;
@@ -35,11 +35,53 @@ struct PingData {
 
 pub enum WebsocketEvent {
     OrderUpdate(OrderSubs),
+    AccountUpdate(AccountSubs),
+}
+
+// Huobi redelivers order updates after a reconnect, a fill must not be counted twice
+const DEDUP_CAPACITY: usize = 10_000;
+
+// Keys of the last DEDUP_CAPACITY order updates
+pub struct OrderUpdateDedup {
+    seen: HashSet<(u64, &'static str, u64)>,
+    order: VecDeque<(u64, &'static str, u64)>,
+    capacity: usize,
+}
+
+impl OrderUpdateDedup {
+    pub fn new(capacity: usize) -> Self {
+        OrderUpdateDedup {
+            seen: HashSet::new(),
+            order: VecDeque::new(),
+            capacity,
+        }
+    }
+
+    // Whether the update is new, it is remembered if so
+    pub fn insert(&mut self, update: &EventType) -> bool {
+        let key = update.dedup_key();
+
+        if !self.seen.insert(key) {
+            return false;
+        }
+
+        self.order.push_back(key);
+
+        if self.order.len() > self.capacity {
+            if let Some(oldest) = self.order.pop_front() {
+                self.seen.remove(&oldest);
+            }
+        }
+
+        true
+    }
 }
 
 pub struct WebSockets<'a> {
     pub socket: Option<(WebSocket<AutoStream>, Response)>,
     handler: Box<dyn FnMut(WebsocketEvent) -> APIResult<()> + 'a>,
+    dedup: Option<OrderUpdateDedup>,
+    log_policy: LogPolicy,
 }
 
 impl<'a> WebSockets<'a> {
@@ -50,14 +92,30 @@ impl<'a> WebSockets<'a> {
         WebSockets {
             socket: None,
             handler: Box::new(handler),
+            dedup: Some(OrderUpdateDedup::new(DEDUP_CAPACITY)),
+            log_policy: LogPolicy::default(),
         }
     }
 
+    // Same levels as the Client, messages at the body level and connection events at
+    // the endpoint level. The keys and signature of the auth request are masked
+    pub fn with_log_policy(mut self, log_policy: LogPolicy) -> Self {
+        self.log_policy = log_policy;
+        self
+    }
+
+    // Every order update goes to the handler, redelivered ones too. For callers
+    // that dedup on their own, e.g. with EventType::sequence
+    pub fn without_dedup(mut self) -> Self {
+        self.dedup = None;
+        self
+    }
+
     pub fn connect_auth(
         &mut self,
         endpoint: &str,
         symbols: Vec<&str>,
-        _channels: Vec<&str>,
+        channels: Vec<&str>,
         access_key: &str,
         secret_key: &str,
     ) -> APIResult<()> {
@@ -67,9 +125,10 @@ impl<'a> WebSockets<'a> {
             SYMBOLS.lock().unwrap().push(symbol.to_string());
         }
 
-        // for channel in channels {
-        //     CHANNELS.lock().unwrap().push(channel.to_string());
-        // }
+        // e.g. "accounts.update#1", subscribed as is
+        for channel in channels {
+            CHANNELS.lock().unwrap().push(channel.to_string());
+        }
 
         match connect(url) {
             Ok(answer) => {
@@ -110,13 +169,14 @@ impl<'a> WebSockets<'a> {
                     socket
                         .0
                         .write_message(tungstenite::Message::Text(auth_message.to_string()))?;
-                    debug!("Write auth message {}", auth_message.to_string());
+                    self.log_policy.ws_sent(&auth_message);
                 };
 
                 Ok(())
             }
             Err(e) => {
-                info!("Error during handshake {}", e);
+                self.log_policy
+                    .ws_event(format_args!("[Huobi] WS error during handshake {}", e));
                 Err(Box::new(e))
             }
         }
@@ -127,7 +187,8 @@ impl<'a> WebSockets<'a> {
             socket.0.close(None)?;
             Ok(())
         } else {
-            info!("Not able to close the connection");
+            self.log_policy
+                .ws_event(format_args!("[Huobi] WS not able to close the connection"));
             Ok(())
         }
     }
@@ -146,7 +207,7 @@ impl<'a> WebSockets<'a> {
                                 serde_json::Value::String(action_type) => {
                                     match action_type.as_str() {
                                         "ping" => {
-                                            debug!("ping {}", text);
+                                            self.log_policy.ws_received(&text);
 
                                             let ping_number =
                                                 serde_json::from_str::<PingMessage>(&text)
@@ -168,20 +229,28 @@ impl<'a> WebSockets<'a> {
                                                     ),
                                                 )?;
 
-                                                debug!(
-                                                    "Write message {}",
-                                                    pong_message.to_string()
-                                                );
+                                                self.log_policy.ws_sent(&pong_message);
                                             };
                                         }
 
                                         "req" => {
-                                            debug!("req {}", text);
-
-                                            for symbol in &*SYMBOLS.lock().unwrap() {
+                                            self.log_policy.ws_received(&text);
+
+                                            let mut subscriptions: Vec<String> = SYMBOLS
+                                                .lock()
+                                                .unwrap()
+                                                .iter()
+                                                .map(|symbol| {
+                                                    format!("orders#{}", symbol.to_lowercase())
+                                                })
+                                                .collect();
+                                            subscriptions
+                                                .extend(CHANNELS.lock().unwrap().iter().cloned());
+
+                                            for channel in subscriptions {
                                                 let subscribe_message = json!({
                                                         "action": "sub",
-                                                        "ch": format!("orders#{}", symbol.to_lowercase())
+                                                        "ch": channel
                                                 });
 
                                                 // subscribe
@@ -191,26 +260,53 @@ impl<'a> WebSockets<'a> {
                                                             subscribe_message.to_string(),
                                                         ),
                                                     )?;
-                                                    debug!(
-                                                        "[Huobi] WebSockets write message {}",
-                                                        subscribe_message.to_string()
-                                                    );
+                                                    self.log_policy.ws_sent(&subscribe_message);
                                                 };
                                             }
                                         }
 
                                         "sub" => {
-                                            debug!("sub {}", text);
+                                            self.log_policy.ws_received(&text);
                                         }
 
                                         "push" => {
-                                            debug!("push {:?}", text);
-
-                                            let order_sub: OrderSubs =
-                                                serde_json::from_str(&text).unwrap();
+                                            self.log_policy.ws_received(&text);
+
+                                            let channel = msg
+                                                .get("ch")
+                                                .and_then(|ch| ch.as_str())
+                                                .unwrap_or_default();
+
+                                            if channel.starts_with("accounts.update") {
+                                                let account_sub: AccountSubs =
+                                                    serde_json::from_str(&text)?;
+
+                                                (self.handler)(WebsocketEvent::AccountUpdate(
+                                                    account_sub,
+                                                ))?;
+                                            } else {
+                                                let order_sub: OrderSubs =
+                                                    serde_json::from_str(&text).unwrap();
+
+                                                let is_new = match self.dedup {
+                                                    Some(ref mut dedup) => {
+                                                        dedup.insert(&order_sub.data)
+                                                    }
+                                                    None => true,
+                                                };
 
-                                            (self.handler)(WebsocketEvent::OrderUpdate(order_sub))
-                                                .unwrap();
+                                                if is_new {
+                                                    (self.handler)(WebsocketEvent::OrderUpdate(
+                                                        order_sub,
+                                                    ))
+                                                    .unwrap();
+                                                } else {
+                                                    self.log_policy.ws_event(format_args!(
+                                                        "[Huobi] Skip redelivered {}",
+                                                        text
+                                                    ));
+                                                }
+                                            }
                                         }
 
                                         _ => {}
@@ -223,11 +319,13 @@ impl<'a> WebSockets<'a> {
                     }
 
                     Message::Ping(_bin) | Message::Pong(_bin) | Message::Binary(_bin) => {
-                        debug!("[Huobi] WebSockets binary message");
+                        self.log_policy
+                            .ws_event(format_args!("[Huobi] WebSockets binary message"));
                     }
 
                     Message::Close(e) => {
-                        info!("Disconnected {:?}", e);
+                        self.log_policy
+                            .ws_event(format_args!("[Huobi] WS disconnected {:?}", e));
                     }
                 }
             }
@@ -235,3 +333,45 @@ impl<'a> WebSockets<'a> {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn trade(order_id: u64, trade_id: u64) -> EventType {
+        serde_json::from_value(json!({
+            "tradePrice": "76.000000000000000000",
+            "tradeVolume": "1.013157894736842100",
+            "tradeId": trade_id,
+            "tradeTime": 1583854188883u64,
+            "aggressor": true,
+            "remainAmt": "0.000000000000000400000000000000000000",
+            "execAmt": "2",
+            "orderId": order_id,
+            "type": "buy-limit",
+            "clientOrderId": "abc123",
+            "orderSource": "spot-api",
+            "orderPrice": "15000",
+            "orderSize": "0.01",
+            "orderStatus": "filled",
+            "symbol": "btcusdt",
+            "eventType": "trade"
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn redelivered_fill_is_skipped() {
+        let mut dedup = OrderUpdateDedup::new(2);
+
+        assert!(dedup.insert(&trade(1, 100)));
+        assert!(!dedup.insert(&trade(1, 100)));
+        assert!(dedup.insert(&trade(1, 101)));
+        assert_eq!(trade(1, 101).sequence(), 101);
+
+        // Only the last 2 keys are kept
+        assert!(dedup.insert(&trade(2, 200)));
+        assert!(dedup.insert(&trade(1, 100)));
+    }
+}
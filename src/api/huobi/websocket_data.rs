@@ -1,41 +1,55 @@
+use super::error::HuobiError;
+use super::log_policy::LogPolicy;
+use super::market_sink::{market_record, SinkWriter};
+use super::models::{APIResult, Kline, KlinePeriod, SymbolTicker};
 use flate2::read::GzDecoder;
 use serde::{Deserialize, Serialize};
-use serde_json;
+use serde_json::{self, json};
+use std::collections::{BTreeSet, HashMap};
 use std::io::prelude::*;
-use tracing::debug;
+use std::sync::mpsc::{self, Receiver, SyncSender, TrySendError};
+use std::sync::{Arc, Mutex};
 use tungstenite::{client::AutoStream, connect, Message, WebSocket};
 use url::Url;
 
 const WS_HUOBI_URL: &str = "wss://api.huobi.pro/ws";
 
+const ALL_TICKERS_CHANNEL: &str = "market.overview";
+
 const HUOBI_READ_MESSAGE_ERROR: &str = "Error reading message";
 
+// Events queued to a consumer that does not keep up, newer ones are dropped
+const ROUTE_CAPACITY: usize = 1024;
+
 #[derive(Debug)]
 pub struct HuobiWS {
     socket: WebSocket<AutoStream>,
+    log_policy: LogPolicy,
 }
 
 impl HuobiWS {
     pub fn connect(instrument: &str) -> Self {
-        debug!("Connecting to Huobi WebSocket");
+        HuobiWS::connect_with_log_policy(instrument, LogPolicy::default())
+    }
+
+    pub fn connect_with_log_policy(instrument: &str, log_policy: LogPolicy) -> Self {
+        log_policy.ws_event(format_args!("[Huobi] WS connecting to {}", WS_HUOBI_URL));
 
         // Connect to Huobi WebSockets
         let (mut socket, response) =
             connect(Url::parse(WS_HUOBI_URL).unwrap()).expect("Can't connect");
 
-        debug!("Huobi WebSocket headers");
-
         for (header, _value) in response.headers() {
-            debug!("* {}", header);
+            log_policy.ws_event(format_args!("[Huobi] WS header {}", header));
         }
 
         // Read Huobi ping message
         let msg = socket.read_message().expect(HUOBI_READ_MESSAGE_ERROR);
         let data = msg.into_data();
-        let ping_number = HuobiWS::ping_number(&data);
+        let ping_number = HuobiWS::ping_number(&data, &log_policy);
 
         // Send back pong message to Huobi
-        HuobiWS::send_pong_message(ping_number, &mut socket);
+        HuobiWS::send_pong_message(ping_number, &mut socket, &log_policy);
 
         let sub_msg = SubscriptionMessage {
             sub: format!("market.{}.mbp.refresh.20", instrument.to_lowercase()),
@@ -44,6 +58,8 @@ impl HuobiWS {
 
         let sub_msg_str = serde_json::to_string(&sub_msg).unwrap();
 
+        log_policy.ws_sent(&serde_json::to_value(&sub_msg).unwrap());
+
         // Send subscription message
         socket
             .write_message(Message::Text(sub_msg_str.into()))
@@ -52,51 +68,47 @@ impl HuobiWS {
         // Receive responce status
         let _status_msg = socket.read_message().expect(HUOBI_READ_MESSAGE_ERROR);
 
-        HuobiWS { socket }
+        HuobiWS { socket, log_policy }
     }
 
-    fn decode_message(data: &Vec<u8>) -> String {
+    fn decode_message(data: &Vec<u8>, log_policy: &LogPolicy) -> String {
         let mut gz_decoder = GzDecoder::new(&data[..]);
         let mut buffer = String::new();
         match gz_decoder.read_to_string(&mut buffer) {
             Ok(_v) => {}
-            Err(e) => println!("error decode message {}", e),
+            Err(e) => log_policy.ws_event(format_args!("[Huobi] WS error decode message {}", e)),
         };
 
-        debug!("[Huobi WS] Decoded message: {}", buffer);
+        log_policy.ws_received(&buffer);
 
         buffer
     }
 
-    fn ping_number(data: &Vec<u8>) -> i64 {
-        let ping_string = HuobiWS::decode_message(data);
+    fn ping_number(data: &Vec<u8>, log_policy: &LogPolicy) -> i64 {
+        let ping_string = HuobiWS::decode_message(data, log_policy);
         let ping_msg: PingMessage = serde_json::from_str(&ping_string).unwrap();
 
-        debug!("[Huobi WS] Ping number: {}", ping_msg.ping);
-
         ping_msg.ping
     }
 
-    fn send_pong_message(pong: i64, socket: &mut WebSocket<AutoStream>) {
-        debug!("[Huobi WS] Send pong message, pong = {}", pong);
-
+    fn send_pong_message(pong: i64, socket: &mut WebSocket<AutoStream>, log_policy: &LogPolicy) {
         let pong_msg = PongMessage { pong: pong };
 
+        log_policy.ws_sent(&serde_json::to_value(&pong_msg).unwrap());
+
         let pong_str = serde_json::to_string(&pong_msg).unwrap();
 
         match socket.write_message(Message::Text(pong_str.into())) {
             Ok(_) => {}
-            Err(e) => println!("error send pong message {}", e),
+            Err(e) => log_policy.ws_event(format_args!("[Huobi] WS error send pong message {}", e)),
         };
     }
 
     fn read_message(&mut self) -> String {
-        debug!("[Huobi WS] Read message");
-
         let msg = self.socket.read_message().expect(HUOBI_READ_MESSAGE_ERROR);
         let data = msg.into_data();
 
-        HuobiWS::decode_message(&data)
+        HuobiWS::decode_message(&data, &self.log_policy)
     }
 
     pub fn get_depth(&mut self) -> Depth {
@@ -105,20 +117,20 @@ impl HuobiWS {
             let msg = self.socket.read_message().expect(HUOBI_READ_MESSAGE_ERROR);
 
             let data = msg.into_data();
-            let decoded_msg = HuobiWS::decode_message(&data);
+            let decoded_msg = HuobiWS::decode_message(&data, &self.log_policy);
 
             if decoded_msg.contains("ping") {
-                debug!("[Huobi WS] Ping");
+                let ping_number = HuobiWS::ping_number(&data, &self.log_policy);
 
-                let ping_number = HuobiWS::ping_number(&data);
-
-                HuobiWS::send_pong_message(ping_number, &mut self.socket);
+                HuobiWS::send_pong_message(ping_number, &mut self.socket, &self.log_policy);
             } else {
                 match serde_json::from_str::<DepthMessage>(&decoded_msg) {
                     Ok(depth_msg) => {
                         return HuobiWS::tick_to_depth(depth_msg.tick);
                     }
-                    Err(e) => println!("err get depth {}", e),
+                    Err(e) => self
+                        .log_policy
+                        .ws_event(format_args!("[Huobi] WS error get depth {}", e)),
                 }
             }
         }
@@ -147,6 +159,477 @@ impl HuobiWS {
     }
 }
 
+#[derive(Debug, Clone)]
+pub enum KlineEvent {
+    // Recent candles requested on subscription, the oldest first
+    Snapshot { channel: String, klines: Vec<Kline> },
+    Update { channel: String, kline: Kline },
+}
+
+#[derive(Debug, Clone)]
+pub enum MarketEvent {
+    Kline(KlineEvent),
+    // 24h stats of every symbol, pushed about once a second
+    Tickers { ts: u64, tickers: Vec<SymbolTicker> },
+}
+
+impl MarketEvent {
+    pub fn channel(&self) -> &str {
+        match self {
+            MarketEvent::Kline(KlineEvent::Snapshot { channel, .. })
+            | MarketEvent::Kline(KlineEvent::Update { channel, .. }) => channel,
+            MarketEvent::Tickers { .. } => ALL_TICKERS_CHANNEL,
+        }
+    }
+}
+
+enum MarketMessage {
+    Ping(i64),
+    Subscribed(String),
+    Unsubscribed(String),
+    // Error answer to the sub, req or unsub of the channel
+    Rejected { channel: String, why: String },
+    Event(MarketEvent),
+    Other,
+}
+
+// Consumers of the channels of a MarketStream, each with its own receiver
+#[derive(Debug, Default)]
+struct Router {
+    routes: HashMap<String, Vec<SyncSender<MarketEvent>>>,
+    // Channels routed through a RouteHandle, subscribed by the socket reader
+    pending: Vec<PendingSub>,
+    // Events lost to consumers with a full queue
+    dropped: u64,
+}
+
+#[derive(Debug)]
+struct PendingSub {
+    channel: String,
+    // Recent candles requested before the sub
+    snapshot: bool,
+}
+
+enum Dispatch {
+    // No consumer for the channel, the event goes to the caller of next_event
+    Unrouted(MarketEvent),
+    Delivered,
+    // Every receiver of the channel is dropped, nobody listens to it anymore
+    Abandoned(String),
+}
+
+impl Router {
+    fn add(&mut self, channel: &str) -> Receiver<MarketEvent> {
+        let (sender, receiver) = mpsc::sync_channel(ROUTE_CAPACITY);
+
+        self.routes
+            .entry(channel.to_string())
+            .or_default()
+            .push(sender);
+
+        receiver
+    }
+
+    // Receivers of the channel are disconnected
+    fn remove(&mut self, channel: &str) {
+        self.routes.remove(channel);
+    }
+
+    fn dispatch(&mut self, event: MarketEvent) -> Dispatch {
+        let senders = match self.routes.get_mut(event.channel()) {
+            Some(senders) => senders,
+            None => return Dispatch::Unrouted(event),
+        };
+
+        let dropped = &mut self.dropped;
+        senders.retain(|sender| match sender.try_send(event.clone()) {
+            Ok(()) => true,
+            Err(TrySendError::Full(_)) => {
+                *dropped += 1;
+                true
+            }
+            Err(TrySendError::Disconnected(_)) => false,
+        });
+
+        if senders.is_empty() {
+            let channel = event.channel().to_string();
+            self.routes.remove(&channel);
+            return Dispatch::Abandoned(channel);
+        }
+
+        Dispatch::Delivered
+    }
+}
+
+// Adds consumers to a MarketStream from other threads, also while run reads the
+// socket. The sub of a new channel is sent by the reader after the next message it
+// reads, so within a ping interval (5 s)
+#[derive(Debug, Clone)]
+pub struct RouteHandle {
+    router: Arc<Mutex<Router>>,
+}
+
+impl RouteHandle {
+    // The channel is unsubscribed once every receiver of it is dropped
+    pub fn route(&self, channel: &str) -> Receiver<MarketEvent> {
+        self.add(channel, false)
+    }
+
+    // The Snapshot only comes if the channel is not subscribed yet
+    pub fn route_klines(&self, symbol: &str, period: KlinePeriod) -> Receiver<MarketEvent> {
+        self.add(&kline_channel(symbol, period), true)
+    }
+
+    fn add(&self, channel: &str, snapshot: bool) -> Receiver<MarketEvent> {
+        let mut router = self.router.lock().unwrap();
+
+        router.pending.push(PendingSub {
+            channel: channel.to_string(),
+            snapshot,
+        });
+
+        router.add(channel)
+    }
+}
+
+fn kline_channel(symbol: &str, period: KlinePeriod) -> String {
+    format!("market.{}.kline.{}", symbol.to_lowercase(), period.as_str())
+}
+
+// Market data stream of typed channels on one connection. Huobi takes many channels
+// per connection, so one stream serves every symbol: each consumer gets a receiver
+// of its channel with route, and one thread reads the socket with run. Consumers
+// added while run goes on come through a RouteHandle
+#[derive(Debug)]
+pub struct MarketStream {
+    socket: WebSocket<AutoStream>,
+    // Channels to subscribe again on reconnect. A channel is removed once Huobi
+    // acknowledges its unsub or rejects it
+    subscriptions: BTreeSet<String>,
+    router: Arc<Mutex<Router>>,
+    // Gets every market push read from the socket
+    sink: Option<SinkWriter>,
+    log_policy: LogPolicy,
+}
+
+impl MarketStream {
+    pub fn connect() -> APIResult<Self> {
+        let (socket, _response) = connect(Url::parse(WS_HUOBI_URL)?)?;
+
+        Ok(MarketStream {
+            socket,
+            subscriptions: BTreeSet::new(),
+            router: Arc::new(Mutex::new(Router::default())),
+            sink: None,
+            log_policy: LogPolicy::default(),
+        })
+    }
+
+    pub fn with_log_policy(mut self, log_policy: LogPolicy) -> Self {
+        self.log_policy = log_policy;
+        self
+    }
+
+    pub fn with_sink(mut self, sink: SinkWriter) -> Self {
+        self.sink = Some(sink);
+        self
+    }
+
+    pub fn sink(&self) -> Option<&SinkWriter> {
+        self.sink.as_ref()
+    }
+
+    // New connection with the same subscriptions
+    pub fn reconnect(&mut self) -> APIResult<()> {
+        let (socket, _response) = connect(Url::parse(WS_HUOBI_URL)?)?;
+        self.socket = socket;
+
+        let channels: Vec<String> = self.subscriptions.iter().cloned().collect();
+
+        for channel in channels {
+            self.subscribe(&channel)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn route_handle(&self) -> RouteHandle {
+        RouteHandle {
+            router: self.router.clone(),
+        }
+    }
+
+    // Events routed consumers did not take because their queue was full
+    pub fn dropped(&self) -> u64 {
+        self.router.lock().unwrap().dropped
+    }
+
+    pub fn subscriptions(&self) -> &BTreeSet<String> {
+        &self.subscriptions
+    }
+
+    // Raw channel, e.g. "market.btcusdt.kline.1min"
+    pub fn subscribe(&mut self, channel: &str) -> APIResult<()> {
+        let sub_msg = json!({ "sub": channel, "id": channel });
+
+        self.log_policy.ws_sent(&sub_msg);
+
+        self.socket
+            .write_message(Message::Text(sub_msg.to_string()))?;
+
+        self.subscriptions.insert(channel.to_string());
+
+        Ok(())
+    }
+
+    // Pushes of the channel may still come until Huobi acknowledges the unsub
+    pub fn unsubscribe(&mut self, channel: &str) -> APIResult<()> {
+        let unsub_msg = json!({ "unsub": channel, "id": channel });
+
+        self.log_policy.ws_sent(&unsub_msg);
+
+        self.socket
+            .write_message(Message::Text(unsub_msg.to_string()))?;
+
+        Ok(())
+    }
+
+    // The channel only pushes changes, so the recent candles are requested first
+    // and come as a Snapshot before the first Update
+    pub fn subscribe_klines(&mut self, symbol: &str, period: KlinePeriod) -> APIResult<()> {
+        self.subscribe_with_snapshot(&kline_channel(symbol, period))
+    }
+
+    fn subscribe_with_snapshot(&mut self, channel: &str) -> APIResult<()> {
+        let req_msg = json!({ "req": channel, "id": channel });
+
+        self.log_policy.ws_sent(&req_msg);
+
+        self.socket
+            .write_message(Message::Text(req_msg.to_string()))?;
+
+        self.subscribe(channel)
+    }
+
+    // Snapshots of every symbol come as MarketEvent::Tickers, without bid and ask
+    pub fn subscribe_all_tickers(&mut self) -> APIResult<()> {
+        self.subscribe(ALL_TICKERS_CHANNEL)
+    }
+
+    // Receiver of the events of the channel, subscribed unless it already is.
+    // The channel is unsubscribed once every receiver of it is dropped
+    pub fn route(&mut self, channel: &str) -> APIResult<Receiver<MarketEvent>> {
+        if !self.subscriptions.contains(channel) {
+            self.subscribe(channel)?;
+        }
+
+        Ok(self.router.lock().unwrap().add(channel))
+    }
+
+    // Only the consumer that subscribes the channel gets the Snapshot
+    pub fn route_klines(
+        &mut self,
+        symbol: &str,
+        period: KlinePeriod,
+    ) -> APIResult<Receiver<MarketEvent>> {
+        let channel = kline_channel(symbol, period);
+
+        if !self.subscriptions.contains(&channel) {
+            self.subscribe_klines(symbol, period)?;
+        }
+
+        Ok(self.router.lock().unwrap().add(&channel))
+    }
+
+    // Subscribes the channels routed through a RouteHandle
+    fn subscribe_pending(&mut self) -> APIResult<()> {
+        let pending = std::mem::take(&mut self.router.lock().unwrap().pending);
+
+        for sub in pending {
+            if self.subscriptions.contains(&sub.channel) {
+                continue;
+            }
+
+            if sub.snapshot {
+                self.subscribe_with_snapshot(&sub.channel)?;
+            } else {
+                self.subscribe(&sub.channel)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    // Reads the socket for the routed consumers until it fails, events of channels
+    // without consumer are dropped. The receivers of a rejected channel are
+    // disconnected, other channels go on
+    pub fn run(&mut self) -> APIResult<()> {
+        loop {
+            self.read_event(false)?;
+        }
+    }
+
+    pub fn disconnect(&mut self) -> APIResult<()> {
+        self.socket.close(None)?;
+        Ok(())
+    }
+
+    // Blocks until the next candle event, other events are skipped
+    pub fn next_kline(&mut self) -> APIResult<KlineEvent> {
+        loop {
+            if let MarketEvent::Kline(event) = self.next_event()? {
+                return Ok(event);
+            }
+        }
+    }
+
+    // Blocks until the next event of a subscribed channel without consumer, events of
+    // routed channels go to their receivers. Answers pings meanwhile. Fails when
+    // Huobi rejects a channel, which is then no longer subscribed
+    pub fn next_event(&mut self) -> APIResult<MarketEvent> {
+        self.read_event(true)
+    }
+
+    fn read_event(&mut self, fail_on_rejected: bool) -> APIResult<MarketEvent> {
+        loop {
+            self.subscribe_pending()?;
+
+            let msg = self.socket.read_message()?;
+
+            let data = match msg {
+                Message::Binary(data) => data,
+                Message::Close(frame) => {
+                    return Err(Box::new(HuobiError::ApiError(format!(
+                        "market stream is closed: {:?}",
+                        frame
+                    ))))
+                }
+                _ => continue,
+            };
+
+            let text = HuobiWS::decode_message(&data, &self.log_policy);
+
+            if let Some(sink) = &self.sink {
+                if let Some(record) = market_record(&text) {
+                    sink.push(record);
+                }
+            }
+
+            match parse_market_message(&text)? {
+                MarketMessage::Ping(ping) => {
+                    HuobiWS::send_pong_message(ping, &mut self.socket, &self.log_policy)
+                }
+                MarketMessage::Subscribed(channel) => self
+                    .log_policy
+                    .ws_event(format_args!("[Huobi] WS subscribed to {}", channel)),
+                MarketMessage::Unsubscribed(channel) => {
+                    self.log_policy
+                        .ws_event(format_args!("[Huobi] WS unsubscribed from {}", channel));
+                    self.subscriptions.remove(&channel);
+                    self.router.lock().unwrap().remove(&channel);
+                }
+                MarketMessage::Rejected { channel, why } => {
+                    self.log_policy
+                        .ws_event(format_args!("[Huobi] WS rejected {}: {}", channel, why));
+                    self.subscriptions.remove(&channel);
+                    self.router.lock().unwrap().remove(&channel);
+
+                    if fail_on_rejected {
+                        return Err(Box::new(HuobiError::ApiError(format!(
+                            "market stream error: {}",
+                            why
+                        ))));
+                    }
+                }
+                MarketMessage::Event(event) => {
+                    let dispatch = self.router.lock().unwrap().dispatch(event);
+
+                    match dispatch {
+                        Dispatch::Unrouted(event) => return Ok(event),
+                        Dispatch::Delivered => {}
+                        Dispatch::Abandoned(channel) => self.unsubscribe(&channel)?,
+                    }
+                }
+                MarketMessage::Other => {}
+            }
+        }
+    }
+}
+
+fn parse_market_message(text: &str) -> APIResult<MarketMessage> {
+    let msg: serde_json::Value = serde_json::from_str(text)?;
+
+    if let Some(ping) = msg.get("ping").and_then(|ping| ping.as_i64()) {
+        return Ok(MarketMessage::Ping(ping));
+    }
+
+    if msg.get("status").and_then(|status| status.as_str()) == Some("error") {
+        // Subs, reqs and unsubs are sent with the channel as id
+        if let Some(channel) = msg.get("id").and_then(|id| id.as_str()) {
+            return Ok(MarketMessage::Rejected {
+                channel: channel.to_string(),
+                why: msg.to_string(),
+            });
+        }
+
+        return Err(Box::new(HuobiError::ApiError(format!(
+            "market stream error: {}",
+            msg
+        ))));
+    }
+
+    if let Some(channel) = msg.get("subbed").and_then(|channel| channel.as_str()) {
+        return Ok(MarketMessage::Subscribed(channel.to_string()));
+    }
+
+    if let Some(channel) = msg.get("unsubbed").and_then(|channel| channel.as_str()) {
+        return Ok(MarketMessage::Unsubscribed(channel.to_string()));
+    }
+
+    // Answer to req
+    if let (Some(channel), Some(data)) = (msg.get("rep"), msg.get("data")) {
+        if let Some(channel) = channel
+            .as_str()
+            .filter(|channel| channel.contains(".kline."))
+        {
+            let mut klines: Vec<Kline> = serde_json::from_value(data.clone())?;
+            klines.sort_by_key(|kline| kline.id);
+
+            return Ok(MarketMessage::Event(MarketEvent::Kline(
+                KlineEvent::Snapshot {
+                    channel: channel.to_string(),
+                    klines,
+                },
+            )));
+        }
+    }
+
+    if let (Some(channel), Some(tick)) = (msg.get("ch"), msg.get("tick")) {
+        if let Some(channel) = channel
+            .as_str()
+            .filter(|channel| channel.contains(".kline."))
+        {
+            return Ok(MarketMessage::Event(MarketEvent::Kline(
+                KlineEvent::Update {
+                    channel: channel.to_string(),
+                    kline: serde_json::from_value(tick.clone())?,
+                },
+            )));
+        }
+    }
+
+    if msg.get("ch").and_then(|channel| channel.as_str()) == Some(ALL_TICKERS_CHANNEL) {
+        if let Some(data) = msg.get("data") {
+            return Ok(MarketMessage::Event(MarketEvent::Tickers {
+                ts: msg.get("ts").and_then(|ts| ts.as_u64()).unwrap_or_default(),
+                tickers: serde_json::from_value(data.clone())?,
+            }));
+        }
+    }
+
+    Ok(MarketMessage::Other)
+}
+
 #[derive(Debug, Deserialize)]
 struct PingMessage {
     ping: i64,
@@ -213,7 +696,181 @@ impl Default for Depth {
 #[cfg(test)]
 mod tests {
 
-    use super::HuobiWS;
+    use super::*;
+
+    #[test]
+    fn parse_kline_snapshot_and_update() {
+        let snapshot = r#"{"id":"market.btcusdt.kline.1min","rep":"market.btcusdt.kline.1min","status":"ok","data":[{"id":1630000060,"open":47010.1,"close":47020.5,"low":47001.1,"high":47030,"amount":1.2,"vol":56420.3,"count":320},{"id":1630000000,"open":47000,"close":47010.1,"low":46990,"high":47015,"amount":2.1,"vol":98710.4,"count":410}]}"#;
+        let update = r#"{"ch":"market.btcusdt.kline.1min","ts":1630000075000,"tick":{"id":1630000060,"open":47010.1,"close":47025.0,"low":47001.1,"high":47030,"amount":1.3,"vol":61120.8,"count":331}}"#;
+
+        match parse_market_message(snapshot).unwrap() {
+            MarketMessage::Event(MarketEvent::Kline(KlineEvent::Snapshot { channel, klines })) => {
+                assert_eq!(channel, "market.btcusdt.kline.1min");
+                assert_eq!(klines[0].id, 1630000000);
+                assert_eq!(klines.len(), 2);
+            }
+            _ => panic!("snapshot is not parsed"),
+        }
+
+        match parse_market_message(update).unwrap() {
+            MarketMessage::Event(MarketEvent::Kline(KlineEvent::Update { kline, .. })) => {
+                assert_eq!(kline.id, 1630000060);
+                assert_eq!(kline.count, 331);
+            }
+            _ => panic!("update is not parsed"),
+        }
+    }
+
+    #[test]
+    fn parse_all_tickers() {
+        let push = r#"{"ch":"market.overview","ts":1630000075000,"data":[{"symbol":"btcusdt","open":48000.0,"high":49500.5,"low":47800.0,"close":49100.0,"amount":1520.3,"vol":73000000.0,"count":90210},{"symbol":"ethusdt","open":3200.0,"high":3350.0,"low":3150.0,"close":3300.0,"amount":21000.0,"vol":68000000.0,"count":70111}]}"#;
+
+        match parse_market_message(push).unwrap() {
+            MarketMessage::Event(MarketEvent::Tickers { ts, tickers }) => {
+                assert_eq!(ts, 1630000075000);
+                assert_eq!(tickers.len(), 2);
+                assert_eq!(tickers[1].symbol, "ethusdt");
+                assert_eq!(tickers[1].bid, None);
+            }
+            _ => panic!("tickers are not parsed"),
+        }
+    }
+
+    #[test]
+    fn parse_unsub_ack() {
+        let ack = r#"{"id":"market.btcusdt.kline.1min","status":"ok","unsubbed":"market.btcusdt.kline.1min","ts":1630000075000}"#;
+
+        match parse_market_message(ack).unwrap() {
+            MarketMessage::Unsubscribed(channel) => {
+                assert_eq!(channel, "market.btcusdt.kline.1min")
+            }
+            _ => panic!("unsub ack is not parsed"),
+        }
+    }
+
+    #[test]
+    fn parse_subscription_error() {
+        let error = r#"{"status":"error","ts":1630000075000,"id":"market.btcusdt.kline.2min","err-code":"bad-request","err-msg":"invalid topic market.btcusdt.kline.2min"}"#;
+
+        match parse_market_message(error).unwrap() {
+            MarketMessage::Rejected { channel, why } => {
+                assert_eq!(channel, "market.btcusdt.kline.2min");
+                assert!(why.contains("invalid topic"));
+            }
+            _ => panic!("subscription error is not parsed"),
+        }
+
+        assert!(parse_market_message(r#"{"status":"error","err-msg":"bad"}"#).is_err());
+    }
+
+    fn tickers_event() -> MarketEvent {
+        MarketEvent::Tickers {
+            ts: 1630000075000,
+            tickers: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn events_routed_by_channel() {
+        let mut router = Router::default();
+        let btc = router.add("market.btcusdt.kline.1min");
+        let tickers = router.add(ALL_TICKERS_CHANNEL);
+        let more_tickers = router.add(ALL_TICKERS_CHANNEL);
+
+        let kline = parse_market_message(r#"{"ch":"market.btcusdt.kline.1min","ts":1630000075000,"tick":{"id":1630000060,"open":47010.1,"close":47025.0,"low":47001.1,"high":47030,"amount":1.3,"vol":61120.8,"count":331}}"#).unwrap();
+        let kline = match kline {
+            MarketMessage::Event(event) => event,
+            _ => panic!("update is not parsed"),
+        };
+
+        assert!(matches!(router.dispatch(kline), Dispatch::Delivered));
+        assert!(matches!(
+            router.dispatch(tickers_event()),
+            Dispatch::Delivered
+        ));
+
+        assert_eq!(
+            btc.try_recv().unwrap().channel(),
+            "market.btcusdt.kline.1min"
+        );
+        assert!(btc.try_recv().is_err());
+        assert!(tickers.try_recv().is_ok());
+        assert!(more_tickers.try_recv().is_ok());
+
+        match router.dispatch(MarketEvent::Kline(KlineEvent::Snapshot {
+            channel: "market.ethusdt.kline.1min".into(),
+            klines: Vec::new(),
+        })) {
+            Dispatch::Unrouted(event) => assert_eq!(event.channel(), "market.ethusdt.kline.1min"),
+            _ => panic!("event without consumer is routed"),
+        }
+    }
+
+    #[test]
+    fn channel_abandoned_when_its_receivers_are_dropped() {
+        let mut router = Router::default();
+        let tickers = router.add(ALL_TICKERS_CHANNEL);
+        let more_tickers = router.add(ALL_TICKERS_CHANNEL);
+
+        drop(tickers);
+        assert!(matches!(
+            router.dispatch(tickers_event()),
+            Dispatch::Delivered
+        ));
+
+        drop(more_tickers);
+        match router.dispatch(tickers_event()) {
+            Dispatch::Abandoned(channel) => assert_eq!(channel, ALL_TICKERS_CHANNEL),
+            _ => panic!("channel is not abandoned"),
+        }
+        assert!(matches!(
+            router.dispatch(tickers_event()),
+            Dispatch::Unrouted(_)
+        ));
+    }
+
+    #[test]
+    fn route_handle_queues_the_sub() {
+        let handle = RouteHandle {
+            router: Arc::new(Mutex::new(Router::default())),
+        };
+
+        let tickers = handle.route(ALL_TICKERS_CHANNEL);
+        let _klines = handle.route_klines("BTCUSDT", KlinePeriod::Min1);
+
+        let mut router = handle.router.lock().unwrap();
+        let pending: Vec<(&str, bool)> = router
+            .pending
+            .iter()
+            .map(|sub| (sub.channel.as_str(), sub.snapshot))
+            .collect();
+        assert_eq!(
+            pending,
+            vec![
+                (ALL_TICKERS_CHANNEL, false),
+                ("market.btcusdt.kline.1min", true)
+            ]
+        );
+
+        assert!(matches!(
+            router.dispatch(tickers_event()),
+            Dispatch::Delivered
+        ));
+        assert!(tickers.try_recv().is_ok());
+    }
+
+    #[test]
+    fn full_route_drops_newer_events() {
+        let mut router = Router::default();
+        let tickers = router.add(ALL_TICKERS_CHANNEL);
+
+        for _ in 0..ROUTE_CAPACITY + 2 {
+            router.dispatch(tickers_event());
+        }
+
+        assert_eq!(router.dropped, 2);
+        assert_eq!(tickers.try_iter().count(), ROUTE_CAPACITY);
+    }
 
     #[test]
     #[ignore]
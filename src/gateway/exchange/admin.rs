@@ -3,7 +3,7 @@
 #[cfg(test)]
 mod tests {
 
-    use crate::api::huobi::models::EventType;
+    use crate::api::huobi::models::{EventType, OrderId};
     use crate::api::huobi::websocket_account::WebsocketEvent as HuobiWebsocketEvent;
     use crate::gateway::exchange::account::Accounts;
     use crate::gateway::gateway_params::test_utils::GatewayParamsUtils;
@@ -233,7 +233,7 @@ mod tests {
         let huobi_account = Accounts::huobi(&params_account);
 
         // change order_id
-        let order_id = 292644691913752;
+        let order_id = OrderId(292644691913752);
 
         match huobi_account.cancel_order("", order_id) {
             Ok(order_canceled) => println!("canceled {:?}", order_canceled),
@@ -383,7 +383,7 @@ mod tests {
         let open_orders = huobi_account.get_open_orders(symbol);
 
         for order in open_orders.unwrap().data {
-            let order_id = order.id;
+            let order_id = OrderId(order.id);
 
             match huobi_account.cancel_order("", order_id) {
                 Ok(order_canceled) => println!("canceled {:?}", order_canceled),
@@ -532,6 +532,9 @@ mod tests {
                         EventType::Trade(_) => {}
                     };
                 }
+                HuobiWebsocketEvent::AccountUpdate(account_subscription) => {
+                    println!("Account update {:?}", account_subscription.data);
+                }
             }
 
             Ok(())
@@ -11,6 +11,7 @@ use crate::gateway::{
     gateway::ExchangeInstrumentInfo,
     Depth, GatewayParamsAccount,
 };
+use rust_decimal::prelude::ToPrimitive;
 use std::collections::HashMap;
 use std::sync::atomic::AtomicBool;
 use tracing::{debug, error, info, warn};
@@ -125,13 +126,17 @@ impl ExchangeAction for Huobi {
                 .huobi_account
                 .get_balance(&instrument.base)
                 .unwrap()
-                .balance;
+                .balance
+                .to_f64()
+                .unwrap_or(0.);
 
             let balance_quote = self
                 .huobi_account
                 .get_balance(&instrument.quote)
                 .unwrap()
-                .balance;
+                .balance
+                .to_f64()
+                .unwrap_or(0.);
 
             info!("[Gateway] Got balance for Huobi account");
 
@@ -158,7 +163,7 @@ impl ExchangeAction for Huobi {
 
                 Ok(PlatformTransaction {
                     symbol: transaction.symbol,
-                    order_id: transaction.order_id,
+                    order_id: transaction.order_id.into(),
                 })
             }
             Err(error) => {
@@ -183,7 +188,7 @@ impl ExchangeAction for Huobi {
                 debug!("[Huobi] Ok. Limit Sell order was placed");
                 Ok(PlatformTransaction {
                     symbol: transaction.symbol,
-                    order_id: transaction.order_id,
+                    order_id: transaction.order_id.into(),
                 })
             }
             Err(error) => {
@@ -198,7 +203,7 @@ impl ExchangeAction for Huobi {
         match self.huobi_account.market_buy(symbol, amount) {
             Ok(transaction) => Ok(PlatformTransaction {
                 symbol: transaction.symbol,
-                order_id: transaction.order_id,
+                order_id: transaction.order_id.into(),
             }),
             Err(error) => Err(error),
         }
@@ -208,7 +213,7 @@ impl ExchangeAction for Huobi {
         match self.huobi_account.market_sell(symbol, amount) {
             Ok(transaction) => Ok(PlatformTransaction {
                 symbol: transaction.symbol,
-                order_id: transaction.order_id,
+                order_id: transaction.order_id.into(),
             }),
             Err(error) => Err(error),
         }
@@ -225,7 +230,7 @@ impl ExchangeAction for Huobi {
         {
             Ok(transaction) => Ok(PlatformTransaction {
                 symbol: transaction.symbol,
-                order_id: transaction.order_id,
+                order_id: transaction.order_id.into(),
             }),
             Err(error) => {
                 // Do not throw error
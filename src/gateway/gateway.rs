@@ -21,6 +21,7 @@ use crate::{
 };
 use binance;
 use crossbeam::channel::{bounded, Receiver, Sender};
+use rust_decimal::prelude::ToPrimitive;
 use std::{
     collections::{HashMap, VecDeque},
     fmt,
@@ -350,7 +351,7 @@ impl Gateway {
                                 order_id: trade.order_id,
                                 custom_order_id: trade.client_order_id,
                                 symbol: trade.symbol.clone(),
-                                amount: trade.order_size.clone(),
+                                amount: trade.order_size.to_string(),
                             })) {
                                 Ok(_) => {
                                     info!(
@@ -370,6 +371,7 @@ impl Gateway {
                         EventType::Cancellation(_order) => {}
                     };
                 }
+                WebsocketEvent::AccountUpdate(_account_subscription) => {}
             }
 
             Ok(())
@@ -843,13 +845,19 @@ impl Gateway {
                     ExchangeName::Huobi => {
                         let huobi_account = self.account.huobi.as_ref().unwrap();
 
-                        let balance_base =
-                            huobi_account.get_balance(&instrument.base).unwrap().balance;
+                        let balance_base = huobi_account
+                            .get_balance(&instrument.base)
+                            .unwrap()
+                            .balance
+                            .to_f64()
+                            .unwrap_or(0.);
 
                         let balance_quote = huobi_account
                             .get_balance(&instrument.quote)
                             .unwrap()
-                            .balance;
+                            .balance
+                            .to_f64()
+                            .unwrap_or(0.);
 
                         info!("[Gateway] Got balance for Huobi account");
 
@@ -936,7 +944,7 @@ impl Gateway {
 
                         Ok(PlatformTransaction {
                             symbol: transaction.symbol,
-                            order_id: transaction.order_id,
+                            order_id: transaction.order_id.into(),
                         })
                     }
                     Err(e) => Err(e),
@@ -1073,7 +1081,7 @@ impl Gateway {
 
                         Ok(PlatformTransaction {
                             symbol: transaction.symbol,
-                            order_id: transaction.order_id,
+                            order_id: transaction.order_id.into(),
                         })
                     }
                     Err(error) => {
@@ -15,6 +15,7 @@ mod robot;
 mod server;
 mod storage;
 
+pub use api::huobi;
 pub use cli::CLI;
 pub use platform::Platform;
 pub use server::paths;
@@ -0,0 +1,67 @@
+// Read-only checks against the live Huobi API, they catch response changes that the
+// fixtures of the unit tests cannot. Nothing here places, cancels or withdraws.
+//
+//   HUOBI_API_KEY=... HUOBI_SECRET_KEY=... cargo test --features integration-tests
+//
+// Every test is skipped when the credentials are not set
+#![cfg(feature = "integration-tests")]
+
+use mx::huobi::{Account, Client};
+use std::env;
+
+const SYMBOL: &str = "btcusdt";
+
+fn credentials() -> Option<(String, String)> {
+    match (env::var("HUOBI_API_KEY"), env::var("HUOBI_SECRET_KEY")) {
+        (Ok(api_key), Ok(secret_key)) => Some((api_key, secret_key)),
+        _ => {
+            eprintln!("HUOBI_API_KEY or HUOBI_SECRET_KEY is not set, skipped");
+            None
+        }
+    }
+}
+
+fn client() -> Option<Client> {
+    credentials().map(|(api_key, secret_key)| Client::new(&api_key, &secret_key))
+}
+
+#[test]
+fn server_time() {
+    if let Some(client) = client() {
+        client.ping().unwrap();
+    }
+}
+
+#[test]
+fn symbols() {
+    if let Some(client) = client() {
+        let symbol = client.get_symbol(SYMBOL).unwrap();
+
+        assert_eq!(symbol.base, "btc");
+        assert_eq!(symbol.quote, "usdt");
+        assert!(symbol.state.is_tradeable());
+    }
+}
+
+#[test]
+fn ticker() {
+    if let Some(client) = client() {
+        let ticker = client.get_ticker(SYMBOL).unwrap();
+
+        assert!(ticker.bid[0] > 0.);
+        assert!(ticker.bid[0] <= ticker.ask[0]);
+        assert!(client.get_last_price(SYMBOL).unwrap() > 0.);
+    }
+}
+
+#[test]
+fn accounts() {
+    if let Some((api_key, secret_key)) = credentials() {
+        // The account id is not used to list the accounts
+        let account = Account::new("0", Some(api_key), Some(secret_key));
+
+        let accounts = account.get_accounts().unwrap();
+
+        assert!(!accounts.data.is_empty());
+    }
+}